@@ -0,0 +1,30 @@
+//! Generates random packets for every protocol state and direction and checks that they survive an
+//! encode-decode-encode round trip. Run with `cargo fuzz run packet_round_trip`.
+
+#![no_main]
+
+use arbitrary::Unstructured;
+use libfuzzer_sys::fuzz_target;
+use raft::net::{fuzzing::check_round_trip, protocol::*};
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let checks: &[fn(&mut Unstructured<'_>) -> arbitrary::Result<()>] = &[
+        check_round_trip::<cb_handshake::Packet>,
+        check_round_trip::<sb_handshake::Packet>,
+        check_round_trip::<cb_status::Packet>,
+        check_round_trip::<sb_status::Packet>,
+        check_round_trip::<cb_login::Packet>,
+        check_round_trip::<sb_login::Packet>,
+        check_round_trip::<cb_play::Packet>,
+        check_round_trip::<sb_play::Packet>,
+        check_round_trip::<cb_config::Packet>,
+        check_round_trip::<sb_config::Packet>,
+    ];
+
+    let Ok(check) = u.choose(checks) else {
+        return;
+    };
+
+    let _ = check(&mut u);
+});
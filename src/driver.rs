@@ -1,4 +1,4 @@
-use crate::net::driver::run_server;
+use raft::net::driver::run_server;
 
 pub async fn main_inner() -> anyhow::Result<()> {
     // Initialize the logger
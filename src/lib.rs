@@ -0,0 +1,4 @@
+#![allow(dead_code)] // TODO: Remove
+
+pub mod net;
+mod util;
@@ -1,8 +1,4 @@
-#![allow(dead_code)] // TODO: Remove
-
 mod driver;
-mod net;
-mod util;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
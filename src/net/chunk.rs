@@ -0,0 +1,655 @@
+//! The chunk section format carried by the data array of the Chunk Data packet, where each
+//! section's block states and biomes are stored in paletted containers of packed longs.
+
+use bytes::{BufMut, Bytes};
+
+use crate::util::{bytes_integration::Snip, proto::byte_stream::ByteCursor};
+
+use super::primitives::{BitSet, Codec, Position, SizedCodec, VarInt, VarUint};
+
+// === Coordinates === //
+
+/// The width of a chunk column and the size of each of its sections along every axis.
+pub const CHUNK_SIZE: i32 = 16;
+
+/// The position of a block in the world. Unlike [`Position`], this is not limited to the range
+/// which can be sent over the network.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPos {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// The column containing this block. This rounds towards negative infinity so, for example,
+    /// the block at `x = -1` is in the chunk at `x = -1`.
+    pub fn chunk(self) -> ChunkPos {
+        ChunkPos::new(self.x >> 4, self.z >> 4)
+    }
+
+    /// The index of the section containing this block in a column whose lowest section starts at
+    /// `min_y`.
+    pub fn section_index(self, min_y: i32) -> i32 {
+        (self.y - min_y) >> 4
+    }
+
+    /// The index of this block in its [`ChunkSection`]'s block states.
+    pub fn index_in_section(self) -> usize {
+        let (x, y, z) = (self.x & 15, self.y & 15, self.z & 15);
+        ((y * CHUNK_SIZE + z) * CHUNK_SIZE + x) as usize
+    }
+}
+
+impl From<Position> for BlockPos {
+    fn from(pos: Position) -> Self {
+        Self::new(pos.x, pos.y, pos.z)
+    }
+}
+
+impl From<BlockPos> for Position {
+    fn from(pos: BlockPos) -> Self {
+        Self::new(pos.x, pos.y, pos.z)
+    }
+}
+
+/// The position of a chunk column, measured in chunks rather than blocks.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// The block at the column's lowest X and Z coordinates and at `y = 0`.
+    pub fn origin_block(self) -> BlockPos {
+        BlockPos::new(self.x * CHUNK_SIZE, 0, self.z * CHUNK_SIZE)
+    }
+}
+
+// === Paletted Containers === //
+
+/// The shape of a paletted container: how many entries it holds and which bits-per-entry values
+/// select which palette.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PaletteKind {
+    pub entry_count: usize,
+    /// Indirect palettes with fewer bits per entry than this are widened to this size.
+    pub min_indirect_bits: u8,
+    pub max_indirect_bits: u8,
+    /// The width of the global registry's ids, which is the only width a direct palette can have.
+    pub direct_bits: u8,
+}
+
+impl PaletteKind {
+    /// The 16x16x16 block states of a section. The direct width matches the size of the vanilla
+    /// block state registry.
+    pub const BLOCK_STATES: Self = Self {
+        entry_count: 4096,
+        min_indirect_bits: 4,
+        max_indirect_bits: 8,
+        direct_bits: 15,
+    };
+
+    /// The 4x4x4 biomes of a section. The direct width matches the size of the vanilla biome
+    /// registry.
+    pub const BIOMES: Self = Self {
+        entry_count: 64,
+        min_indirect_bits: 1,
+        max_indirect_bits: 3,
+        direct_bits: 6,
+    };
+}
+
+#[derive(Debug, Clone)]
+enum Palette {
+    Single(u16),
+    Indirect(Vec<u16>),
+    Direct,
+}
+
+impl Palette {
+    /// Picks the smallest palette able to represent `entries` along with the number of bits per
+    /// entry used to pack them.
+    fn choose(kind: PaletteKind, entries: &[u16]) -> (u8, Self) {
+        let max_indirect_len = 1 << kind.max_indirect_bits;
+        let mut palette = Vec::new();
+
+        for &entry in entries {
+            if palette.contains(&entry) {
+                continue;
+            }
+
+            if palette.len() == max_indirect_len {
+                return (kind.direct_bits, Self::Direct);
+            }
+
+            palette.push(entry);
+        }
+
+        match palette.len() {
+            0 | 1 => (0, Self::Single(palette.first().copied().unwrap_or(0))),
+            len => {
+                let bits = (usize::BITS - (len - 1).leading_zeros()) as u8;
+                (bits.max(kind.min_indirect_bits), Self::Indirect(palette))
+            }
+        }
+    }
+
+    fn index_of(&self, entry: u16) -> u64 {
+        match self {
+            Self::Single(_) => 0,
+            Self::Indirect(palette) => palette.iter().position(|&v| v == entry).unwrap() as u64,
+            Self::Direct => u64::from(entry),
+        }
+    }
+}
+
+fn long_count(kind: PaletteKind, bits: u8) -> usize {
+    if bits == 0 {
+        0
+    } else {
+        // Entries are never split across longs so the top bits of each long may go unused.
+        kind.entry_count.div_ceil(64 / bits as usize)
+    }
+}
+
+fn decode_id(src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<u16> {
+    let id = VarInt::decode((), src, cursor)?.0;
+    u16::try_from(id).map_err(|_| {
+        anyhow::anyhow!(
+            "Palette entry {id} is not a valid registry id (location: {}).",
+            cursor.format_location(),
+        )
+    })
+}
+
+fn decode_container(
+    kind: PaletteKind,
+    src: &impl Snip,
+    cursor: &mut ByteCursor,
+) -> anyhow::Result<Vec<u16>> {
+    let bits = u8::decode((), src, cursor)?;
+
+    let (bits, palette) = match bits {
+        0 => (0, Palette::Single(decode_id(src, cursor)?)),
+        bits if bits <= kind.max_indirect_bits => {
+            let bits = bits.max(kind.min_indirect_bits);
+            let len = VarInt::decode((), src, cursor)?.0;
+
+            anyhow::ensure!(
+                (1..=1 << bits).contains(&len),
+                "Indirect palette with {bits} bit(s) per entry cannot have {len} entries \
+                 (location: {}).",
+                cursor.format_location(),
+            );
+
+            let mut palette = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                palette.push(decode_id(src, cursor)?);
+            }
+
+            (bits, Palette::Indirect(palette))
+        }
+        bits if bits == kind.direct_bits => (bits, Palette::Direct),
+        bits => anyhow::bail!(
+            "Paletted container cannot have {bits} bit(s) per entry: indirect palettes use at most \
+             {} and direct palettes use exactly {} (location: {}).",
+            kind.max_indirect_bits,
+            kind.direct_bits,
+            cursor.format_location(),
+        ),
+    };
+
+    let len = VarInt::decode((), src, cursor)?.0;
+    let expected_len = long_count(kind, bits);
+    anyhow::ensure!(
+        usize::try_from(len).is_ok_and(|len| len == expected_len),
+        "Paletted container with {bits} bit(s) per entry must have {expected_len} long(s) of data \
+         but has {len} (location: {}).",
+        cursor.format_location(),
+    );
+
+    let mut longs = Vec::with_capacity(expected_len);
+    for _ in 0..expected_len {
+        longs.push(u64::decode((), src, cursor)?);
+    }
+
+    if let Palette::Single(value) = palette {
+        return Ok(vec![value; kind.entry_count]);
+    }
+
+    let per_long = 64 / bits as usize;
+    let mask = (1u64 << bits) - 1;
+    let mut entries = Vec::with_capacity(kind.entry_count);
+
+    for i in 0..kind.entry_count {
+        let index = (longs[i / per_long] >> ((i % per_long) * bits as usize)) & mask;
+
+        entries.push(match &palette {
+            Palette::Indirect(palette) => match palette.get(index as usize) {
+                Some(&entry) => entry,
+                None => anyhow::bail!(
+                    "Paletted container entry {i} refers to index {index} of a palette with {} \
+                     entries (location: {}).",
+                    palette.len(),
+                    cursor.format_location(),
+                ),
+            },
+            // `bits` is the direct width, which is small enough for every index to fit.
+            _ => index as u16,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn encode_container(kind: PaletteKind, entries: &[u16], cursor: &mut impl BufMut) {
+    let (bits, palette) = Palette::choose(kind, entries);
+    bits.encode((), cursor);
+
+    match &palette {
+        Palette::Single(value) => VarInt(i32::from(*value)).encode((), cursor),
+        Palette::Indirect(palette) => {
+            VarInt(palette.len() as i32).encode((), cursor);
+            for &entry in palette {
+                VarInt(i32::from(entry)).encode((), cursor);
+            }
+        }
+        Palette::Direct => {}
+    }
+
+    VarInt(long_count(kind, bits) as i32).encode((), cursor);
+
+    if bits == 0 {
+        return;
+    }
+
+    let per_long = 64 / bits as usize;
+    for chunk in entries.chunks(per_long) {
+        let mut long = 0u64;
+        for (i, &entry) in chunk.iter().enumerate() {
+            long |= palette.index_of(entry) << (i * bits as usize);
+        }
+        long.encode((), cursor);
+    }
+}
+
+fn container_size(kind: PaletteKind, entries: &[u16]) -> usize {
+    let (bits, palette) = Palette::choose(kind, entries);
+    let long_count = long_count(kind, bits);
+
+    let palette_size = match &palette {
+        Palette::Single(value) => VarInt(i32::from(*value)).size(()),
+        Palette::Indirect(palette) => {
+            VarInt(palette.len() as i32).size(())
+                + palette
+                    .iter()
+                    .map(|&entry| VarInt(i32::from(entry)).size(()))
+                    .sum::<usize>()
+        }
+        Palette::Direct => 0,
+    };
+
+    1 + palette_size + VarInt(long_count as i32).size(()) + long_count * 8
+}
+
+fn validate_container(kind: PaletteKind, entries: &[u16], what: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        entries.len() == kind.entry_count,
+        "Chunk sections must have {} {what} but this one has {}.",
+        kind.entry_count,
+        entries.len(),
+    );
+
+    if let Some(entry) = entries.iter().find(|&&v| v >> kind.direct_bits != 0) {
+        anyhow::bail!(
+            "{what} id {entry} does not fit in the {} bit(s) of the global palette.",
+            kind.direct_bits,
+        );
+    }
+
+    Ok(())
+}
+
+// === Sections === //
+
+/// A 16x16x16 section of a chunk column. Entries are stored in YZX order, which is to say that the
+/// index of a block is `(y * 16 + z) * 16 + x`, and biomes follow the same order over a 4x4x4 grid.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ChunkSection {
+    /// The number of non-air blocks in the section, which the client uses to skip empty sections
+    /// when rendering.
+    pub block_count: i16,
+    pub block_states: Vec<u16>,
+    pub biomes: Vec<u16>,
+}
+
+impl Codec<()> for ChunkSection {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Ok(Self {
+            block_count: i16::decode((), src, cursor)?,
+            block_states: decode_container(PaletteKind::BLOCK_STATES, src, cursor)?,
+            biomes: decode_container(PaletteKind::BIOMES, src, cursor)?,
+        })
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.block_count.encode((), cursor);
+        encode_container(PaletteKind::BLOCK_STATES, &self.block_states, cursor);
+        encode_container(PaletteKind::BIOMES, &self.biomes, cursor);
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        validate_container(
+            PaletteKind::BLOCK_STATES,
+            &self.block_states,
+            "block states",
+        )?;
+        validate_container(PaletteKind::BIOMES, &self.biomes, "biomes")?;
+        Ok(())
+    }
+}
+
+impl SizedCodec<()> for ChunkSection {
+    fn size(&self, _args: ()) -> usize {
+        2 + container_size(PaletteKind::BLOCK_STATES, &self.block_states)
+            + container_size(PaletteKind::BIOMES, &self.biomes)
+    }
+}
+
+// === Columns === //
+
+/// The sections of a chunk column from the bottom of the world up. The number of sections isn't
+/// encoded since it is determined by the height of the dimension.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ChunkData {
+    pub sections: Vec<ChunkSection>,
+}
+
+/// Decodes a column with the given number of sections.
+impl Codec<u32> for ChunkData {
+    fn decode(
+        section_count: u32,
+        src: &impl Snip,
+        cursor: &mut ByteCursor,
+    ) -> anyhow::Result<Self> {
+        let mut sections = Vec::new();
+        for _ in 0..section_count {
+            sections.push(ChunkSection::decode((), src, cursor)?);
+        }
+
+        Ok(Self { sections })
+    }
+
+    fn encode(&self, _section_count: u32, cursor: &mut impl BufMut) {
+        for section in &self.sections {
+            section.encode((), cursor);
+        }
+    }
+
+    fn validate(&self, section_count: u32) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.sections.len() == section_count as usize,
+            "Chunk column must have {section_count} section(s) but has {}.",
+            self.sections.len(),
+        );
+
+        for section in &self.sections {
+            section.validate(())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SizedCodec<u32> for ChunkData {
+    fn size(&self, _section_count: u32) -> usize {
+        self.sections.iter().map(|section| section.size(())).sum()
+    }
+}
+
+// === Light === //
+
+/// The size of a section's light array, which packs one 4-bit light level per block.
+pub const LIGHT_ARRAY_LEN: usize = 2048;
+
+/// The light levels of a chunk column. Bit `i` of each mask refers to the `i - 1`th section of the
+/// column since light is also tracked for the sections directly below and above the world.
+///
+/// Sections in neither the present nor the empty mask of a given light kind are left unchanged by
+/// the client.
+#[derive(Debug, Clone, Default)]
+pub struct LightData {
+    pub sky_light_mask: BitSet,
+    pub block_light_mask: BitSet,
+    pub empty_sky_light_mask: BitSet,
+    pub empty_block_light_mask: BitSet,
+    /// One array for every set bit of `sky_light_mask`, in ascending order of section.
+    pub sky_light_arrays: Vec<Bytes>,
+    /// One array for every set bit of `block_light_mask`, in ascending order of section.
+    pub block_light_arrays: Vec<Bytes>,
+}
+
+impl Codec<()> for LightData {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let sky_light_mask = BitSet::decode((), src, cursor)?;
+        let block_light_mask = BitSet::decode((), src, cursor)?;
+        let empty_sky_light_mask = BitSet::decode((), src, cursor)?;
+        let empty_block_light_mask = BitSet::decode((), src, cursor)?;
+        let sky_light_arrays = decode_light_arrays(&sky_light_mask, "sky", src, cursor)?;
+        let block_light_arrays = decode_light_arrays(&block_light_mask, "block", src, cursor)?;
+
+        Ok(Self {
+            sky_light_mask,
+            block_light_mask,
+            empty_sky_light_mask,
+            empty_block_light_mask,
+            sky_light_arrays,
+            block_light_arrays,
+        })
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.sky_light_mask.encode((), cursor);
+        self.block_light_mask.encode((), cursor);
+        self.empty_sky_light_mask.encode((), cursor);
+        self.empty_block_light_mask.encode((), cursor);
+        encode_light_arrays(&self.sky_light_arrays, cursor);
+        encode_light_arrays(&self.block_light_arrays, cursor);
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        validate_light_arrays(&self.sky_light_mask, &self.sky_light_arrays, "sky")?;
+        validate_light_arrays(&self.block_light_mask, &self.block_light_arrays, "block")?;
+        Ok(())
+    }
+}
+
+impl SizedCodec<()> for LightData {
+    fn size(&self, _args: ()) -> usize {
+        self.sky_light_mask.size(())
+            + self.block_light_mask.size(())
+            + self.empty_sky_light_mask.size(())
+            + self.empty_block_light_mask.size(())
+            + light_arrays_size(&self.sky_light_arrays)
+            + light_arrays_size(&self.block_light_arrays)
+    }
+}
+
+fn decode_light_arrays(
+    mask: &BitSet,
+    kind: &str,
+    src: &impl Snip,
+    cursor: &mut ByteCursor,
+) -> anyhow::Result<Vec<Bytes>> {
+    let count = VarUint::decode((), src, cursor)?.0 as usize;
+    anyhow::ensure!(
+        count == mask.count_ones(),
+        "Expected {} {kind} light array(s) to match the mask but found {count} (location: {}).",
+        mask.count_ones(),
+        cursor.format_location(),
+    );
+
+    // The mask has already been read in full so `count` is bounded by the packet's size.
+    let mut arrays = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = VarUint::decode((), src, cursor)?.0 as usize;
+        anyhow::ensure!(
+            len == LIGHT_ARRAY_LEN,
+            "Expected {kind} light array to be {LIGHT_ARRAY_LEN} byte(s) long but it was {len} \
+             byte(s) long (location: {}).",
+            cursor.format_location(),
+        );
+
+        let Some(data) = cursor.read_slice(len) else {
+            anyhow::bail!(
+                "Expected {len} byte(s) of {kind} light data; found {} (location: {}).",
+                cursor.len(),
+                cursor.format_location(),
+            );
+        };
+
+        arrays.push(src.freeze_range(data));
+    }
+
+    Ok(arrays)
+}
+
+fn encode_light_arrays(arrays: &[Bytes], cursor: &mut impl BufMut) {
+    VarUint(arrays.len() as u32).encode((), cursor);
+
+    for array in arrays {
+        VarUint(array.len() as u32).encode((), cursor);
+        cursor.put_slice(array);
+    }
+}
+
+fn light_arrays_size(arrays: &[Bytes]) -> usize {
+    VarUint(arrays.len() as u32).size(())
+        + arrays
+            .iter()
+            .map(|array| VarUint(array.len() as u32).size(()) + array.len())
+            .sum::<usize>()
+}
+
+fn validate_light_arrays(mask: &BitSet, arrays: &[Bytes], kind: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        arrays.len() == mask.count_ones(),
+        "Light data has {} {kind} light array(s) but its mask has {} bit(s) set.",
+        arrays.len(),
+        mask.count_ones(),
+    );
+
+    if let Some(array) = arrays.iter().find(|array| array.len() != LIGHT_ARRAY_LEN) {
+        anyhow::bail!(
+            "Light arrays must be {LIGHT_ARRAY_LEN} byte(s) long but a {kind} light array is {} \
+             byte(s) long.",
+            array.len(),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_single_valued_sections() {
+        let section = [
+            0x10, 0x00, // 4096 non-air blocks
+            0x00, 0x01, 0x00, // block states: 0 bits per entry, all stone, no longs
+            0x00, 0x05, 0x00, // biomes: 0 bits per entry, all biome 5, no longs
+        ];
+        let bytes = Bytes::copy_from_slice(&section);
+
+        let decoded = ChunkSection::decode_exact((), &bytes).unwrap();
+        assert_eq!(decoded.block_count, 4096);
+        assert_eq!(decoded.block_states, vec![1; 4096]);
+        assert_eq!(decoded.biomes, vec![5; 64]);
+
+        let mut encoded = Vec::new();
+        decoded.encode((), &mut encoded);
+        assert_eq!(encoded, section);
+        assert_eq!(decoded.size(()), section.len());
+
+        // Single-valued containers have no data so a non-zero long count is malformed.
+        let mut padded = section.to_vec();
+        padded[4] = 0x01;
+        padded.splice(5..5, [0; 8]);
+        let err = ChunkSection::decode_exact((), &Bytes::from(padded)).unwrap_err();
+        assert!(err.to_string().contains("must have 0 long(s)"), "{err:#}");
+    }
+
+    #[test]
+    fn decodes_one_present_sky_light_section() {
+        let light = (0..LIGHT_ARRAY_LEN).map(|i| i as u8).collect::<Vec<_>>();
+
+        // Section 1 has sky light, section 2 is known to be dark, and there's no block light.
+        let mut payload = vec![
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, // sky light mask
+            0x00, // block light mask
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, // empty sky light mask
+            0x00, // empty block light mask
+            0x01, 0x80, 0x10, // one sky light array of 2048 bytes
+        ];
+        payload.extend_from_slice(&light);
+        payload.push(0x00); // no block light arrays
+
+        let decoded = LightData::decode_exact((), &Bytes::from(payload.clone())).unwrap();
+        assert_eq!(decoded.sky_light_mask.words(), [0b10]);
+        assert_eq!(decoded.empty_sky_light_mask.words(), [0b100]);
+        assert_eq!(decoded.sky_light_arrays, [Bytes::from(light)]);
+        assert!(decoded.block_light_arrays.is_empty());
+
+        let mut encoded = Vec::new();
+        decoded.encode((), &mut encoded);
+        assert_eq!(encoded, payload);
+        assert_eq!(decoded.size(()), payload.len());
+
+        // The array count must match the mask's popcount.
+        payload[20] = 0x02;
+        let err = LightData::decode_exact((), &Bytes::from(payload)).unwrap_err();
+        assert!(
+            err.to_string().contains("Expected 1 sky light array(s)"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn block_positions_round_down_to_their_chunk() {
+        for (block_x, chunk_x) in [(0, 0), (15, 0), (16, 1), (-1, -1), (-16, -1), (-17, -2)] {
+            let block = BlockPos::new(block_x, 64, -block_x);
+            assert_eq!(block.chunk(), ChunkPos::new(chunk_x, (-block_x) >> 4));
+        }
+
+        assert_eq!(
+            ChunkPos::new(-1, 2).origin_block(),
+            BlockPos::new(-16, 0, 32)
+        );
+        for chunk in [ChunkPos::new(0, 0), ChunkPos::new(-3, 7)] {
+            assert_eq!(chunk.origin_block().chunk(), chunk);
+        }
+
+        // Sections are counted from the bottom of the world, which may be below zero.
+        assert_eq!(BlockPos::new(0, -64, 0).section_index(-64), 0);
+        assert_eq!(BlockPos::new(0, -1, 0).section_index(-64), 3);
+        assert_eq!(BlockPos::new(0, 0, 0).section_index(-64), 4);
+
+        assert_eq!(BlockPos::new(-1, -1, -1).index_in_section(), 4095);
+        assert_eq!(
+            BlockPos::new(17, 2, 3).index_in_section(),
+            (2 * 16 + 3) * 16 + 1
+        );
+    }
+}
@@ -1,24 +1,189 @@
+use std::{
+    collections::HashMap,
+    future::{self, Future},
+    io,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use futures::future::select_all;
 use smallvec::SmallVec;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::{
+    net::{TcpListener, TcpSocket, TcpStream},
+    sync::Semaphore,
+    task::{JoinError, JoinSet},
+};
+use tokio_util::sync::CancellationToken;
 
 use crate::net::{
-    primitives::{ChatComponent, Codec, JsonValue, NetString, RootChatComponent},
-    protocol::{cb_login, cb_status, sb_handshake, sb_login, sb_status},
+    primitives::{ChatComponent, Codec, JsonValue, RootChatComponent},
+    protocol::{
+        cb_play, cb_status, sb_handshake, sb_login, sb_play, sb_status, state, structs, PeerState,
+        ProtocolVersion, PROTOCOL_VERSION, VERSION_NAME,
+    },
 };
 
-use super::transport::{RawPeerStream, HARD_MAX_PACKET_LEN_INCL};
+use super::encryption::{EncryptionChallenge, ServerKeyPair};
+use super::transport::{
+    Frame, LegacyPingResponse, RateLimits, RawPeerStream, HARD_MAX_PACKET_LEN_INCL,
+};
+
+/// How long peers are given to disconnect once the server starts shutting down before their tasks
+/// are aborted.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a peer which connected while the server was full may stay connected. Such peers can
+/// only check the server's status or be told that it's full, so this keeps them from piling up
+/// tasks beyond the connection limit.
+const FULL_PEER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the server waits before accepting connections again after a transient failure, such as
+/// running out of file descriptors, so that it doesn't spin while the condition persists.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The address the server listens on when none are specified.
+pub const DEFAULT_LISTEN_ADDR: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 8080));
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// The addresses to accept connections on. Connections from every address are handled the same
+    /// way.
+    pub listen_addrs: Vec<SocketAddr>,
+
+    /// The number of peers which can be connected at once. Peers connecting while the server is
+    /// full are still shown the server's status but are kicked if they try to log in and are
+    /// disconnected after a short timeout regardless.
+    pub max_connections: usize,
+
+    /// The number of pending connections the OS will queue for each listener before refusing new
+    /// ones.
+    pub backlog: u32,
+}
 
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addrs: vec![DEFAULT_LISTEN_ADDR],
+            max_connections: 1000,
+            backlog: 1024,
+        }
+    }
+}
+
+/// Runs the server with the default configuration until it receives Ctrl+C.
 pub async fn run_server() -> anyhow::Result<()> {
-    let listener = TcpListener::bind("0.0.0.0:8080").await?;
+    run_server_with(&ServerConfig::default()).await
+}
+
+/// Runs the server until it receives Ctrl+C.
+pub async fn run_server_with(config: &ServerConfig) -> anyhow::Result<()> {
+    run_server_until(config, async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for Ctrl+C; the server must be killed to stop it: {err}");
+            future::pending().await
+        }
+    })
+    .await
+}
+
+/// Runs the server until `shutdown` resolves, at which point it stops accepting connections and
+/// kicks every connected peer.
+///
+/// Connections which fail to be accepted are skipped, but if a listener breaks, the server shuts
+/// down in the same way and then returns the error.
+pub async fn run_server_until(
+    config: &ServerConfig,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !config.listen_addrs.is_empty(),
+        "The server must listen on at least one address."
+    );
+
+    let mut listeners = Vec::with_capacity(config.listen_addrs.len());
+    for &addr in &config.listen_addrs {
+        let listener = bind_listener(addr, config.backlog)
+            .with_context(|| format!("failed to listen on {addr}"))?;
+
+        log::info!("Server is listening on {}.", listener.local_addr()?);
+        listeners.push(listener);
+    }
+
+    let connection_permits = Arc::new(Semaphore::new(config.max_connections));
+    let keys = Arc::new(ServerKeyPair::generate()?);
+    let closing = CancellationToken::new();
+    let mut peers = JoinSet::new();
+
+    tokio::pin!(shutdown);
 
-    log::info!("Server is listening.");
+    let mut accept_error = None;
 
     loop {
-        let (peer_stream, remote_ip) = listener.accept().await?;
+        // `accept` is cancel-safe so the futures for listeners which didn't accept a connection
+        // can be dropped and recreated on the next iteration.
+        let accept_any = select_all(listeners.iter().map(|listener| Box::pin(listener.accept())));
+
+        let accepted = tokio::select! {
+            (accepted, _, _) = accept_any => accepted,
+            () = &mut shutdown => break,
+            // Reap finished peers so that the set doesn't grow for the lifetime of the server.
+            Some(result) = peers.join_next(), if !peers.is_empty() => {
+                log_peer_task_exit(result);
+                continue;
+            }
+        };
+
+        let (peer_stream, remote_ip) = match accepted {
+            Ok(accepted) => accepted,
+            Err(err) if is_transient_accept_error(&err) => {
+                log::warn!(
+                    "Failed to accept a connection: {err}; retrying in {ACCEPT_ERROR_BACKOFF:?}."
+                );
+
+                tokio::select! {
+                    () = tokio::time::sleep(ACCEPT_ERROR_BACKOFF) => continue,
+                    () = &mut shutdown => break,
+                }
+            }
+            Err(err) => {
+                // Peers are still disconnected gracefully before the error is reported.
+                log::error!("Failed to accept a connection: {err}; shutting down.");
+                accept_error = Some(err);
+                break;
+            }
+        };
+
         log::info!("Got connection from {remote_ip:?}");
 
-        tokio::spawn(async move {
-            match run_peer_listener(peer_stream).await {
+        // The permit is held by the peer's task for as long as it's connected.
+        let permit = connection_permits.clone().try_acquire_owned().ok();
+        if permit.is_none() {
+            log::info!("Server is full; {remote_ip:?} will not be allowed to log in.");
+        }
+
+        let keys = keys.clone();
+        let closing = closing.clone();
+        peers.spawn(async move {
+            let is_full = permit.is_none();
+            let listener = run_peer_listener(peer_stream, &keys, &closing, is_full);
+            let result = if is_full {
+                tokio::time::timeout(FULL_PEER_TIMEOUT, listener)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(anyhow::anyhow!(
+                            "peer connected while the server was full and stayed longer than \
+                             {FULL_PEER_TIMEOUT:?}"
+                        ))
+                    })
+            } else {
+                listener.await
+            };
+            drop(permit);
+
+            match result {
                 Ok(true) => {
                     log::info!("Closed connection to {remote_ip:?}")
                 }
@@ -31,52 +196,334 @@ pub async fn run_server() -> anyhow::Result<()> {
             }
         });
     }
+
+    log::info!(
+        "Server is shutting down; disconnecting {} peer(s).",
+        peers.len()
+    );
+    closing.cancel();
+
+    let drained = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+        while let Some(result) = peers.join_next().await {
+            log_peer_task_exit(result);
+        }
+    })
+    .await;
+
+    if drained.is_err() {
+        log::warn!(
+            "{} peer(s) failed to disconnect within {SHUTDOWN_TIMEOUT:?}; aborting them.",
+            peers.len(),
+        );
+        peers.shutdown().await;
+    }
+
+    match accept_error {
+        Some(err) => Err(anyhow::Error::new(err).context("failed to accept a connection")),
+        None => Ok(()),
+    }
+}
+
+/// Whether a failure to accept a connection only affects that connection or is caused by a
+/// condition which may clear up on its own, in which case the server should keep listening.
+fn is_transient_accept_error(err: &io::Error) -> bool {
+    // Running out of file descriptors or memory, which `io::ErrorKind` can't express on stable.
+    #[cfg(unix)]
+    const RESOURCE_EXHAUSTION_ERRNOS: [i32; 3] = [
+        12, // ENOMEM
+        23, // ENFILE
+        24, // EMFILE
+    ];
+
+    #[cfg(unix)]
+    if err
+        .raw_os_error()
+        .is_some_and(|errno| RESOURCE_EXHAUSTION_ERRNOS.contains(&errno))
+    {
+        return true;
+    }
+
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::OutOfMemory
+    )
+}
+
+fn bind_listener(addr: SocketAddr, backlog: u32) -> anyhow::Result<TcpListener> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+
+    // Matches `TcpListener::bind`, which lets the server restart without waiting for connections
+    // from its previous run to time out.
+    #[cfg(unix)]
+    socket.set_reuseaddr(true)?;
+
+    socket.bind(addr)?;
+    Ok(socket.listen(backlog)?)
+}
+
+fn log_peer_task_exit(result: Result<(), JoinError>) {
+    if let Err(err) = result {
+        log::error!("Peer task failed to run to completion: {err}");
+    }
+}
+
+#[derive(Debug)]
+struct PeerStateTracker {
+    state: PeerState,
+    entered_at: Instant,
+    durations: HashMap<PeerState, Duration>,
+}
+
+impl PeerStateTracker {
+    fn new(state: PeerState) -> Self {
+        Self {
+            state,
+            entered_at: Instant::now(),
+            durations: HashMap::new(),
+        }
+    }
+
+    fn state(&self) -> PeerState {
+        self.state
+    }
+
+    fn transition(&mut self, state: PeerState) {
+        self.flush();
+        log::trace!("Transitioning peer from {:?} to {state:?}", self.state);
+        self.state = state;
+    }
+
+    fn flush(&mut self) {
+        let now = Instant::now();
+        *self.durations.entry(self.state).or_default() += now - self.entered_at;
+        self.entered_at = now;
+    }
+
+    fn durations(&mut self) -> &HashMap<PeerState, Duration> {
+        self.flush();
+        &self.durations
+    }
+}
+
+/// The most a peer may send per second before we assume it's flooding us and disconnect it.
+const PEER_RATE_LIMITS: RateLimits = RateLimits {
+    max_packets_per_sec: 500,
+    max_bytes_per_sec: 4 * 1024 * 1024,
+};
+
+/// Packet bodies at least this many bytes long are compressed once a peer has logged in, or `None`
+/// to leave the connection uncompressed. This matches the vanilla default.
+const COMPRESSION_THRESHOLD: Option<u32> = Some(256);
+
+/// How often a keep-alive is sent to a peer in the play state.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long a peer has to echo a keep-alive before it is disconnected.
+const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+struct KeepAliveTracker {
+    next_send: Instant,
+    pending: Option<(i64, Instant)>,
 }
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-enum PeerState {
-    Handshake,
-    Status,
-    Login,
-    Play,
+impl KeepAliveTracker {
+    fn new() -> Self {
+        Self {
+            next_send: Instant::now() + KEEP_ALIVE_INTERVAL,
+            pending: None,
+        }
+    }
+
+    /// The instant at which `poll` should next be called.
+    fn deadline(&self) -> Instant {
+        match self.pending {
+            Some((_, sent_at)) => sent_at + KEEP_ALIVE_TIMEOUT,
+            None => self.next_send,
+        }
+    }
+
+    /// Returns the id of the keep-alive to send if one is due, or an error if the peer failed to
+    /// echo the previous one in time. Only one keep-alive is outstanding at a time.
+    fn poll(&mut self, now: Instant) -> anyhow::Result<Option<i64>> {
+        if let Some((id, sent_at)) = self.pending {
+            anyhow::ensure!(
+                now < sent_at + KEEP_ALIVE_TIMEOUT,
+                "Peer did not respond to keep-alive {id} within {KEEP_ALIVE_TIMEOUT:?}."
+            );
+
+            return Ok(None);
+        }
+
+        if now < self.next_send {
+            return Ok(None);
+        }
+
+        let id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis() as i64);
+
+        self.pending = Some((id, now));
+        self.next_send = now + KEEP_ALIVE_INTERVAL;
+
+        Ok(Some(id))
+    }
+
+    fn acknowledge(&mut self, id: i64) {
+        match self.pending {
+            Some((pending_id, _)) if pending_id == id => self.pending = None,
+            _ => log::warn!(
+                "Received unexpected keep-alive {id}; expected {:?}",
+                self.pending
+            ),
+        }
+    }
 }
 
-async fn run_peer_listener(peer_stream: TcpStream) -> anyhow::Result<bool> {
+async fn run_peer_listener(
+    peer_stream: TcpStream,
+    keys: &ServerKeyPair,
+    closing: &CancellationToken,
+    is_full: bool,
+) -> anyhow::Result<bool> {
     let peer_addr = peer_stream.peer_addr()?;
-    let mut peer_stream = RawPeerStream::new(peer_stream, HARD_MAX_PACKET_LEN_INCL);
-    let mut state = PeerState::Handshake;
+    let mut peer_stream =
+        RawPeerStream::with_limits(peer_stream, HARD_MAX_PACKET_LEN_INCL, PEER_RATE_LIMITS);
+    let mut tracker = PeerStateTracker::new(PeerState::Handshake);
+
+    let result = tokio::select! {
+        result = drive_peer(peer_addr, keys, is_full, &mut peer_stream, &mut tracker) => result,
+        () = closing.cancelled() => {
+            peer_stream
+                .kick(
+                    tracker.state(),
+                    JsonValue(ChatComponent::text("Server closed").into()),
+                )
+                .await
+                .map(|()| true)
+        }
+    };
+
+    log::info!(
+        "Connection metrics for {peer_addr:?}: {}; time spent in each state: {:?}",
+        peer_stream.metrics().snapshot(),
+        tracker.durations(),
+    );
+
+    result
+}
 
-    while let Some(packet) = peer_stream.read().await {
-        let packet = packet?;
-        log::info!("Received packet: {packet:#?}");
+async fn drive_peer(
+    peer_addr: SocketAddr,
+    keys: &ServerKeyPair,
+    is_full: bool,
+    peer_stream: &mut RawPeerStream,
+    tracker: &mut PeerStateTracker,
+) -> anyhow::Result<bool> {
+    let mut encryption = None;
+    let mut keep_alive = None;
 
-        match state {
+    loop {
+        match tracker.state() {
             PeerState::Handshake => {
-                let packet = sb_handshake::Packet::decode_bytes((), &packet)?;
+                let Some(frame) = peer_stream.read_frame().await else {
+                    break;
+                };
+
+                let packet = match frame? {
+                    Frame::Packet(packet) => sb_handshake::Packet::decode_exact((), &packet)?,
+                    Frame::LegacyPing(ping) => {
+                        log::info!("Received legacy server list ping: {ping:?}");
+                        peer_stream
+                            .respond_to_legacy_ping(
+                                &ping,
+                                &LegacyPingResponse {
+                                    protocol: PROTOCOL_VERSION,
+                                    version_name: VERSION_NAME,
+                                    motd: "Raft Development Server",
+                                    online: 2,
+                                    max: 1000,
+                                },
+                            )
+                            .await?;
+
+                        return Ok(true);
+                    }
+                };
 
                 match packet {
                     sb_handshake::Handshake(packet) => {
                         log::info!("Received handshake packet: {packet:#?}");
+                        peer_stream.set_protocol_version(ProtocolVersion(packet.version.0));
+
+                        match packet.next_state {
+                            structs::HandshakeIntent::Status => {
+                                tracker.transition(PeerState::Status)
+                            }
+                            structs::HandshakeIntent::Login if is_full => {
+                                tracker.transition(PeerState::Login);
+                                peer_stream
+                                    .kick(
+                                        PeerState::Login,
+                                        JsonValue(
+                                            ChatComponent::text("The server is full.").into(),
+                                        ),
+                                    )
+                                    .await?;
+
+                                return Ok(true);
+                            }
+                            structs::HandshakeIntent::Login => tracker.transition(PeerState::Login),
+                            structs::HandshakeIntent::Transfer => {
+                                // Like vanilla servers with transfers disabled, we turn transferred
+                                // peers away before they even begin logging in.
+                                tracker.transition(PeerState::Login);
+                                peer_stream
+                                    .kick(
+                                        PeerState::Login,
+                                        JsonValue(
+                                            ChatComponent::text(
+                                                "This server does not accept transfers.",
+                                            )
+                                            .into(),
+                                        ),
+                                    )
+                                    .await?;
 
-                        match packet.next_state.0 {
-                            1 => state = PeerState::Status,
-                            2 => state = PeerState::Login,
-                            _ => anyhow::bail!("Invalid handshake target state."),
+                                return Ok(true);
+                            }
                         }
                     }
                 }
             }
             PeerState::Status => {
-                let packet = sb_status::Packet::decode_bytes((), &packet)?;
+                let Some(packet) = peer_stream.read_packet::<state::Status>().await else {
+                    break;
+                };
 
-                match packet {
+                match packet? {
                     sb_status::StatusRequest(packet) => {
                         log::info!("Received status request: {packet:#?}");
                         peer_stream
                             .write(cb_status::StatusResponse {
-                                json_resp: NetString::from_static_str(include_str!(
-                                    "tmp/status.json"
-                                )),
+                                json_resp: JsonValue(
+                                    structs::StatusResponse::new(
+                                        ChatComponent::text("Raft Development Server")
+                                            .color("red")
+                                            .into(),
+                                    )
+                                    .with_players(1000, 2)
+                                    .with_secure_chat(true)
+                                    .with_chat_previews(true),
+                                ),
                             })
                             .await?;
                     }
@@ -91,48 +538,360 @@ async fn run_peer_listener(peer_stream: TcpStream) -> anyhow::Result<bool> {
                 }
             }
             PeerState::Login => {
-                let packet = sb_login::Packet::decode_bytes((), &packet)?;
+                let Some(packet) = peer_stream.read_packet::<state::Login>().await else {
+                    break;
+                };
 
-                match packet {
+                match packet? {
                     sb_login::LoginStart(packet) => {
                         log::info!("Received login start request: {packet:?}");
 
+                        let (challenge, request) = EncryptionChallenge::begin(keys);
+                        encryption = Some(challenge);
+                        peer_stream.write(request).await?;
+                    }
+                    sb_login::EncryptionResponse(packet) => {
+                        let Some(challenge) = encryption.take() else {
+                            anyhow::bail!("Received an unsolicited encryption response.");
+                        };
+
+                        let shared_secret = challenge.complete(keys, &packet)?;
+                        peer_stream.enable_encryption(shared_secret.0);
+                        log::info!("Enabled encryption for {peer_addr:?}");
+
+                        if let Some(threshold) = COMPRESSION_THRESHOLD {
+                            peer_stream.enable_compression(threshold).await?;
+                        }
+
                         peer_stream
-                            .write(cb_login::Disconnect {
-                                reason: JsonValue(RootChatComponent(SmallVec::from_iter([
-                                    ChatComponent {
-                                        text: Some(format!("Your IP is ",)),
-                                        color: Some("red".to_string()),
-                                        ..Default::default()
-                                    },
-                                    ChatComponent {
-                                        text: Some(peer_addr.to_string()),
-                                        color: Some("white".to_string()),
-                                        bold: Some(true),
-                                        ..Default::default()
-                                    },
-                                    ChatComponent {
-                                        text: Some(format!(".",)),
-                                        color: Some("red".to_string()),
-                                        ..Default::default()
-                                    },
-                                    ChatComponent {
-                                        text: Some(format!("\n\nRun.",)),
-                                        color: Some("dark_red".to_string()),
-                                        italic: Some(true),
-                                        ..Default::default()
-                                    },
+                            .kick(
+                                PeerState::Login,
+                                JsonValue(RootChatComponent(SmallVec::from_iter([
+                                    ChatComponent::text("Your IP is ").color("red"),
+                                    ChatComponent::text(peer_addr.to_string())
+                                        .color("white")
+                                        .bold(),
+                                    ChatComponent::text(".").color("red"),
+                                    ChatComponent::text("\n\nRun.").color("dark_red").italic(),
                                 ]))),
-                            })
+                            )
                             .await?;
+
+                        return Ok(true);
+                    }
+                    sb_login::LoginPluginResponse(_packet) => {
+                        // We never send login plugin requests so there's nothing to respond to.
+                        anyhow::bail!("Received an unsolicited login plugin response.");
+                    }
+                    sb_login::CookieResponse(packet) => {
+                        // We never request cookies so there's nothing to do with these yet.
+                        log::info!("Received cookie response: {packet:?}");
+                    }
+                }
+            }
+            PeerState::Play => {
+                let keep_alive = keep_alive.get_or_insert_with(KeepAliveTracker::new);
+
+                let packet = tokio::select! {
+                    packet = peer_stream.read_packet::<state::Play>() => packet,
+                    _ = tokio::time::sleep_until(keep_alive.deadline().into()) => {
+                        if let Some(id) = keep_alive.poll(Instant::now())? {
+                            peer_stream.write(cb_play::KeepAlive { id }).await?;
+                        }
+
+                        continue;
                     }
-                    sb_login::EncryptionResponse(_packet) => todo!(),
-                    sb_login::LoginPluginResponse(_packet) => todo!(),
+                };
+
+                let Some(packet) = packet else {
+                    break;
+                };
+
+                match packet? {
+                    sb_play::KeepAlive(packet) => keep_alive.acknowledge(packet.id),
                 }
             }
-            PeerState::Play => todo!(),
         }
     }
 
     Ok(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::{net::TcpListener, sync::oneshot};
+
+    use crate::net::{
+        primitives::{NetString, VarInt},
+        protocol::cb_login,
+        transport::Client,
+    };
+
+    use super::*;
+
+    /// Finds `N` distinct ports on the loopback interface which are free at the time of the call.
+    fn ephemeral_addrs<const N: usize>() -> [SocketAddr; N] {
+        // Every listener is held until all of them are bound so that no port is handed out twice.
+        let listeners = [(); N].map(|()| std::net::TcpListener::bind("127.0.0.1:0").unwrap());
+        listeners.map(|listener| listener.local_addr().unwrap())
+    }
+
+    fn ephemeral_addr() -> SocketAddr {
+        let [addr] = ephemeral_addrs();
+        addr
+    }
+
+    async fn connect(addr: SocketAddr) -> TcpStream {
+        // The server may not have started listening yet.
+        loop {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => return stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    }
+
+    /// Pings the server at `addr` for its status. The returned peer is known to be connected since
+    /// the server has answered it.
+    async fn status_ping(addr: SocketAddr, payload: i64) -> RawPeerStream<Client> {
+        let mut peer = RawPeerStream::<Client>::new(connect(addr).await, HARD_MAX_PACKET_LEN_INCL);
+        peer.write(sb_handshake::Handshake {
+            version: VarInt(PROTOCOL_VERSION),
+            server_addr: NetString::from_static_str("localhost"),
+            port: addr.port(),
+            next_state: structs::HandshakeIntent::Status,
+        })
+        .await
+        .unwrap();
+        peer.write(sb_status::PingRequest { payload })
+            .await
+            .unwrap();
+
+        let Some(Ok(cb_status::Packet::PingResponse(pong))) =
+            peer.read_packet::<state::Status>().await
+        else {
+            panic!("expected a ping response");
+        };
+        assert_eq!(pong.payload, payload);
+
+        peer
+    }
+
+    #[tokio::test]
+    async fn run_server_until_returns_once_shutdown_resolves() {
+        let addr = ephemeral_addr();
+        let config = ServerConfig {
+            listen_addrs: vec![addr],
+            ..ServerConfig::default()
+        };
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            run_server_until(&config, async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+        });
+
+        let mut peer = status_ping(addr, 42).await;
+
+        shutdown_tx.send(()).unwrap();
+
+        tokio::time::timeout(SHUTDOWN_TIMEOUT + Duration::from_secs(1), server)
+            .await
+            .expect("the server did not shut down")
+            .unwrap()
+            .unwrap();
+
+        // Peers in the status state are disconnected by closing their stream.
+        assert!(peer.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn every_listen_addr_accepts_connections() {
+        let addrs = ephemeral_addrs::<2>();
+        let config = ServerConfig {
+            listen_addrs: addrs.to_vec(),
+            ..ServerConfig::default()
+        };
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            run_server_until(&config, async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+        });
+
+        // Both peers are served at once, one through each listener.
+        let first = status_ping(addrs[0], 1).await;
+        let second = status_ping(addrs[1], 2).await;
+        drop((first, second));
+
+        shutdown_tx.send(()).unwrap();
+        tokio::time::timeout(SHUTDOWN_TIMEOUT + Duration::from_secs(1), server)
+            .await
+            .expect("the server did not shut down")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn peers_past_the_connection_limit_are_told_the_server_is_full() {
+        let addr = ephemeral_addr();
+        let config = ServerConfig {
+            listen_addrs: vec![addr],
+            max_connections: 1,
+            ..ServerConfig::default()
+        };
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            run_server_until(&config, async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+        });
+
+        // This peer holds the only connection permit for as long as it stays connected.
+        let _first = status_ping(addr, 1).await;
+
+        // Peers past the limit can still see the server's status...
+        let _second = status_ping(addr, 2).await;
+
+        // ...but are turned away when they try to log in.
+        let mut third = RawPeerStream::<Client>::new(connect(addr).await, HARD_MAX_PACKET_LEN_INCL);
+        third
+            .write(sb_handshake::Handshake {
+                version: VarInt(PROTOCOL_VERSION),
+                server_addr: NetString::from_static_str("localhost"),
+                port: addr.port(),
+                next_state: structs::HandshakeIntent::Login,
+            })
+            .await
+            .unwrap();
+
+        let Some(Ok(cb_login::Packet::Disconnect(disconnect))) =
+            third.read_packet::<state::Login>().await
+        else {
+            panic!("expected a login disconnect");
+        };
+        assert_eq!(
+            serde_json::to_value(&disconnect.reason.0).unwrap(),
+            serde_json::json!({ "text": "The server is full." }),
+        );
+        assert!(third.read().await.is_none());
+
+        shutdown_tx.send(()).unwrap();
+        tokio::time::timeout(SHUTDOWN_TIMEOUT + Duration::from_secs(1), server)
+            .await
+            .expect("the server did not shut down")
+            .unwrap()
+            .unwrap();
+    }
+
+    /// Connects a client to a server-side stream which has yet to be driven and sends a handshake
+    /// with the given intent.
+    async fn handshaken_pair(
+        intent: structs::HandshakeIntent,
+    ) -> (RawPeerStream, RawPeerStream<Client>, SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (client, accepted) =
+            tokio::join!(connect(listener.local_addr().unwrap()), listener.accept());
+        let (server, peer_addr) = accepted.unwrap();
+
+        let mut client = RawPeerStream::<Client>::new(client, HARD_MAX_PACKET_LEN_INCL);
+        client
+            .write(sb_handshake::Handshake {
+                version: VarInt(PROTOCOL_VERSION),
+                server_addr: NetString::from_static_str("localhost"),
+                port: peer_addr.port(),
+                next_state: intent,
+            })
+            .await
+            .unwrap();
+
+        (
+            RawPeerStream::new(server, HARD_MAX_PACKET_LEN_INCL),
+            client,
+            peer_addr,
+        )
+    }
+
+    #[tokio::test]
+    async fn transfer_handshakes_move_peers_to_login() {
+        let (mut server, mut client, peer_addr) =
+            handshaken_pair(structs::HandshakeIntent::Transfer).await;
+
+        let keys = ServerKeyPair::generate().unwrap();
+        let mut tracker = PeerStateTracker::new(PeerState::Handshake);
+        let closed = drive_peer(peer_addr, &keys, false, &mut server, &mut tracker)
+            .await
+            .unwrap();
+
+        assert!(closed);
+        assert_eq!(tracker.state(), PeerState::Login);
+
+        // The peer is kicked with a login disconnect rather than a play one.
+        let Some(Ok(cb_login::Packet::Disconnect(disconnect))) =
+            client.read_packet::<state::Login>().await
+        else {
+            panic!("expected a login disconnect");
+        };
+        assert_eq!(
+            serde_json::to_value(&disconnect.reason.0).unwrap(),
+            serde_json::json!({ "text": "This server does not accept transfers." }),
+        );
+    }
+
+    #[tokio::test]
+    async fn unsolicited_login_plugin_responses_are_rejected() {
+        let (mut server, mut client, peer_addr) =
+            handshaken_pair(structs::HandshakeIntent::Login).await;
+        client
+            .write(sb_login::LoginPluginResponse {
+                message_id: VarInt(0),
+                data: None,
+            })
+            .await
+            .unwrap();
+
+        let keys = ServerKeyPair::generate().unwrap();
+        let mut tracker = PeerStateTracker::new(PeerState::Handshake);
+        let err = drive_peer(peer_addr, &keys, false, &mut server, &mut tracker)
+            .await
+            .unwrap_err();
+
+        assert!(
+            err.to_string()
+                .contains("unsolicited login plugin response"),
+            "{err:#}"
+        );
+        assert_eq!(tracker.state(), PeerState::Login);
+    }
+
+    #[test]
+    fn only_fatal_accept_errors_stop_the_server() {
+        for kind in [
+            io::ErrorKind::ConnectionAborted,
+            io::ErrorKind::ConnectionReset,
+            io::ErrorKind::Interrupted,
+        ] {
+            assert!(is_transient_accept_error(&kind.into()), "{kind:?}");
+        }
+
+        #[cfg(unix)]
+        for errno in [23, 24] {
+            let err = io::Error::from_raw_os_error(errno);
+            assert!(is_transient_accept_error(&err), "{err}");
+        }
+
+        // e.g. `EBADF` or `EINVAL`, which mean that the listener itself is broken.
+        assert!(!is_transient_accept_error(
+            &io::ErrorKind::InvalidInput.into()
+        ));
+        assert!(!is_transient_accept_error(&io::Error::other(
+            "listener closed"
+        )));
+    }
+}
@@ -0,0 +1,482 @@
+//! `arbitrary` support for the packet codecs, used by fuzz targets to generate random packets and
+//! check that they survive an encode-decode-encode round trip.
+
+use std::{any::type_name, fmt::Debug};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::util::proto::byte_stream::ByteCursor;
+
+use super::primitives::{
+    Angle, ArrayLen, ArrayMaxLen, BoundedVec, ByteArray, CanonicalFloat, Codec, EnumSet,
+    FixedPoint, IdOr, Identifier, ItemStack, JsonValue, LengthDelimited, NetString, NullableArray,
+    OneOf2, OneOf3, Position, PreEncodedJson, ProtoEnum, RawNbt, RegistryId, RootChatComponent,
+    SerializableJsonValue, SizedCodec, Slot, TeleportFlags, TrailingOptional, Uuid, VarInt,
+    VarUint,
+};
+
+// === Traits === //
+
+/// Generates an arbitrary value which can be encoded under the given codec arguments. Unlike
+/// [`Arbitrary`], implementors are expected to respect the constraints imposed by those arguments
+/// (e.g. string length limits) so that the generated value never fails [`Codec::validate`].
+pub trait ArbitraryCodec<A>: Codec<A> {
+    fn arbitrary(args: A, u: &mut Unstructured<'_>) -> arbitrary::Result<Self>;
+}
+
+/// Generates an arbitrary `T`, encodes it, decodes the encoded bytes, and re-encodes the decoded
+/// value, panicking if any step fails or if the two encodings differ. Packets don't implement
+/// `PartialEq` so comparing encodings is the closest we can get to asserting equality.
+pub fn check_round_trip<T>(u: &mut Unstructured<'_>) -> arbitrary::Result<()>
+where
+    T: ArbitraryCodec<()> + SizedCodec<()> + Debug,
+{
+    let value = T::arbitrary((), u)?;
+
+    if let Err(err) = value.validate(()) {
+        panic!(
+            "Arbitrary {} failed to validate: {err:?}\n{value:#?}",
+            type_name::<T>()
+        );
+    }
+
+    let encoded = encode_to_bytes(&value);
+    assert_eq!(
+        encoded.len(),
+        value.size(()),
+        "Reported size of {} did not match its encoded size.\n{value:#?}",
+        type_name::<T>(),
+    );
+
+    let mut cursor = ByteCursor::new(&encoded);
+    let decoded = match T::decode((), &encoded, &mut cursor) {
+        Ok(decoded) => decoded,
+        Err(err) => panic!(
+            "Failed to decode encoded {}: {err:?}\n{value:#?}",
+            type_name::<T>()
+        ),
+    };
+
+    assert!(
+        cursor.is_empty(),
+        "Decoding {} left {} trailing byte(s) unconsumed.\n{value:#?}",
+        type_name::<T>(),
+        cursor.len(),
+    );
+
+    assert_eq!(
+        encoded,
+        encode_to_bytes(&decoded),
+        "Re-encoded {} did not match its original encoding.\n{value:#?}\n{decoded:#?}",
+        type_name::<T>(),
+    );
+
+    Ok(())
+}
+
+fn encode_to_bytes<T: Codec<()>>(value: &T) -> Bytes {
+    let mut buf = BytesMut::new();
+    value.encode((), &mut buf);
+    buf.freeze()
+}
+
+// === Primitives === //
+
+macro_rules! impl_prim {
+    ($($ty:ty),*$(,)?) => {$(
+		impl ArbitraryCodec<()> for $ty {
+			fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+				u.arbitrary()
+			}
+		}
+	)*};
+}
+
+impl_prim!(bool, i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, u128);
+
+macro_rules! impl_canonical_float {
+    ($($ty:ty),*$(,)?) => {$(
+		impl ArbitraryCodec<()> for CanonicalFloat<$ty> {
+			fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+				let value: $ty = u.arbitrary()?;
+				Ok(Self(if value.is_finite() { value } else { 0.0 }))
+			}
+		}
+	)*};
+}
+
+impl_canonical_float!(f32, f64);
+
+impl ArbitraryCodec<()> for VarInt {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self(u.arbitrary()?))
+    }
+}
+
+impl ArbitraryCodec<()> for VarUint {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        // `VarUint`s are sent as `VarInt`s, which are rejected on decode if they are negative.
+        Ok(Self(u.int_in_range(0..=i32::MAX as u32)?))
+    }
+}
+
+impl<K: 'static> ArbitraryCodec<()> for RegistryId<K> {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self::new(u.arbitrary()?))
+    }
+}
+
+impl ArbitraryCodec<()> for Uuid {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self(u.arbitrary()?))
+    }
+}
+
+impl ArbitraryCodec<()> for Angle {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self(u.arbitrary()?))
+    }
+}
+
+impl ArbitraryCodec<()> for TeleportFlags {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self::absolute()
+            .with_x_relative(u.arbitrary()?)
+            .with_y_relative(u.arbitrary()?)
+            .with_z_relative(u.arbitrary()?)
+            .with_yaw_relative(u.arbitrary()?)
+            .with_pitch_relative(u.arbitrary()?))
+    }
+}
+
+impl<const SHIFT: u32> ArbitraryCodec<()> for FixedPoint<SHIFT> {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self::from_raw(u.arbitrary()?))
+    }
+}
+
+impl ArbitraryCodec<()> for Position {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self::new(
+            u.int_in_range(Self::MIN_XZ..=Self::MAX_XZ)?,
+            u.int_in_range(Self::MIN_Y..=Self::MAX_Y)?,
+            u.int_in_range(Self::MIN_XZ..=Self::MAX_XZ)?,
+        ))
+    }
+}
+
+impl<E: ProtoEnum> ArbitraryCodec<()> for EnumSet<E> {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        let mut set = Self::new();
+
+        for variant in E::VARIANTS {
+            if u.arbitrary()? {
+                set.insert(variant);
+            }
+        }
+
+        Ok(set)
+    }
+}
+
+impl ArbitraryCodec<()> for Bytes {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Bytes::from(u.arbitrary::<Vec<u8>>()?))
+    }
+}
+
+impl ArbitraryCodec<()> for ByteArray {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self::new(Bytes::arbitrary((), u)?))
+    }
+}
+
+// === Strings === //
+
+impl ArbitraryCodec<Option<u32>> for NetString {
+    fn arbitrary(max_len: Option<u32>, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        let mut str = String::arbitrary(u)?;
+
+        // Truncate the string to its maximum length without splitting a surrogate pair.
+        if let Some(max_len) = max_len {
+            let mut len = 0;
+            let end = str
+                .char_indices()
+                .find(|&(_, char)| {
+                    len += char.len_utf16();
+                    len > max_len as usize
+                })
+                .map_or(str.len(), |(idx, _)| idx);
+
+            str.truncate(end);
+        }
+
+        Ok(Self::from_string(str))
+    }
+}
+
+impl ArbitraryCodec<u32> for NetString {
+    fn arbitrary(max_len: u32, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Self::arbitrary(Some(max_len), u)
+    }
+}
+
+impl ArbitraryCodec<()> for NetString {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Self::arbitrary(None, u)
+    }
+}
+
+impl ArbitraryCodec<()> for Identifier {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        const NAMESPACE_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789._-";
+        const PATH_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789._-/";
+
+        fn arbitrary_part(chars: &[u8], u: &mut Unstructured<'_>) -> arbitrary::Result<String> {
+            let mut part = String::from(*u.choose(chars)? as char);
+
+            while !u.is_empty() && u.arbitrary()? {
+                part.push(*u.choose(chars)? as char);
+            }
+
+            Ok(part)
+        }
+
+        let mut id = String::new();
+        if u.arbitrary()? {
+            id.push_str(&arbitrary_part(NAMESPACE_CHARS, u)?);
+            id.push(':');
+        }
+        id.push_str(&arbitrary_part(PATH_CHARS, u)?);
+
+        // Identifiers are limited to 32767 characters, all of which are ASCII.
+        if id.len() > 32767 {
+            return Err(arbitrary::Error::IncorrectFormat);
+        }
+
+        Ok(Self(NetString::from_string(id)))
+    }
+}
+
+impl ArbitraryCodec<()> for RawNbt {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        // Generate a named compound of named integers. This doesn't exercise every tag type but
+        // `RawNbt` only cares about the structure's extent anyway.
+        let mut buf = BytesMut::new();
+        buf.put_u8(10);
+        buf.put_u16(0);
+
+        while !u.is_empty() && u.arbitrary()? {
+            let name = NetString::arbitrary(u16::MAX as u32 / 3, u)?;
+            buf.put_u8(3);
+            buf.put_u16(name.len() as u16);
+            buf.put_slice(name.as_bytes());
+            buf.put_i32(u.arbitrary()?);
+        }
+
+        buf.put_u8(0);
+
+        Ok(Self::from_bytes_unchecked(buf.freeze()))
+    }
+}
+
+impl ArbitraryCodec<()> for Slot {
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        if !u.arbitrary()? {
+            return Ok(Self::empty());
+        }
+
+        Ok(Self(Some(ItemStack {
+            count: u.int_in_range(1..=i32::MAX)?,
+            item_id: RegistryId::arbitrary((), u)?,
+            added_components: ArbitraryCodec::arbitrary(|| (), u)?,
+            removed_components: ArbitraryCodec::arbitrary(|| (), u)?,
+        })))
+    }
+}
+
+// === JSON === //
+
+impl<'a> Arbitrary<'a> for RootChatComponent {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self(u.arbitrary_iter()?.collect::<Result<_, _>>()?))
+    }
+}
+
+fn arbitrary_json<E>(u: &mut Unstructured<'_>) -> arbitrary::Result<(E, String)>
+where
+    E: SerializableJsonValue + for<'a> Arbitrary<'a>,
+{
+    let value = E::arbitrary(u)?;
+    let encoded = serde_json::to_string(&value).unwrap();
+
+    // Reject values whose encoding wouldn't fit in the string. Generating them is fine but encoding
+    // them would violate `E::MAX_STR_LEN`.
+    if encoded.encode_utf16().count() > E::MAX_STR_LEN as usize {
+        return Err(arbitrary::Error::IncorrectFormat);
+    }
+
+    Ok((value, encoded))
+}
+
+impl<E> ArbitraryCodec<()> for JsonValue<E>
+where
+    E: SerializableJsonValue + for<'a> Arbitrary<'a>,
+{
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self(arbitrary_json::<E>(u)?.0))
+    }
+}
+
+impl<E> ArbitraryCodec<()> for PreEncodedJson<E>
+where
+    E: SerializableJsonValue + for<'a> Arbitrary<'a>,
+{
+    fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self::new(&arbitrary_json::<E>(u)?.0))
+    }
+}
+
+// === Containers === //
+
+impl<A, T: ArbitraryCodec<A>> ArbitraryCodec<A> for Option<T> {
+    fn arbitrary(args: A, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(if u.arbitrary()? {
+            Some(T::arbitrary(args, u)?)
+        } else {
+            None
+        })
+    }
+}
+
+impl<A, T: ArbitraryCodec<A>> ArbitraryCodec<A> for TrailingOptional<T> {
+    fn arbitrary(args: A, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self(Option::<T>::arbitrary(args, u)?))
+    }
+}
+
+impl<A, T: ArbitraryCodec<A>> ArbitraryCodec<A> for IdOr<T> {
+    fn arbitrary(args: A, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(if u.arbitrary()? {
+            Self::Id(u.int_in_range(0..=i32::MAX as u32 - 1)?)
+        } else {
+            Self::Inline(T::arbitrary(args, u)?)
+        })
+    }
+}
+
+impl<T0, T1, A0, A1> ArbitraryCodec<(A0, A1)> for OneOf2<T0, T1>
+where
+    T0: ArbitraryCodec<A0>,
+    T1: ArbitraryCodec<A1>,
+{
+    fn arbitrary((a0, a1): (A0, A1), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(match u.int_in_range(0..=1)? {
+            0 => Self::First(T0::arbitrary(a0, u)?),
+            _ => Self::Second(T1::arbitrary(a1, u)?),
+        })
+    }
+}
+
+impl<T0, T1, T2, A0, A1, A2> ArbitraryCodec<(A0, A1, A2)> for OneOf3<T0, T1, T2>
+where
+    T0: ArbitraryCodec<A0>,
+    T1: ArbitraryCodec<A1>,
+    T2: ArbitraryCodec<A2>,
+{
+    fn arbitrary((a0, a1, a2): (A0, A1, A2), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(match u.int_in_range(0..=2)? {
+            0 => Self::First(T0::arbitrary(a0, u)?),
+            1 => Self::Second(T1::arbitrary(a1, u)?),
+            _ => Self::Third(T2::arbitrary(a2, u)?),
+        })
+    }
+}
+
+impl<A: Clone, T> ArbitraryCodec<A> for LengthDelimited<T>
+where
+    T: ArbitraryCodec<A> + SizedCodec<A>,
+{
+    fn arbitrary(args: A, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self(T::arbitrary(args, u)?))
+    }
+}
+
+impl<A, F, T> ArbitraryCodec<F> for Vec<T>
+where
+    T: ArbitraryCodec<A>,
+    F: FnMut() -> A,
+{
+    fn arbitrary(mut args: F, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        let mut builder = Vec::new();
+
+        while !u.is_empty() && u.arbitrary()? {
+            builder.push(T::arbitrary(args(), u)?);
+        }
+
+        Ok(builder)
+    }
+}
+
+impl<A, F, T> ArbitraryCodec<ArrayLen<F>> for Vec<T>
+where
+    T: ArbitraryCodec<A>,
+    F: FnMut() -> A,
+{
+    fn arbitrary(
+        ArrayLen(len, mut args): ArrayLen<F>,
+        u: &mut Unstructured<'_>,
+    ) -> arbitrary::Result<Self> {
+        (0..len).map(|_| T::arbitrary(args(), u)).collect()
+    }
+}
+
+impl<A, F, T> ArbitraryCodec<ArrayMaxLen<F>> for Vec<T>
+where
+    T: ArbitraryCodec<A>,
+    F: FnMut() -> A,
+{
+    fn arbitrary(
+        ArrayMaxLen(max_len, mut args): ArrayMaxLen<F>,
+        u: &mut Unstructured<'_>,
+    ) -> arbitrary::Result<Self> {
+        let mut builder = Vec::new();
+
+        while builder.len() < max_len as usize && !u.is_empty() && u.arbitrary()? {
+            builder.push(T::arbitrary(args(), u)?);
+        }
+
+        Ok(builder)
+    }
+}
+
+impl<A, F, T> ArbitraryCodec<F> for NullableArray<T>
+where
+    T: ArbitraryCodec<A>,
+    F: FnMut() -> A,
+{
+    fn arbitrary(args: F, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Self(if u.arbitrary()? {
+            Some(<Vec<T> as ArbitraryCodec<F>>::arbitrary(args, u)?)
+        } else {
+            None
+        }))
+    }
+}
+
+impl<A, F, T, const MAX_LEN: u32> ArbitraryCodec<F> for BoundedVec<T, MAX_LEN>
+where
+    T: ArbitraryCodec<A>,
+    F: FnMut() -> A,
+{
+    fn arbitrary(mut args: F, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        let mut builder = Vec::new();
+
+        while builder.len() < MAX_LEN as usize && !u.is_empty() && u.arbitrary()? {
+            builder.push(T::arbitrary(args(), u)?);
+        }
+
+        Ok(Self(builder))
+    }
+}
@@ -1,5 +1,11 @@
+mod chunk;
 pub mod driver;
+mod encryption;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
+mod nbt;
 mod primitives;
 mod primitives2;
-mod protocol;
+pub mod protocol;
+mod replay;
 mod transport;
@@ -0,0 +1,791 @@
+//! NBT (named binary tag) values in the network format used since 1.20.2, where the root tag is
+//! not named.
+
+use bytes::BufMut;
+
+use crate::util::{bytes_integration::Snip, proto::byte_stream::ByteCursor};
+
+use super::primitives::{
+    ChatClickAction, ChatClickEvent, ChatComponent, ChatHoverEvent, ChatShownItem, Codec,
+    RootChatComponent, SizedCodec,
+};
+
+// === Tags === //
+
+/// The maximum nesting depth of an NBT tag when no other limit is specified. This mirrors the
+/// limit imposed by the vanilla client.
+pub const DEFAULT_MAX_DEPTH: u32 = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtTag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// A list of tags which must all be of the type `elem_id`. The element type is kept even when
+    /// the list is empty so that empty lists re-encode to the same bytes.
+    List {
+        elem_id: u8,
+        elems: Vec<NbtTag>,
+    },
+    /// A list of named tags. Ordering is preserved so that values re-encode to the same bytes.
+    Compound(Vec<(String, NbtTag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+const TAG_END: u8 = 0;
+const TAG_LONG_ARRAY: u8 = 12;
+
+impl NbtTag {
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Byte(_) => 1,
+            Self::Short(_) => 2,
+            Self::Int(_) => 3,
+            Self::Long(_) => 4,
+            Self::Float(_) => 5,
+            Self::Double(_) => 6,
+            Self::ByteArray(_) => 7,
+            Self::String(_) => 8,
+            Self::List { .. } => 9,
+            Self::Compound(_) => 10,
+            Self::IntArray(_) => 11,
+            Self::LongArray(_) => 12,
+        }
+    }
+
+    /// Creates a list whose element type is that of its first element, or `TAG_End` if it's empty.
+    pub fn list(elems: Vec<NbtTag>) -> Self {
+        Self::List {
+            elem_id: elems.first().map_or(TAG_END, Self::id),
+            elems,
+        }
+    }
+
+    /// Looks up the entry with the given name if this tag is a compound.
+    pub fn get(&self, name: &str) -> Option<&NbtTag> {
+        match self {
+            Self::Compound(entries) => entries
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn decode_payload(
+        id: u8,
+        remaining_depth: u32,
+        src: &impl Snip,
+        cursor: &mut ByteCursor,
+    ) -> anyhow::Result<Self> {
+        Ok(match id {
+            1 => Self::Byte(i8::decode((), src, cursor)?),
+            2 => Self::Short(i16::decode((), src, cursor)?),
+            3 => Self::Int(i32::decode((), src, cursor)?),
+            4 => Self::Long(i64::decode((), src, cursor)?),
+            5 => Self::Float(f32::decode((), src, cursor)?),
+            6 => Self::Double(f64::decode((), src, cursor)?),
+            7 => Self::ByteArray(decode_array(src, cursor)?),
+            8 => Self::String(decode_string(cursor)?),
+            9 => {
+                let remaining_depth = descend(remaining_depth, cursor)?;
+                let elem_id = u8::decode((), src, cursor)?;
+                let len = i32::decode((), src, cursor)?;
+
+                if elem_id > TAG_LONG_ARRAY {
+                    anyhow::bail!(
+                        "NBT list has the unknown element type {elem_id} (location: {}).",
+                        cursor.format_location(),
+                    );
+                }
+
+                if elem_id == TAG_END && len > 0 {
+                    anyhow::bail!(
+                        "NBT list of {len} element(s) has the element type TAG_End (location: {}).",
+                        cursor.format_location(),
+                    );
+                }
+
+                // Don't trust the length for preallocation since every element could be empty.
+                let mut elems = Vec::new();
+                for _ in 0..len.max(0) {
+                    elems.push(Self::decode_payload(elem_id, remaining_depth, src, cursor)?);
+                }
+
+                Self::List { elem_id, elems }
+            }
+            10 => {
+                let remaining_depth = descend(remaining_depth, cursor)?;
+                let mut entries = Vec::new();
+
+                loop {
+                    let id = u8::decode((), src, cursor)?;
+                    if id == TAG_END {
+                        break;
+                    }
+
+                    let name = decode_string(cursor)?;
+                    let value = Self::decode_payload(id, remaining_depth, src, cursor)?;
+                    entries.push((name, value));
+                }
+
+                Self::Compound(entries)
+            }
+            11 => Self::IntArray(decode_array(src, cursor)?),
+            12 => Self::LongArray(decode_array(src, cursor)?),
+            _ => anyhow::bail!(
+                "Unknown NBT tag type {id} (location: {}).",
+                cursor.format_location(),
+            ),
+        })
+    }
+
+    fn encode_payload(&self, cursor: &mut impl BufMut) {
+        match self {
+            Self::Byte(v) => v.encode((), cursor),
+            Self::Short(v) => v.encode((), cursor),
+            Self::Int(v) => v.encode((), cursor),
+            Self::Long(v) => v.encode((), cursor),
+            Self::Float(v) => v.encode((), cursor),
+            Self::Double(v) => v.encode((), cursor),
+            Self::ByteArray(v) => encode_array(v, cursor),
+            Self::String(v) => encode_string(v, cursor),
+            Self::List { elem_id, elems } => {
+                elem_id.encode((), cursor);
+                array_len(elems).encode((), cursor);
+
+                for elem in elems {
+                    elem.encode_payload(cursor);
+                }
+            }
+            Self::Compound(entries) => {
+                for (name, value) in entries {
+                    value.id().encode((), cursor);
+                    encode_string(name, cursor);
+                    value.encode_payload(cursor);
+                }
+
+                TAG_END.encode((), cursor);
+            }
+            Self::IntArray(v) => encode_array(v, cursor),
+            Self::LongArray(v) => encode_array(v, cursor),
+        }
+    }
+
+    fn payload_size(&self) -> usize {
+        match self {
+            Self::Byte(_) => 1,
+            Self::Short(_) => 2,
+            Self::Int(_) | Self::Float(_) => 4,
+            Self::Long(_) | Self::Double(_) => 8,
+            Self::ByteArray(v) => 4 + v.len(),
+            Self::String(v) => string_size(v),
+            Self::List { elems, .. } => 1 + 4 + elems.iter().map(Self::payload_size).sum::<usize>(),
+            Self::Compound(entries) => {
+                entries
+                    .iter()
+                    .map(|(name, value)| 1 + string_size(name) + value.payload_size())
+                    .sum::<usize>()
+                    + 1
+            }
+            Self::IntArray(v) => 4 + v.len() * 4,
+            Self::LongArray(v) => 4 + v.len() * 8,
+        }
+    }
+
+    fn validate_payload(&self, remaining_depth: u32) -> anyhow::Result<()> {
+        match self {
+            Self::String(v) => validate_string(v),
+            Self::List { elem_id, elems } => {
+                let remaining_depth = remaining_depth
+                    .checked_sub(1)
+                    .ok_or_else(|| anyhow::anyhow!("NBT tag is nested too deeply."))?;
+
+                anyhow::ensure!(
+                    *elem_id <= TAG_LONG_ARRAY,
+                    "NBT list has the unknown element type {elem_id}.",
+                );
+
+                anyhow::ensure!(
+                    *elem_id != TAG_END || elems.is_empty(),
+                    "NBT list of {} element(s) has the element type TAG_End.",
+                    elems.len(),
+                );
+
+                if let Some((i, elem)) = elems
+                    .iter()
+                    .enumerate()
+                    .find(|(_, elem)| elem.id() != *elem_id)
+                {
+                    anyhow::bail!(
+                        "NBT list elements must all have the type {elem_id} but element {i} has \
+                         the type {}.",
+                        elem.id(),
+                    );
+                }
+
+                for elem in elems {
+                    elem.validate_payload(remaining_depth)?;
+                }
+
+                Ok(())
+            }
+            Self::Compound(entries) => {
+                let remaining_depth = remaining_depth
+                    .checked_sub(1)
+                    .ok_or_else(|| anyhow::anyhow!("NBT tag is nested too deeply."))?;
+
+                for (name, value) in entries {
+                    validate_string(name)?;
+                    value.validate_payload(remaining_depth)?;
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Decodes a root tag, which is not allowed to be nested more than `max_depth` levels deep.
+impl Codec<u32> for NbtTag {
+    fn decode(max_depth: u32, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let id = u8::decode((), src, cursor)?;
+        if id == TAG_END {
+            anyhow::bail!(
+                "Expected an NBT tag but found TAG_End (location: {}).",
+                cursor.format_location(),
+            );
+        }
+
+        Self::decode_payload(id, max_depth, src, cursor)
+    }
+
+    fn encode(&self, _max_depth: u32, cursor: &mut impl BufMut) {
+        self.id().encode((), cursor);
+        self.encode_payload(cursor);
+    }
+
+    fn validate(&self, max_depth: u32) -> anyhow::Result<()> {
+        self.validate_payload(max_depth)
+    }
+}
+
+impl SizedCodec<u32> for NbtTag {
+    fn size(&self, _max_depth: u32) -> usize {
+        1 + self.payload_size()
+    }
+}
+
+impl Codec<()> for NbtTag {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Self::decode(DEFAULT_MAX_DEPTH, src, cursor)
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.encode(DEFAULT_MAX_DEPTH, cursor)
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        self.validate(DEFAULT_MAX_DEPTH)
+    }
+}
+
+impl SizedCodec<()> for NbtTag {
+    fn size(&self, _args: ()) -> usize {
+        self.size(DEFAULT_MAX_DEPTH)
+    }
+}
+
+// === Text Components === //
+
+// Since 1.20.3, most play packets send text components as NBT rather than JSON. The NBT form has
+// the same structure as the JSON one except that booleans are bytes and that a component with
+// nothing but text may be abbreviated to a string tag.
+
+impl RootChatComponent {
+    pub fn to_nbt(&self) -> NbtTag {
+        if self.0.len() == 1 {
+            self.0[0].to_nbt()
+        } else {
+            NbtTag::list(self.0.iter().map(ChatComponent::to_nbt).collect())
+        }
+    }
+
+    pub fn from_nbt(tag: &NbtTag) -> anyhow::Result<Self> {
+        match tag {
+            NbtTag::List { elems, .. } => Ok(Self(
+                elems
+                    .iter()
+                    .map(ChatComponent::from_nbt)
+                    .collect::<anyhow::Result<_>>()?,
+            )),
+            _ => Ok(Self::from(ChatComponent::from_nbt(tag)?)),
+        }
+    }
+}
+
+impl ChatComponent {
+    pub fn to_nbt(&self) -> NbtTag {
+        let mut obj = NbtCompoundWriter::default();
+        obj.opt_str("text", &self.text);
+        obj.opt_str("translate", &self.translate);
+        obj.opt_str("keybind", &self.keybind);
+        obj.opt_bool("bold", self.bold);
+        obj.opt_bool("italic", self.italic);
+        obj.opt_bool("underlined", self.underlined);
+        obj.opt_bool("strikethrough", self.strikethrough);
+        obj.opt_bool("obfuscated", self.obfuscated);
+        obj.opt_str("font", &self.font);
+        obj.opt_str("color", &self.color);
+        obj.opt_str("insertion", &self.insertion);
+
+        if let Some(event) = &self.click_event {
+            let mut event_obj = NbtCompoundWriter::default();
+            event_obj.str("action", event.action.name());
+            event_obj.str("value", &event.value);
+            obj.put("clickEvent", event_obj.finish());
+        }
+
+        if let Some(event) = &self.hover_event {
+            let mut event_obj = NbtCompoundWriter::default();
+            event_obj.opt_str("show_text", &event.show_text);
+
+            if let Some(item) = &event.show_item {
+                let mut item_obj = NbtCompoundWriter::default();
+                item_obj.str("id", &item.id);
+                item_obj.put("count", NbtTag::Int(item.count));
+                item_obj.opt_str("tag", &item.tag);
+                event_obj.put("show_item", item_obj.finish());
+            }
+
+            event_obj.opt_str("show_entity", &event.show_entity);
+            obj.put("hoverEvent", event_obj.finish());
+        }
+
+        if !self.extra.is_empty() {
+            obj.put(
+                "extra",
+                NbtTag::list(self.extra.iter().map(Self::to_nbt).collect()),
+            );
+        }
+
+        obj.finish()
+    }
+
+    pub fn from_nbt(tag: &NbtTag) -> anyhow::Result<Self> {
+        if let NbtTag::String(text) = tag {
+            return Ok(Self::text(text.clone()));
+        }
+
+        let obj = NbtCompoundReader::new(tag, "text component")?;
+
+        Ok(Self {
+            text: obj.opt_str("text")?,
+            translate: obj.opt_str("translate")?,
+            keybind: obj.opt_str("keybind")?,
+            bold: obj.opt_bool("bold")?,
+            italic: obj.opt_bool("italic")?,
+            underlined: obj.opt_bool("underlined")?,
+            strikethrough: obj.opt_bool("strikethrough")?,
+            obfuscated: obj.opt_bool("obfuscated")?,
+            font: obj.opt_str("font")?,
+            color: obj.opt_str("color")?,
+            insertion: obj.opt_str("insertion")?,
+            click_event: obj
+                .opt("clickEvent")
+                .map(|tag| {
+                    let obj = NbtCompoundReader::new(tag, "click event")?;
+                    let action = obj.str("action")?;
+                    anyhow::Ok(ChatClickEvent {
+                        action: ChatClickAction::from_name(&action).ok_or_else(|| {
+                            anyhow::anyhow!("Unknown click event action {action:?}.")
+                        })?,
+                        value: obj.str("value")?,
+                    })
+                })
+                .transpose()?,
+            hover_event: obj
+                .opt("hoverEvent")
+                .map(|tag| {
+                    let obj = NbtCompoundReader::new(tag, "hover event")?;
+                    anyhow::Ok(ChatHoverEvent {
+                        show_text: obj.opt_str("show_text")?,
+                        show_item: obj
+                            .opt("show_item")
+                            .map(|tag| {
+                                let obj = NbtCompoundReader::new(tag, "shown item")?;
+                                anyhow::Ok(ChatShownItem {
+                                    id: obj.str("id")?,
+                                    count: obj.int("count")?,
+                                    tag: obj.opt_str("tag")?,
+                                })
+                            })
+                            .transpose()?,
+                        show_entity: obj.opt_str("show_entity")?,
+                    })
+                })
+                .transpose()?,
+            extra: match obj.opt("extra") {
+                Some(NbtTag::List { elems, .. }) => elems
+                    .iter()
+                    .map(Self::from_nbt)
+                    .collect::<anyhow::Result<_>>()?,
+                Some(other) => anyhow::bail!(
+                    "Expected `extra` to be a list tag but found a tag of type {}.",
+                    other.id(),
+                ),
+                None => Vec::new(),
+            },
+        })
+    }
+}
+
+#[derive(Default)]
+struct NbtCompoundWriter(Vec<(String, NbtTag)>);
+
+impl NbtCompoundWriter {
+    fn put(&mut self, key: &str, value: NbtTag) {
+        self.0.push((key.to_string(), value));
+    }
+
+    fn str(&mut self, key: &str, value: &str) {
+        self.put(key, NbtTag::String(value.to_string()));
+    }
+
+    fn opt_str(&mut self, key: &str, value: &Option<String>) {
+        if let Some(value) = value {
+            self.str(key, value);
+        }
+    }
+
+    fn opt_bool(&mut self, key: &str, value: Option<bool>) {
+        if let Some(value) = value {
+            self.put(key, NbtTag::Byte(value as i8));
+        }
+    }
+
+    fn finish(self) -> NbtTag {
+        NbtTag::Compound(self.0)
+    }
+}
+
+struct NbtCompoundReader<'a>(&'a NbtTag);
+
+impl<'a> NbtCompoundReader<'a> {
+    fn new(tag: &'a NbtTag, what: &str) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            matches!(tag, NbtTag::Compound(_)),
+            "Expected a {what} to be a compound tag but found a tag of type {}.",
+            tag.id(),
+        );
+
+        Ok(Self(tag))
+    }
+
+    fn opt(&self, key: &str) -> Option<&'a NbtTag> {
+        self.0.get(key)
+    }
+
+    fn req(&self, key: &str) -> anyhow::Result<&'a NbtTag> {
+        self.opt(key)
+            .ok_or_else(|| anyhow::anyhow!("Missing required field `{key}`."))
+    }
+
+    fn str(&self, key: &str) -> anyhow::Result<String> {
+        match self.req(key)? {
+            NbtTag::String(value) => Ok(value.clone()),
+            other => anyhow::bail!(
+                "Expected `{key}` to be a string tag but found a tag of type {}.",
+                other.id(),
+            ),
+        }
+    }
+
+    fn int(&self, key: &str) -> anyhow::Result<i32> {
+        match self.req(key)? {
+            NbtTag::Int(value) => Ok(*value),
+            other => anyhow::bail!(
+                "Expected `{key}` to be an int tag but found a tag of type {}.",
+                other.id(),
+            ),
+        }
+    }
+
+    fn opt_str(&self, key: &str) -> anyhow::Result<Option<String>> {
+        self.opt(key).map(|_| self.str(key)).transpose()
+    }
+
+    fn opt_bool(&self, key: &str) -> anyhow::Result<Option<bool>> {
+        match self.opt(key) {
+            Some(NbtTag::Byte(value)) => Ok(Some(*value != 0)),
+            Some(other) => anyhow::bail!(
+                "Expected `{key}` to be a byte tag but found a tag of type {}.",
+                other.id(),
+            ),
+            None => Ok(None),
+        }
+    }
+}
+
+// === Helpers === //
+
+fn descend(remaining_depth: u32, cursor: &ByteCursor) -> anyhow::Result<u32> {
+    remaining_depth.checked_sub(1).ok_or_else(|| {
+        anyhow::anyhow!(
+            "NBT tag is nested too deeply (location: {}).",
+            cursor.format_location(),
+        )
+    })
+}
+
+fn array_len<T>(elems: &[T]) -> i32 {
+    i32::try_from(elems.len()).expect("NBT array is too big to send over the network")
+}
+
+fn decode_array<T: Codec<()>>(src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Vec<T>> {
+    let len = i32::decode((), src, cursor)?;
+    let Ok(len) = usize::try_from(len) else {
+        anyhow::bail!(
+            "NBT array has a negative length of {len} (location: {}).",
+            cursor.format_location(),
+        );
+    };
+
+    // Each element is at least one byte so this bounds the allocation to the packet's size.
+    anyhow::ensure!(
+        len <= cursor.len(),
+        "NBT array of {len} element(s) is longer than the remaining {} byte(s) (location: {}).",
+        cursor.len(),
+        cursor.format_location(),
+    );
+
+    let mut elems = Vec::with_capacity(len);
+    for _ in 0..len {
+        elems.push(T::decode((), src, cursor)?);
+    }
+
+    Ok(elems)
+}
+
+fn encode_array<T: Codec<()>>(elems: &[T], cursor: &mut impl BufMut) {
+    array_len(elems).encode((), cursor);
+
+    for elem in elems {
+        elem.encode((), cursor);
+    }
+}
+
+// Strings are encoded in Java's "modified UTF-8," which differs from UTF-8 in that NUL is encoded
+// in two bytes and supplementary characters are encoded as two three-byte surrogates.
+
+fn decode_string(cursor: &mut ByteCursor) -> anyhow::Result<String> {
+    let len = match cursor.read_arr() {
+        Some(len) => u16::from_be_bytes(len),
+        None => anyhow::bail!(
+            "Expected an NBT string length (location: {}).",
+            cursor.format_location(),
+        ),
+    };
+
+    let Some(bytes) = cursor.read_slice(len as usize) else {
+        anyhow::bail!(
+            "Expected {len} byte(s) of NBT string data; found {} (location: {}).",
+            cursor.len(),
+            cursor.format_location(),
+        );
+    };
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter().copied();
+
+    while let Some(first) = iter.next() {
+        let mut continuation = || match iter.next() {
+            Some(byte) if byte & 0xC0 == 0x80 => Ok(u16::from(byte & 0x3F)),
+            _ => Err(anyhow::anyhow!(
+                "NBT string is not valid modified UTF-8 (location: {}).",
+                cursor.format_location(),
+            )),
+        };
+
+        units.push(match first {
+            0x00..=0x7F => u16::from(first),
+            0xC0..=0xDF => (u16::from(first & 0x1F) << 6) | continuation()?,
+            0xE0..=0xEF => {
+                (u16::from(first & 0x0F) << 12) | (continuation()? << 6) | continuation()?
+            }
+            _ => anyhow::bail!(
+                "NBT string is not valid modified UTF-8 (location: {}).",
+                cursor.format_location(),
+            ),
+        });
+    }
+
+    String::from_utf16(&units).map_err(|_| {
+        anyhow::anyhow!(
+            "NBT string contains unpaired surrogates (location: {}).",
+            cursor.format_location(),
+        )
+    })
+}
+
+fn encode_string(str: &str, cursor: &mut impl BufMut) {
+    u16::try_from(modified_utf8_len(str))
+        .expect("NBT string is too long to send over the network")
+        .encode((), cursor);
+
+    for unit in str.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => cursor.put_u8(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                cursor.put_u8(0xC0 | (unit >> 6) as u8);
+                cursor.put_u8(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                cursor.put_u8(0xE0 | (unit >> 12) as u8);
+                cursor.put_u8(0x80 | ((unit >> 6) & 0x3F) as u8);
+                cursor.put_u8(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+}
+
+fn modified_utf8_len(str: &str) -> usize {
+    str.encode_utf16()
+        .map(|unit| match unit {
+            0x0001..=0x007F => 1,
+            0x0000 | 0x0080..=0x07FF => 2,
+            _ => 3,
+        })
+        .sum()
+}
+
+fn string_size(str: &str) -> usize {
+    2 + modified_utf8_len(str)
+}
+
+fn validate_string(str: &str) -> anyhow::Result<()> {
+    let len = modified_utf8_len(str);
+    anyhow::ensure!(
+        len <= u16::MAX as usize,
+        "NBT strings can be at most {} byte(s) long but this one is {len} byte(s) long.",
+        u16::MAX,
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bytes::Bytes;
+
+    fn encode(tag: &NbtTag) -> Vec<u8> {
+        let mut buf = Vec::new();
+        Codec::<()>::encode(tag, (), &mut buf);
+        assert_eq!(buf.len(), SizedCodec::<()>::size(tag, ()));
+        buf
+    }
+
+    #[test]
+    fn tags_round_trip_known_bytes() {
+        #[rustfmt::skip]
+        let bytes: &[u8] = &[
+            10, // Unnamed root compound
+                1, 0, 1, b'b', 0x7F, // "b": Byte(127)
+                8, 0, 1, b's', 0, 2, b'h', b'i', // "s": String("hi")
+                9, 0, 1, b'l', 2, 0, 0, 0, 2, 0, 1, 0xFF, 0xFE, // "l": List of Short [1, -2]
+                11, 0, 1, b'i', 0, 0, 0, 1, 0, 0, 0, 5, // "i": IntArray([5])
+            0, // End of root
+        ];
+
+        let tag = NbtTag::decode_bytes((), &Bytes::from_static(bytes)).unwrap();
+        assert_eq!(tag.get("b"), Some(&NbtTag::Byte(127)));
+        assert_eq!(tag.get("s"), Some(&NbtTag::String("hi".to_string())));
+        assert_eq!(
+            tag.get("l"),
+            Some(&NbtTag::List {
+                elem_id: 2,
+                elems: vec![NbtTag::Short(1), NbtTag::Short(-2)],
+            }),
+        );
+        assert_eq!(tag.get("i"), Some(&NbtTag::IntArray(vec![5])));
+
+        assert_eq!(encode(&tag), bytes);
+    }
+
+    #[test]
+    fn empty_lists_keep_their_element_type() {
+        // An unnamed root list of zero ints.
+        let bytes: &[u8] = &[9, 3, 0, 0, 0, 0];
+
+        let tag = NbtTag::decode_bytes((), &Bytes::from_static(bytes)).unwrap();
+        assert_eq!(
+            tag,
+            NbtTag::List {
+                elem_id: 3,
+                elems: Vec::new(),
+            },
+        );
+        assert_eq!(encode(&tag), bytes);
+    }
+
+    #[test]
+    fn lists_reject_invalid_element_types() {
+        // Unknown element types are rejected even when the list is empty.
+        let err = NbtTag::decode_bytes((), &Bytes::from_static(&[9, 13, 0, 0, 0, 0])).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown element type 13"),
+            "{err:#}"
+        );
+
+        // Non-empty lists can't have the element type TAG_End.
+        assert!(NbtTag::decode_bytes((), &Bytes::from_static(&[9, 0, 0, 0, 0, 1])).is_err());
+
+        let mismatched = NbtTag::List {
+            elem_id: 1,
+            elems: vec![NbtTag::Byte(1), NbtTag::Short(2)],
+        };
+        assert!(Codec::<()>::validate(&mismatched, ()).is_err());
+    }
+
+    #[test]
+    fn text_components_round_trip_through_nbt() {
+        fn round_trip(json: serde_json::Value) {
+            let component: RootChatComponent = serde_json::from_value(json.clone()).unwrap();
+            let bytes = Bytes::from(encode(&component.to_nbt()));
+            let tag = NbtTag::decode_bytes((), &bytes).unwrap();
+            let component = RootChatComponent::from_nbt(&tag).unwrap();
+
+            assert_eq!(serde_json::to_value(&component).unwrap(), json);
+        }
+
+        round_trip(serde_json::json!({
+            "text": "Welcome, ",
+            "bold": true,
+            "color": "gold",
+            "clickEvent": { "action": "open_url", "value": "https://example.com" },
+            "hoverEvent": {
+                "show_text": "Click me",
+                "show_item": { "id": "minecraft:diamond", "count": 3, "tag": "{Damage:0}" },
+            },
+            "extra": [
+                {
+                    "translate": "multiplayer.player.joined",
+                    "italic": false,
+                    "extra": [{ "keybind": "key.jump", "underlined": true }],
+                },
+                { "text": "!", "insertion": "!", "font": "minecraft:uniform" },
+            ],
+        }));
+
+        round_trip(serde_json::json!([{ "text": "a" }, { "text": "b", "obfuscated": true }]));
+    }
+}
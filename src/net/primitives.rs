@@ -1,13 +1,17 @@
+use anyhow::Context;
 use bytes::{BufMut, Bytes};
+use derive_where::derive_where;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{any::type_name, mem, ops::Deref};
+use std::{any::type_name, fmt, io, marker::PhantomData, mem, ops::Deref};
 
 use smallvec::SmallVec;
 
+use super::nbt::DEFAULT_MAX_DEPTH as DEFAULT_MAX_NBT_DEPTH;
+
 use crate::util::{
-    bits::{i32_from_u32_2c, i32_to_u32_2c, StaticBitSet},
+    bits::{i32_from_u32_2c, i32_to_u32_2c, i64_from_u64_2c, i64_to_u64_2c, StaticBitSet},
     bytes_integration::Snip,
-    proto::byte_stream::{ByteCursor, WriteByteCounter},
+    proto::byte_stream::{ByteCursor, WriteByteCounter, WriteCodepointCounter},
 };
 
 const TOO_BIG_ERR: &str = "byte array is too big to send over the network";
@@ -31,9 +35,34 @@ pub trait Codec<A>: Sized {
 
     fn encode(&self, args: A, cursor: &mut impl BufMut);
 
+    /// Checks that this value can be encoded with the given arguments, returning a recoverable
+    /// error if it can't. `encode` is infallible so this should be called before encoding values
+    /// which may violate their arguments' constraints (e.g. over-long strings).
+    fn validate(&self, _args: A) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     fn decode_bytes(args: A, bytes: &Bytes) -> anyhow::Result<Self> {
         Self::decode(args, bytes, &mut ByteCursor::new(bytes))
     }
+
+    /// Like [`decode_bytes`](Self::decode_bytes) but fails if the value doesn't span all of
+    /// `bytes`. Use this for whole frames, where trailing data means the peer sent something we
+    /// misunderstood.
+    fn decode_exact(args: A, bytes: &Bytes) -> anyhow::Result<Self> {
+        let mut cursor = ByteCursor::new(bytes);
+        let value = Self::decode(args, bytes, &mut cursor)?;
+
+        anyhow::ensure!(
+            cursor.is_empty(),
+            "Found {} trailing byte(s) after decoding {} (location: {}).",
+            cursor.len(),
+            type_name::<Self>(),
+            cursor.format_location(),
+        );
+
+        Ok(value)
+    }
 }
 
 pub fn size_of_tiny<const MAX_SIZE: usize>(body: &impl StreamingCodec) -> usize {
@@ -47,6 +76,18 @@ pub fn size_of_tiny<const MAX_SIZE: usize>(body: &impl StreamingCodec) -> usize
     MAX_SIZE - container.len()
 }
 
+/// A fieldless enum encoded on the wire as the `VarInt` id of its variant. Implement this with
+/// [`proto_enum!`] rather than by hand.
+pub trait ProtoEnum: Sized + 'static {
+    /// Every variant in declaration order. A variant's index in this list is its ordinal, which is
+    /// what [`EnumSet`] uses as its bit position.
+    const VARIANTS: &'static [Self];
+
+    fn from_id(id: i32) -> Option<Self>;
+
+    fn to_id(&self) -> i32;
+}
+
 impl<T: StreamingCodec> Codec<()> for T {
     fn decode(_args: (), _snip: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
         match Self::decode_streaming(cursor)? {
@@ -69,32 +110,35 @@ impl<T: StreamingCodec> Codec<()> for T {
 #[doc(hidden)]
 pub mod codec_struct_internals {
     pub use {
-        super::{Codec, SizedCodec},
+        super::{Codec, ProtoEnum, SizedCodec, StreamingCodec, StreamingDecodeResult, VarInt},
         crate::util::{bytes_integration::Snip, proto::byte_stream::ByteCursor},
-        anyhow::Result,
+        anyhow::{bail, Result},
         bytes::BufMut,
         log::trace,
-        std::{any::type_name, result::Result::Ok, stringify},
+        std::{
+            any::type_name,
+            option::Option::{self, None, Some},
+            result::Result::Ok,
+            stringify,
+        },
+    };
+
+    #[cfg(feature = "arbitrary")]
+    pub use {
+        crate::net::fuzzing::ArbitraryCodec,
+        arbitrary::{Result as ArbitraryResult, Unstructured},
+        std::unreachable,
     };
 }
 
 macro_rules! codec_struct {
-    ($(
-		$(#[$attr:meta])*
-		$struct_vis:vis struct $struct_name:ident {
-			$($field_vis:vis $field_name:ident: $field_ty:ty $(=> $config:expr)?),*
-			$(,)?
-		}
-	)*) => {$(
-		$(#[$attr])*
-		$struct_vis struct $struct_name {
-			$($field_vis $field_name: $field_ty,)*
-		}
-
-        impl $crate::net::primitives::codec_struct_internals::Codec<()> for $struct_name {
+    (@impl $struct_name:ident($args:ident: $args_ty:ty) {
+		$($field_name:ident: $field_ty:ty $(=> $config:expr)?),*
+	}) => {
+        impl $crate::net::primitives::codec_struct_internals::Codec<$args_ty> for $struct_name {
 			#[allow(unused_variables)]
             fn decode(
-				_args: (),
+				$args: $args_ty,
                 src: &impl $crate::net::primitives::codec_struct_internals::Snip,
                 cursor: &mut $crate::net::primitives::codec_struct_internals::ByteCursor,
             ) -> $crate::net::primitives::codec_struct_internals::Result<Self> {
@@ -119,7 +163,7 @@ macro_rules! codec_struct {
 			#[allow(unused_variables)]
 			fn encode(
                 &self,
-				_args: (),
+				$args: $args_ty,
                 cursor: &mut impl $crate::net::primitives::codec_struct_internals::BufMut,
             ) {
 				$($crate::net::primitives::codec_struct_internals::Codec::encode(
@@ -128,18 +172,216 @@ macro_rules! codec_struct {
 					cursor,
 				);)*
             }
+
+			#[allow(unused_variables)]
+			fn validate(&self, $args: $args_ty) -> $crate::net::primitives::codec_struct_internals::Result<()> {
+				$($crate::net::primitives::codec_struct_internals::Codec::validate(
+					&self.$field_name,
+					{ $($config)? },
+				)?;)*
+				$crate::net::primitives::codec_struct_internals::Ok(())
+			}
+        }
+
+		impl $crate::net::primitives::codec_struct_internals::SizedCodec<$args_ty> for $struct_name {
+			#[allow(unused_variables)]
+            fn size(&self, $args: $args_ty) -> usize {
+				$($crate::net::primitives::codec_struct_internals::SizedCodec::size(&self.$field_name, { $($config)? }) + )* 0
+			}
+        }
+
+		#[cfg(feature = "arbitrary")]
+		impl $crate::net::primitives::codec_struct_internals::ArbitraryCodec<$args_ty> for $struct_name {
+			#[allow(unused_variables)]
+			fn arbitrary(
+				$args: $args_ty,
+				u: &mut $crate::net::primitives::codec_struct_internals::Unstructured<'_>,
+			) -> $crate::net::primitives::codec_struct_internals::ArbitraryResult<Self> {
+				$crate::net::primitives::codec_struct_internals::Ok(Self {
+					$($field_name: $crate::net::primitives::codec_struct_internals::ArbitraryCodec::arbitrary(
+						{ $($config)? },
+						u,
+					)?,)*
+				})
+			}
+		}
+    };
+    ($(
+		$(#[$attr:meta])*
+		$struct_vis:vis struct $struct_name:ident {
+			$($field_vis:vis $field_name:ident: $field_ty:ty $(=> $config:expr)?),*
+			$(,)?
+		}
+	)*) => {$(
+		$(#[$attr])*
+		$struct_vis struct $struct_name {
+			$($field_vis $field_name: $field_ty,)*
+		}
+
+		$crate::net::primitives::codec_struct!(@impl $struct_name(_args: ()) {
+			$($field_name: $field_ty $(=> $config)?),*
+		});
+    )*};
+    // Structs whose field configurations depend on a codec argument, which they can refer to as
+    // `$args`. These can also be encoded without an argument, in which case `$default` is used.
+    ($(
+		$(#[$attr:meta])*
+		$struct_vis:vis struct $struct_name:ident($args:ident: $args_ty:ty = $default:expr) {
+			$($field_vis:vis $field_name:ident: $field_ty:ty $(=> $config:expr)?),*
+			$(,)?
+		}
+	)*) => {$(
+		$(#[$attr])*
+		$struct_vis struct $struct_name {
+			$($field_vis $field_name: $field_ty,)*
+		}
+
+		$crate::net::primitives::codec_struct!(@impl $struct_name($args: $args_ty) {
+			$($field_name: $field_ty $(=> $config)?),*
+		});
+
+        impl $crate::net::primitives::codec_struct_internals::Codec<()> for $struct_name {
+            fn decode(
+				_args: (),
+                src: &impl $crate::net::primitives::codec_struct_internals::Snip,
+                cursor: &mut $crate::net::primitives::codec_struct_internals::ByteCursor,
+            ) -> $crate::net::primitives::codec_struct_internals::Result<Self> {
+				$crate::net::primitives::codec_struct_internals::Codec::<$args_ty>::decode($default, src, cursor)
+            }
+
+			fn encode(
+                &self,
+				_args: (),
+                cursor: &mut impl $crate::net::primitives::codec_struct_internals::BufMut,
+            ) {
+				$crate::net::primitives::codec_struct_internals::Codec::<$args_ty>::encode(self, $default, cursor)
+            }
+
+			fn validate(&self, _args: ()) -> $crate::net::primitives::codec_struct_internals::Result<()> {
+				$crate::net::primitives::codec_struct_internals::Codec::<$args_ty>::validate(self, $default)
+			}
         }
 
 		impl $crate::net::primitives::codec_struct_internals::SizedCodec<()> for $struct_name {
             fn size(&self, _args: ()) -> usize {
-				$($crate::net::primitives::codec_struct_internals::SizedCodec::size(&self.$field_name, { $($config)? }) + )* 0
+				$crate::net::primitives::codec_struct_internals::SizedCodec::<$args_ty>::size(self, $default)
 			}
         }
+
+		#[cfg(feature = "arbitrary")]
+		impl $crate::net::primitives::codec_struct_internals::ArbitraryCodec<()> for $struct_name {
+			fn arbitrary(
+				_args: (),
+				u: &mut $crate::net::primitives::codec_struct_internals::Unstructured<'_>,
+			) -> $crate::net::primitives::codec_struct_internals::ArbitraryResult<Self> {
+				$crate::net::primitives::codec_struct_internals::ArbitraryCodec::<$args_ty>::arbitrary($default, u)
+			}
+		}
     )*};
 }
 
 pub(crate) use codec_struct;
 
+macro_rules! proto_enum {
+    ($(
+		$(#[$attr:meta])*
+		$enum_vis:vis enum $enum_name:ident {
+			$($(#[$variant_attr:meta])* $variant_name:ident = $id:literal),*
+			$(,)?
+		}
+	)*) => {$(
+		$(#[$attr])*
+		$enum_vis enum $enum_name {
+			$($(#[$variant_attr])* $variant_name,)*
+		}
+
+		impl $crate::net::primitives::codec_struct_internals::ProtoEnum for $enum_name {
+			const VARIANTS: &'static [Self] = &[$(Self::$variant_name),*];
+
+			fn from_id(id: i32) -> $crate::net::primitives::codec_struct_internals::Option<Self> {
+				match id {
+					$($id => $crate::net::primitives::codec_struct_internals::Some(Self::$variant_name),)*
+					_ => $crate::net::primitives::codec_struct_internals::None,
+				}
+			}
+
+			fn to_id(&self) -> i32 {
+				match self {
+					$(Self::$variant_name => $id,)*
+				}
+			}
+		}
+
+		impl $crate::net::primitives::codec_struct_internals::StreamingCodec for $enum_name {
+			fn decode_streaming(
+				cursor: &mut $crate::net::primitives::codec_struct_internals::ByteCursor,
+			) -> $crate::net::primitives::codec_struct_internals::StreamingDecodeResult<Self> {
+				let $crate::net::primitives::codec_struct_internals::Some(id) =
+					<$crate::net::primitives::codec_struct_internals::VarInt as $crate::net::primitives::codec_struct_internals::StreamingCodec>::decode_streaming(cursor)?
+				else {
+					return $crate::net::primitives::codec_struct_internals::Ok(
+						$crate::net::primitives::codec_struct_internals::None,
+					);
+				};
+
+				match <Self as $crate::net::primitives::codec_struct_internals::ProtoEnum>::from_id(id.0) {
+					$crate::net::primitives::codec_struct_internals::Some(value) => {
+						$crate::net::primitives::codec_struct_internals::Ok(
+							$crate::net::primitives::codec_struct_internals::Some(value),
+						)
+					}
+					$crate::net::primitives::codec_struct_internals::None => {
+						$crate::net::primitives::codec_struct_internals::bail!(
+							"unknown {} id {} (location: {})",
+							$crate::net::primitives::codec_struct_internals::type_name::<Self>(),
+							id.0,
+							cursor.format_location(),
+						)
+					}
+				}
+			}
+
+			fn encode_streaming(
+				&self,
+				cursor: &mut impl $crate::net::primitives::codec_struct_internals::BufMut,
+			) {
+				$crate::net::primitives::codec_struct_internals::StreamingCodec::encode_streaming(
+					&$crate::net::primitives::codec_struct_internals::VarInt(
+						$crate::net::primitives::codec_struct_internals::ProtoEnum::to_id(self),
+					),
+					cursor,
+				);
+			}
+		}
+
+		impl $crate::net::primitives::codec_struct_internals::SizedCodec<()> for $enum_name {
+			fn size(&self, _args: ()) -> usize {
+				$crate::net::primitives::codec_struct_internals::SizedCodec::size(
+					&$crate::net::primitives::codec_struct_internals::VarInt(
+						$crate::net::primitives::codec_struct_internals::ProtoEnum::to_id(self),
+					),
+					(),
+				)
+			}
+		}
+
+		#[cfg(feature = "arbitrary")]
+		impl $crate::net::primitives::codec_struct_internals::ArbitraryCodec<()> for $enum_name {
+			fn arbitrary(
+				_args: (),
+				u: &mut $crate::net::primitives::codec_struct_internals::Unstructured<'_>,
+			) -> $crate::net::primitives::codec_struct_internals::ArbitraryResult<Self> {
+				$crate::net::primitives::codec_struct_internals::Ok(match *u.choose(&[$($id),*])? {
+					$($id => Self::$variant_name,)*
+					_ => $crate::net::primitives::codec_struct_internals::unreachable!(),
+				})
+			}
+		}
+	)*};
+}
+
+pub(crate) use proto_enum;
+
 // === Streaming Primitives === //
 
 impl StreamingCodec for bool {
@@ -195,7 +437,7 @@ macro_rules! impl_prim {
 	)*};
 }
 
-impl_prim!(i8, u8, i16, u16, i32, u32, i64, f32, f64, u128);
+impl_prim!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, u128);
 
 #[derive(Debug, Copy, Clone)]
 pub struct VarInt(pub i32);
@@ -281,6 +523,127 @@ impl SizedCodec<()> for VarUint {
     }
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct VarLong(pub i64);
+
+impl StreamingCodec for VarLong {
+    fn decode_streaming(cursor: &mut ByteCursor) -> StreamingDecodeResult<Self> {
+        let mut accum = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let Some(byte) = cursor.read() else { return Ok(None) };
+            accum |= ((byte & !u8::MSB) as u64) << shift;
+
+            if byte & u8::MSB == 0 {
+                break;
+            }
+
+            shift += 7;
+
+            if shift >= 64 {
+                anyhow::bail!(
+                    "VarLong is too long to fit an i64 (location: {}).",
+                    cursor.format_location(),
+                );
+            }
+        }
+
+        let accum = i64_from_u64_2c(accum);
+        Ok(Some(Self(accum)))
+    }
+
+    fn encode_streaming(&self, cursor: &mut impl BufMut) {
+        let mut accum = i64_to_u64_2c(self.0);
+
+        loop {
+            let byte = accum & !u8::MSB as u64;
+            accum >>= 7;
+
+            if accum > 0 {
+                cursor.put_u8(byte as u8 | u8::MSB);
+            } else {
+                cursor.put_u8(byte as u8);
+                break;
+            }
+        }
+    }
+}
+
+impl SizedCodec<()> for VarLong {
+    fn size(&self, _args: ()) -> usize {
+        size_of_tiny::<10>(self)
+    }
+}
+
+/// The `VarInt` id of an entry in one of the game's registries. The registry is named by the marker
+/// type `K` so that, for example, a block id can't be passed where an item id is expected.
+#[derive_where(Copy, Clone, Hash, Eq, PartialEq)]
+pub struct RegistryId<K> {
+    raw: i32,
+    _kind: PhantomData<fn() -> K>,
+}
+
+/// Marks ids into the block registry.
+pub enum BlockKind {}
+
+/// Marks ids into the item registry.
+pub enum ItemKind {}
+
+/// Marks ids into the item component type registry.
+pub enum ItemComponentKind {}
+
+/// Marks ids into the entity type registry.
+pub enum EntityKind {}
+
+impl<K> RegistryId<K> {
+    pub const fn new(raw: i32) -> Self {
+        Self {
+            raw,
+            _kind: PhantomData,
+        }
+    }
+
+    pub const fn into_raw(self) -> i32 {
+        self.raw
+    }
+}
+
+impl<K> fmt::Debug for RegistryId<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = type_name::<K>().rsplit("::").next().unwrap();
+        write!(f, "RegistryId<{kind}>({})", self.raw)
+    }
+}
+
+impl<K> From<i32> for RegistryId<K> {
+    fn from(raw: i32) -> Self {
+        Self::new(raw)
+    }
+}
+
+impl<K> From<RegistryId<K>> for i32 {
+    fn from(id: RegistryId<K>) -> Self {
+        id.into_raw()
+    }
+}
+
+impl<K: 'static> StreamingCodec for RegistryId<K> {
+    fn decode_streaming(cursor: &mut ByteCursor) -> StreamingDecodeResult<Self> {
+        Ok(VarInt::decode_streaming(cursor)?.map(|id| Self::new(id.0)))
+    }
+
+    fn encode_streaming(&self, cursor: &mut impl BufMut) {
+        VarInt(self.raw).encode_streaming(cursor)
+    }
+}
+
+impl<K: 'static> SizedCodec<()> for RegistryId<K> {
+    fn size(&self, _args: ()) -> usize {
+        VarInt(self.raw).size(())
+    }
+}
+
 // === Codec === //
 
 // Bytes
@@ -303,6 +666,12 @@ impl SizedCodec<()> for Bytes {
 }
 
 // NetString
+/// A `VarUint` byte-length-prefixed UTF-8 string.
+///
+/// An empty string is still present on the wire as the single byte `0x00` (its length). This is
+/// distinct from an absent `Option<NetString>`, which is also encoded as the single byte `0x00` but
+/// means "no string follows" rather than "a string of length zero follows". A present-but-empty
+/// `Option<NetString>` is therefore encoded as the two bytes `0x01 0x00`.
 #[derive(Debug, Clone, Default)]
 pub struct NetString(Bytes);
 
@@ -331,6 +700,12 @@ impl NetString {
     pub fn into_bytes(self) -> Bytes {
         self.0
     }
+
+    /// The length of the string in UTF-16 code units, which is how the vanilla client measures
+    /// string length limits.
+    pub fn utf16_len(&self) -> usize {
+        self.encode_utf16().count()
+    }
 }
 
 impl Deref for NetString {
@@ -345,6 +720,52 @@ impl Deref for NetString {
     }
 }
 
+enum Utf16LenError {
+    Utf8(std::str::Utf8Error),
+    TooLong { at_least: usize },
+}
+
+/// Validates that `data` is UTF-8 while counting its length in UTF-16 code units, stopping as soon
+/// as that length exceeds `max_len`. The data is processed in windows split on character
+/// boundaries so that an over-long string is rejected without validating the rest of it.
+fn check_utf8_within_utf16_len(data: &[u8], max_len: usize) -> Result<(), Utf16LenError> {
+    const WINDOW_LEN: usize = 256;
+
+    let mut len = 0;
+    let mut rest = data;
+
+    while !rest.is_empty() {
+        // Back off to the start of the character straddling the window's end. UTF-8 characters have
+        // at most three continuation bytes; longer runs are invalid and will fail validation below.
+        let mut end = rest.len().min(WINDOW_LEN);
+        for _ in 0..3 {
+            if end < rest.len() && rest[end] & 0b1100_0000 == 0b1000_0000 {
+                end -= 1;
+            } else {
+                break;
+            }
+        }
+
+        let (window, next) = rest.split_at(end);
+        let Ok(window) = std::str::from_utf8(window) else {
+            // Windows always end on a character boundary of valid data so the error must lie within
+            // this window. Revalidate the whole string so that the error's offsets are absolute.
+            return Err(Utf16LenError::Utf8(
+                std::str::from_utf8(data).expect_err("window was invalid UTF-8"),
+            ));
+        };
+
+        len += window.encode_utf16().count();
+        if len > max_len {
+            return Err(Utf16LenError::TooLong { at_least: len });
+        }
+
+        rest = next;
+    }
+
+    Ok(())
+}
+
 impl Codec<Option<u32>> for NetString {
     fn decode(
         max_len: Option<u32>,
@@ -354,16 +775,18 @@ impl Codec<Option<u32>> for NetString {
         let size = VarUint::decode((), snip, cursor)?.0;
 
         if let Some(max_len) = max_len {
-            let max_bytes = max_len.checked_mul(4).unwrap_or_else(|| {
+            // Each UTF-16 code unit takes at most 3 bytes in UTF-8. Characters outside the basic
+            // multilingual plane take 4 bytes but also occupy two UTF-16 code units.
+            let max_bytes = max_len.checked_mul(3).unwrap_or_else(|| {
                 panic!(
-                    "NetStrings with a maximum codepoint length of {max_len} are untenable due to \
+                    "NetStrings limited to {max_len} UTF-16 code unit(s) are untenable due to \
 					 encoding constraints."
                 )
             });
 
             if size > max_bytes {
                 anyhow::bail!(
-					"String byte stream is too long. The string is limited to {max_len} codepoint(s), \
+					"String byte stream is too long. The string is limited to {max_len} UTF-16 code unit(s), \
 					 which can be encoded in up to {max_bytes} bytes, but the size of the string in \
 					 bytes is specified as {size} (location: {}).",
 					cursor.format_location(),
@@ -381,23 +804,25 @@ impl Codec<Option<u32>> for NetString {
 			);
 		};
 
-        match Self::from_bytes(snip.freeze_range(data)) {
-            Ok(str) => {
-                // TODO: Do this in one pass.
-                if let Some(max_len) = max_len {
-                    let actual_len = str.chars().count();
-                    if actual_len > max_len as usize {
-                        anyhow::bail!(
-                            "String is too long: can contain at most {max_len} codepoint(s) but \
-							 contains {actual_len} (location: {}).",
-                            cursor.format_location(),
-                        );
-                    }
-                }
-
-                Ok(str)
-            }
-            Err(err) => Err(anyhow::anyhow!(err).context(format!(
+        let res = match max_len {
+            Some(max_len) => check_utf8_within_utf16_len(data, max_len as usize),
+            None => std::str::from_utf8(data)
+                .map(|_| ())
+                .map_err(Utf16LenError::Utf8),
+        };
+
+        match res {
+            Ok(()) => Ok(unsafe {
+                // Safety: we just validated that `data` is valid UTF-8.
+                Self::from_bytes_unchecked(snip.freeze_range(data))
+            }),
+            Err(Utf16LenError::TooLong { at_least }) => anyhow::bail!(
+                "String is too long: can contain at most {} UTF-16 code unit(s) but contains at \
+				 least {at_least} (location: {}).",
+                max_len.unwrap(),
+                cursor.format_location(),
+            ),
+            Err(Utf16LenError::Utf8(err)) => Err(anyhow::anyhow!(err).context(format!(
                 "String byte data was not valid UTF8 (location: {}).",
                 cursor.format_location(),
             ))),
@@ -408,11 +833,11 @@ impl Codec<Option<u32>> for NetString {
         // Validate string length in debug builds.
         #[cfg(debug_assertions)]
         {
-            let str_len = self.chars().count();
+            let str_len = self.utf16_len();
             let max_len = max_len.unwrap_or(u32::MAX) as usize;
             debug_assert!(
                 str_len <= max_len,
-                "String can be at most {max_len} codepoint(s) but ended up being {str_len}."
+                "String can be at most {max_len} UTF-16 code unit(s) but ended up being {str_len}."
             );
         }
 
@@ -422,6 +847,18 @@ impl Codec<Option<u32>> for NetString {
         // Send string's UTF-8 encoded contents.
         self.bytes().encode((), cursor);
     }
+
+    fn validate(&self, max_len: Option<u32>) -> anyhow::Result<()> {
+        if let Some(max_len) = max_len {
+            let str_len = self.utf16_len();
+            anyhow::ensure!(
+                str_len <= max_len as usize,
+                "String can be at most {max_len} UTF-16 code unit(s) but ended up being {str_len}."
+            );
+        }
+
+        Ok(())
+    }
 }
 
 impl SizedCodec<Option<u32>> for NetString {
@@ -438,6 +875,10 @@ impl Codec<u32> for NetString {
     fn encode(&self, max_len: u32, cursor: &mut impl BufMut) {
         self.encode(Some(max_len), cursor)
     }
+
+    fn validate(&self, max_len: u32) -> anyhow::Result<()> {
+        self.validate(Some(max_len))
+    }
 }
 
 impl SizedCodec<u32> for NetString {
@@ -463,17 +904,85 @@ impl SizedCodec<()> for NetString {
 }
 
 // Identifier
+/// A namespaced identifier of the form `namespace:path`. The namespace may be omitted, in which case
+/// it defaults to [`DEFAULT_NAMESPACE`].
 #[derive(Debug, Clone)]
 pub struct Identifier(pub NetString);
 
+pub const DEFAULT_NAMESPACE: &str = "minecraft";
+
+/// Splits an identifier into its namespace and path without validating either.
+pub fn split_identifier(id: &str) -> (&str, &str) {
+    id.split_once(':').unwrap_or((DEFAULT_NAMESPACE, id))
+}
+
+/// Checks that an identifier's namespace only contains `[a-z0-9._-]` and that its path only
+/// contains `[a-z0-9._/-]`. Neither may be empty.
+pub fn validate_identifier(id: &str) -> anyhow::Result<()> {
+    fn is_namespace_char(c: char) -> bool {
+        matches!(c, 'a'..='z' | '0'..='9' | '.' | '_' | '-')
+    }
+
+    fn is_path_char(c: char) -> bool {
+        is_namespace_char(c) || c == '/'
+    }
+
+    let (namespace, path) = split_identifier(id);
+
+    anyhow::ensure!(
+        !namespace.is_empty(),
+        "Identifier {id:?} has an empty namespace.",
+    );
+    anyhow::ensure!(!path.is_empty(), "Identifier {id:?} has an empty path.");
+
+    if let Some(c) = namespace.chars().find(|&c| !is_namespace_char(c)) {
+        anyhow::bail!(
+            "Identifier {id:?} contains the invalid character {c:?} in its namespace. Namespaces may \
+             only contain lowercase letters, digits, '.', '_', and '-'.",
+        );
+    }
+
+    if let Some(c) = path.chars().find(|&c| !is_path_char(c)) {
+        anyhow::bail!(
+            "Identifier {id:?} contains the invalid character {c:?} in its path. Paths may only \
+             contain lowercase letters, digits, '.', '_', '-', and '/'.",
+        );
+    }
+
+    Ok(())
+}
+
+impl Identifier {
+    pub fn namespace(&self) -> &str {
+        split_identifier(&self.0).0
+    }
+
+    pub fn path(&self) -> &str {
+        split_identifier(&self.0).1
+    }
+}
+
 impl Codec<()> for Identifier {
     fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
-        Ok(Self(NetString::decode(32767, src, cursor)?))
+        let id = NetString::decode(32767, src, cursor)?;
+        validate_identifier(&id).with_context(|| {
+            format!(
+                "Invalid identifier (location: {}).",
+                cursor.format_location()
+            )
+        })?;
+
+        Ok(Self(id))
     }
 
     fn encode(&self, _args: (), cursor: &mut impl BufMut) {
         self.0.encode((), cursor);
     }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        self.0.validate(32767)?;
+        validate_identifier(&self.0)
+    }
 }
 
 impl SizedCodec<()> for Identifier {
@@ -483,11 +992,30 @@ impl SizedCodec<()> for Identifier {
 }
 
 // JSON
+/// A value sent as a JSON string.
+///
+/// Sizing the value serializes it just as encoding does, which is why streams encode packets before
+/// framing them rather than sizing them first (see
+/// [`UnframedPacket::pre_frame`](crate::net::transport::UnframedPacket::pre_frame)). Use a
+/// [`PreEncodedJson`] to share a single serialization between several packets.
 #[derive(Debug, Clone)]
 pub struct JsonValue<E>(pub E);
 
 pub trait SerializableJsonValue: serde::de::DeserializeOwned + serde::Serialize {
     const MAX_STR_LEN: u32;
+
+    /// Writes the value's JSON encoding. By default, this goes through `serde_json` but types on
+    /// hot paths can write themselves out directly to avoid its overhead. The output must be
+    /// something that deserializes back to the same value.
+    fn write_json(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        serde_json::to_writer(writer, self).map_err(io::Error::from)
+    }
+}
+
+fn json_len<E: SerializableJsonValue>(value: &E) -> usize {
+    let mut counter = WriteByteCounter::default();
+    value.write_json(&mut counter).unwrap();
+    counter.0
 }
 
 impl<E: SerializableJsonValue> Codec<()> for JsonValue<E> {
@@ -499,23 +1027,105 @@ impl<E: SerializableJsonValue> Codec<()> for JsonValue<E> {
     }
 
     fn encode(&self, _args: (), cursor: &mut impl BufMut) {
-        let encoded = serde_json::to_string(&self.0).unwrap();
-        NetString::from_string(encoded).encode(E::MAX_STR_LEN, cursor);
+        // The length precedes the text so we buffer it rather than serializing the value twice.
+        let mut encoded = Vec::new();
+        self.0.write_json(&mut encoded).unwrap();
+
+        VarUint(u32::try_from(encoded.len()).expect(TOO_BIG_ERR)).encode((), cursor);
+        cursor.put_slice(&encoded);
     }
-}
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        let mut counter = WriteCodepointCounter::default();
+        self.0.write_json(&mut counter)?;
+
+        let str_len = counter
+            .utf16_len()
+            .expect("serialized JSON should be valid UTF-8");
+        anyhow::ensure!(
+            str_len <= E::MAX_STR_LEN as usize,
+            "{} can be at most {} UTF-16 code unit(s) when serialized but ended up being {str_len}.",
+            type_name::<E>(),
+            E::MAX_STR_LEN,
+        );
+
+        Ok(())
+    }
+}
 
 impl<E: SerializableJsonValue> SizedCodec<()> for JsonValue<E> {
     fn size(&self, _args: ()) -> usize {
-        let mut counter = WriteByteCounter::default();
-        serde_json::to_writer(&mut counter, &self.0).unwrap();
+        let len = json_len(&self.0);
+        VarUint(u32::try_from(len).expect(TOO_BIG_ERR)).size(()) + len
+    }
+}
+
+// PreEncodedJson
+#[derive_where(Debug, Clone)]
+pub struct PreEncodedJson<E> {
+    _ty: PhantomData<fn() -> E>,
+    encoded: NetString,
+}
+
+impl<E: SerializableJsonValue> PreEncodedJson<E> {
+    pub fn new(value: &E) -> Self {
+        let mut encoded = Vec::new();
+        value.write_json(&mut encoded).unwrap();
+
+        Self {
+            _ty: PhantomData,
+            encoded: NetString::from_string(String::from_utf8(encoded).unwrap()),
+        }
+    }
+
+    pub fn encoded(&self) -> &NetString {
+        &self.encoded
+    }
+
+    pub fn parse(&self) -> anyhow::Result<E> {
+        Ok(serde_json::from_str(&self.encoded)?)
+    }
+}
 
-        VarUint(u32::try_from(counter.0).expect(TOO_BIG_ERR)).size(()) + counter.0
+impl<E: SerializableJsonValue> From<&'_ JsonValue<E>> for PreEncodedJson<E> {
+    fn from(value: &'_ JsonValue<E>) -> Self {
+        Self::new(&value.0)
+    }
+}
+
+impl<E: SerializableJsonValue> Codec<()> for PreEncodedJson<E> {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let encoded = NetString::decode(E::MAX_STR_LEN, src, cursor)?;
+
+        // Ensure that the text actually describes a valid value.
+        let _: E = serde_json::from_str(&encoded)?;
+
+        Ok(Self {
+            _ty: PhantomData,
+            encoded,
+        })
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.encoded.encode(E::MAX_STR_LEN, cursor);
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        self.encoded.validate(E::MAX_STR_LEN)
+    }
+}
+
+impl<E: SerializableJsonValue> SizedCodec<()> for PreEncodedJson<E> {
+    fn size(&self, _args: ()) -> usize {
+        self.encoded.size(E::MAX_STR_LEN)
     }
 }
 
 // Chat
 pub type Chat = JsonValue<RootChatComponent>;
 
+pub type PreEncodedChat = PreEncodedJson<RootChatComponent>;
+
 #[derive(Debug, Clone)]
 pub struct RootChatComponent(pub SmallVec<[ChatComponent; 1]>);
 
@@ -565,6 +1175,23 @@ impl<'de> Deserialize<'de> for RootChatComponent {
                 }
                 Ok(RootChatComponent(buffer))
             }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'a>,
+            {
+                let component =
+                    ChatComponent::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+
+                Ok(component.into())
+            }
+
+            fn visit_str<E>(self, text: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(ChatComponent::text(text).into())
+            }
         }
 
         deserializer.deserialize_any(Visitor)
@@ -572,9 +1199,26 @@ impl<'de> Deserialize<'de> for RootChatComponent {
 }
 impl SerializableJsonValue for RootChatComponent {
     const MAX_STR_LEN: u32 = 262144;
+
+    // Chat is written out directly since it is sent often (e.g. in every disconnect and chat
+    // message) and this produces exactly the same text as `serde_json` would.
+    fn write_json(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        if self.0.len() == 1 {
+            self.0[0].write_json(writer)
+        } else {
+            write_json_array(writer, &self.0, ChatComponent::write_json)
+        }
+    }
+}
+
+impl From<ChatComponent> for RootChatComponent {
+    fn from(component: ChatComponent) -> Self {
+        Self(SmallVec::from_buf([component]))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ChatComponent {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
@@ -618,17 +1262,435 @@ pub struct ChatComponent {
     #[serde(rename = "hoverEvent")]
     pub hover_event: Option<ChatHoverEvent>,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub extra: Vec<ChatComponent>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+impl ChatComponent {
+    /// Creates a plain text component. The remaining methods set its style and append children,
+    /// which inherit that style unless they override it.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = Some(true);
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = Some(true);
+        self
+    }
+
+    pub fn underlined(mut self) -> Self {
+        self.underlined = Some(true);
+        self
+    }
+
+    pub fn append(mut self, child: ChatComponent) -> Self {
+        self.extra.push(child);
+        self
+    }
+
+    fn write_json(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        let mut obj = JsonObjectWriter::new(writer)?;
+        obj.opt_str("text", &self.text)?;
+        obj.opt_str("translate", &self.translate)?;
+        obj.opt_str("keybind", &self.keybind)?;
+        obj.opt_bool("bold", self.bold)?;
+        obj.opt_bool("italic", self.italic)?;
+        obj.opt_bool("underlined", self.underlined)?;
+        obj.opt_bool("strikethrough", self.strikethrough)?;
+        obj.opt_bool("obfuscated", self.obfuscated)?;
+        obj.opt_str("font", &self.font)?;
+        obj.opt_str("color", &self.color)?;
+        obj.opt_str("insertion", &self.insertion)?;
+
+        if let Some(event) = &self.click_event {
+            let writer = obj.key("clickEvent")?;
+            let mut obj = JsonObjectWriter::new(writer)?;
+            obj.str("action", event.action.name())?;
+            obj.str("value", &event.value)?;
+            obj.end()?;
+        }
+
+        if let Some(event) = &self.hover_event {
+            let writer = obj.key("hoverEvent")?;
+            let mut obj = JsonObjectWriter::new(writer)?;
+            obj.opt_str("show_text", &event.show_text)?;
+
+            if let Some(item) = &event.show_item {
+                let writer = obj.key("show_item")?;
+                let mut obj = JsonObjectWriter::new(writer)?;
+                obj.str("id", &item.id)?;
+                write!(obj.key("count")?, "{}", item.count)?;
+                obj.opt_str("tag", &item.tag)?;
+                obj.end()?;
+            }
+
+            obj.opt_str("show_entity", &event.show_entity)?;
+            obj.end()?;
+        }
+
+        if !self.extra.is_empty() {
+            write_json_array(obj.key("extra")?, &self.extra, Self::write_json)?;
+        }
+
+        obj.end()
+    }
+}
+
+// Legacy formatting codes
+const LEGACY_CODE_PREFIX: char = '§';
+
+const LEGACY_COLORS: [(char, &str); 16] = [
+    ('0', "black"),
+    ('1', "dark_blue"),
+    ('2', "dark_green"),
+    ('3', "dark_aqua"),
+    ('4', "dark_red"),
+    ('5', "dark_purple"),
+    ('6', "gold"),
+    ('7', "gray"),
+    ('8', "dark_gray"),
+    ('9', "blue"),
+    ('a', "green"),
+    ('b', "aqua"),
+    ('c', "red"),
+    ('d', "light_purple"),
+    ('e', "yellow"),
+    ('f', "white"),
+];
+
+/// The format codes in the order of [`LegacyStyle::formats`].
+const LEGACY_FORMATS: [char; 5] = ['k', 'l', 'm', 'n', 'o'];
+
+impl RootChatComponent {
+    /// Parses text styled with `§`-prefixed formatting codes into an empty component whose `extra`
+    /// children are the differently styled runs of text. Unknown codes are dropped, as the vanilla
+    /// client does.
+    pub fn from_legacy(text: &str) -> Self {
+        let mut root = ChatComponent::text("");
+        let mut style = LegacyStyle::default();
+        let mut run = String::new();
+        let mut chars = text.chars();
+
+        while let Some(ch) = chars.next() {
+            if ch != LEGACY_CODE_PREFIX {
+                run.push(ch);
+                continue;
+            }
+
+            let Some(code) = chars.next() else {
+                run.push(ch);
+                break;
+            };
+
+            let Some(next_style) = style.with_code(code) else {
+                continue;
+            };
+
+            if next_style != style && !run.is_empty() {
+                root.extra.push(style.component(mem::take(&mut run)));
+            }
+
+            style = next_style;
+        }
+
+        if !run.is_empty() {
+            root.extra.push(style.component(run));
+        }
+
+        root.into()
+    }
+
+    /// Flattens this component into text styled with `§`-prefixed formatting codes. Only `text`
+    /// contents are kept and colors which have no legacy code are dropped.
+    pub fn to_legacy(&self) -> String {
+        let mut out = String::new();
+        let mut current = LegacyStyle::default();
+
+        // Every component after the first inherits the style of the first.
+        let Some((first, rest)) = self.0.split_first() else {
+            return out;
+        };
+
+        first.write_legacy(LegacyStyle::default(), &mut current, &mut out);
+
+        let base = LegacyStyle::of(first, LegacyStyle::default());
+        for component in rest {
+            component.write_legacy(base, &mut current, &mut out);
+        }
+
+        out
+    }
+}
+
+impl ChatComponent {
+    fn write_legacy(&self, parent: LegacyStyle, current: &mut LegacyStyle, out: &mut String) {
+        let style = LegacyStyle::of(self, parent);
+
+        if let Some(text) = self.text.as_deref().filter(|text| !text.is_empty()) {
+            current.transition_to(style, out);
+            out.push_str(text);
+        }
+
+        for child in &self.extra {
+            child.write_legacy(style, current, out);
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+struct LegacyStyle {
+    /// An index into [`LEGACY_COLORS`].
+    color: Option<usize>,
+    formats: [bool; 5],
+}
+
+impl LegacyStyle {
+    fn of(component: &ChatComponent, parent: Self) -> Self {
+        let color = match &component.color {
+            Some(color) => LEGACY_COLORS.iter().position(|(_, name)| name == color),
+            None => parent.color,
+        };
+
+        let mut formats = parent.formats;
+        for (format, value) in formats.iter_mut().zip(legacy_formats(component)) {
+            if let Some(value) = value {
+                *format = value;
+            }
+        }
+
+        Self { color, formats }
+    }
+
+    fn with_code(self, code: char) -> Option<Self> {
+        let code = code.to_ascii_lowercase();
+
+        if code == 'r' {
+            Some(Self::default())
+        } else if let Some(color) = LEGACY_COLORS.iter().position(|&(color, _)| color == code) {
+            // Like the vanilla client, colors reset the active formats.
+            Some(Self {
+                color: Some(color),
+                formats: [false; 5],
+            })
+        } else {
+            let index = LEGACY_FORMATS.iter().position(|&format| format == code)?;
+            let mut formats = self.formats;
+            formats[index] = true;
+            Some(Self { formats, ..self })
+        }
+    }
+
+    fn component(self, text: String) -> ChatComponent {
+        let [obfuscated, bold, strikethrough, underlined, italic] =
+            self.formats.map(|format| format.then_some(true));
+
+        ChatComponent {
+            color: self.color.map(|color| LEGACY_COLORS[color].1.to_string()),
+            obfuscated,
+            bold,
+            strikethrough,
+            underlined,
+            italic,
+            ..ChatComponent::text(text)
+        }
+    }
+
+    fn transition_to(&mut self, target: Self, out: &mut String) {
+        if *self == target {
+            return;
+        }
+
+        let only_adds_formats = self.color == target.color
+            && self
+                .formats
+                .iter()
+                .zip(target.formats)
+                .all(|(&old, new)| !old || new);
+
+        if !only_adds_formats {
+            out.push(LEGACY_CODE_PREFIX);
+            out.push(target.color.map_or('r', |color| LEGACY_COLORS[color].0));
+        }
+
+        for (i, &format) in target.formats.iter().enumerate() {
+            if format && !(only_adds_formats && self.formats[i]) {
+                out.push(LEGACY_CODE_PREFIX);
+                out.push(LEGACY_FORMATS[i]);
+            }
+        }
+
+        *self = target;
+    }
+}
+
+fn legacy_formats(component: &ChatComponent) -> [Option<bool>; 5] {
+    [
+        component.obfuscated,
+        component.bold,
+        component.strikethrough,
+        component.underlined,
+        component.italic,
+    ]
+}
+
+// JSON writing helpers which produce the same compact output as `serde_json`.
+struct JsonObjectWriter<'a, W> {
+    writer: &'a mut W,
+    is_empty: bool,
+}
+
+impl<'a, W: io::Write> JsonObjectWriter<'a, W> {
+    fn new(writer: &'a mut W) -> io::Result<Self> {
+        writer.write_all(b"{")?;
+        Ok(Self {
+            writer,
+            is_empty: true,
+        })
+    }
+
+    /// Writes the key of the next field, returning the writer to which its value should be written.
+    fn key(&mut self, key: &str) -> io::Result<&mut W> {
+        if !mem::take(&mut self.is_empty) {
+            self.writer.write_all(b",")?;
+        }
+
+        write_json_str(self.writer, key)?;
+        self.writer.write_all(b":")?;
+        Ok(self.writer)
+    }
+
+    fn str(&mut self, key: &str, value: &str) -> io::Result<()> {
+        write_json_str(self.key(key)?, value)
+    }
+
+    fn opt_str(&mut self, key: &str, value: &Option<String>) -> io::Result<()> {
+        match value {
+            Some(value) => self.str(key, value),
+            None => Ok(()),
+        }
+    }
+
+    fn opt_bool(&mut self, key: &str, value: Option<bool>) -> io::Result<()> {
+        match value {
+            Some(value) => write!(self.key(key)?, "{value}"),
+            None => Ok(()),
+        }
+    }
+
+    fn end(self) -> io::Result<()> {
+        self.writer.write_all(b"}")
+    }
+}
+
+fn write_json_array<W: io::Write, T>(
+    writer: &mut W,
+    elems: &[T],
+    mut write_elem: impl FnMut(&T, &mut W) -> io::Result<()>,
+) -> io::Result<()> {
+    writer.write_all(b"[")?;
+
+    for (i, elem) in elems.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+
+        write_elem(elem, writer)?;
+    }
+
+    writer.write_all(b"]")
+}
+
+fn write_json_str(writer: &mut impl io::Write, str: &str) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+
+    let mut start = 0;
+    for (i, byte) in str.bytes().enumerate() {
+        let escape: &[u8] = match byte {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x08 => b"\\b",
+            0x0C => b"\\f",
+            0x00..=0x1F => b"",
+            _ => continue,
+        };
+
+        writer.write_all(&str.as_bytes()[start..i])?;
+        start = i + 1;
+
+        if escape.is_empty() {
+            write!(writer, "\\u{byte:04x}")?;
+        } else {
+            writer.write_all(escape)?;
+        }
+    }
+
+    writer.write_all(&str.as_bytes()[start..])?;
+    writer.write_all(b"\"")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ChatClickEvent {
-    pub action: String,
+    pub action: ChatClickAction,
     pub value: String,
 }
 
+/// What the client does with a [`ChatClickEvent`]'s value when it is clicked. Events with any other
+/// action fail to deserialize.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(rename_all = "snake_case")]
+pub enum ChatClickAction {
+    OpenUrl,
+    RunCommand,
+    SuggestCommand,
+    ChangePage,
+    CopyToClipboard,
+}
+
+impl ChatClickAction {
+    const ALL: [Self; 5] = [
+        Self::OpenUrl,
+        Self::RunCommand,
+        Self::SuggestCommand,
+        Self::ChangePage,
+        Self::CopyToClipboard,
+    ];
+
+    /// The name of this action in the `action` field of a click event.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::OpenUrl => "open_url",
+            Self::RunCommand => "run_command",
+            Self::SuggestCommand => "suggest_command",
+            Self::ChangePage => "change_page",
+            Self::CopyToClipboard => "copy_to_clipboard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ChatHoverEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub show_text: Option<String>,
@@ -641,6 +1703,7 @@ pub struct ChatHoverEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ChatShownItem {
     pub id: String,
     pub count: i32,
@@ -650,6 +1713,9 @@ pub struct ChatShownItem {
 }
 
 // Option
+/// An optional value prefixed by a `bool` indicating whether it is present. Absent values are
+/// encoded as `false` with no payload; present values as `true` followed by the value, even if the
+/// value itself encodes to nothing more than an empty length prefix.
 impl<A, T: Codec<A>> Codec<A> for Option<T> {
     fn decode(args: A, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
         Ok(if bool::decode((), src, cursor)? {
@@ -667,6 +1733,14 @@ impl<A, T: Codec<A>> Codec<A> for Option<T> {
             false.encode((), cursor);
         }
     }
+
+    fn validate(&self, args: A) -> anyhow::Result<()> {
+        if let Some(inner) = self {
+            inner.validate(args)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<A, T: SizedCodec<A>> SizedCodec<A> for Option<T> {
@@ -679,99 +1753,2731 @@ impl<A, T: SizedCodec<A>> SizedCodec<A> for Option<T> {
     }
 }
 
-// UUID
-#[derive(Debug, Copy, Clone)]
-pub struct Uuid(pub u128);
+// TrailingOptional
+/// An optional value with no presence prefix. It is present iff any bytes remain in the packet, so
+/// it can only appear as the final field of a packet.
+#[derive(Debug, Clone, Default)]
+pub struct TrailingOptional<T>(pub Option<T>);
 
-impl Codec<()> for Uuid {
-    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
-        Ok(Self(u128::decode((), src, cursor)?))
+impl<A, T: Codec<A>> Codec<A> for TrailingOptional<T> {
+    fn decode(args: A, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Ok(Self(if cursor.is_empty() {
+            None
+        } else {
+            Some(T::decode(args, src, cursor)?)
+        }))
     }
 
-    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
-        self.0.encode((), cursor)
+    fn encode(&self, args: A, cursor: &mut impl BufMut) {
+        if let Some(inner) = &self.0 {
+            inner.encode(args, cursor);
+        }
+    }
+
+    fn validate(&self, args: A) -> anyhow::Result<()> {
+        if let Some(inner) = &self.0 {
+            inner.validate(args)?;
+        }
+
+        Ok(())
     }
 }
 
-impl SizedCodec<()> for Uuid {
-    fn size(&self, _args: ()) -> usize {
-        self.0.size(())
+impl<A, T: SizedCodec<A>> SizedCodec<A> for TrailingOptional<T> {
+    fn size(&self, args: A) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.size(args))
     }
 }
 
-// Byte Array
+// IdOr
+/// Either a reference into a registry or an inline value. On the wire this is a `VarInt` which is
+/// the registry id plus one, or zero followed by the inline value.
 #[derive(Debug, Clone)]
-pub struct ByteArray(Bytes);
-
-impl Codec<()> for ByteArray {
-    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
-        let len = VarInt::decode((), src, cursor)?.0;
+pub enum IdOr<T> {
+    Id(u32),
+    Inline(T),
+}
 
-        let Some(data) = cursor.read_slice(len as usize) else {
-			anyhow::bail!(
-				"Expected {len} byte(s) of data for the byte array; found {} (location: {}).",
-				cursor.remaining().len(),
-				cursor.format_location(),
-			);
-		};
+impl<A, T: Codec<A>> Codec<A> for IdOr<T> {
+    fn decode(args: A, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        match VarUint::decode((), src, cursor)?.0 {
+            0 => Ok(Self::Inline(T::decode(args, src, cursor)?)),
+            id => Ok(Self::Id(id - 1)),
+        }
+    }
 
-        Ok(ByteArray(src.freeze_range(data)))
+    fn encode(&self, args: A, cursor: &mut impl BufMut) {
+        match self {
+            Self::Id(id) => VarUint(id + 1).encode((), cursor),
+            Self::Inline(inner) => {
+                VarUint(0).encode((), cursor);
+                inner.encode(args, cursor);
+            }
+        }
     }
 
-    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
-        VarUint(u32::try_from(self.0.len()).expect(TOO_BIG_ERR)).encode((), cursor);
-        self.0.encode((), cursor);
+    fn validate(&self, args: A) -> anyhow::Result<()> {
+        match self {
+            Self::Id(id) => {
+                anyhow::ensure!(
+                    *id < i32::MAX as u32,
+                    "Registry id {id} is too large to be sent as an `IdOr`.",
+                );
+                Ok(())
+            }
+            Self::Inline(inner) => inner.validate(args),
+        }
     }
 }
 
-impl SizedCodec<()> for ByteArray {
-    fn size(&self, _args: ()) -> usize {
-        VarUint(u32::try_from(self.0.len()).expect(TOO_BIG_ERR)).size(()) + self.0.len()
+impl<A, T: SizedCodec<A>> SizedCodec<A> for IdOr<T> {
+    fn size(&self, args: A) -> usize {
+        match self {
+            Self::Id(id) => VarUint(id + 1).size(()),
+            Self::Inline(inner) => VarUint(0).size(()) + inner.size(args),
+        }
     }
 }
 
-// Vec
-impl<A, F, T> Codec<F> for Vec<T>
-where
-    T: Codec<A>,
-    F: FnMut() -> A,
-{
-    fn decode(mut args: F, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
-        let len = VarUint::decode((), src, cursor)?.0;
-        let mut builder = Vec::with_capacity(len as usize);
+// OneOf
+macro_rules! one_of {
+	($(
+		$(#[$attr:meta])*
+		$name:ident { $($variant:ident($ty:ident: $args:ident) = $tag:tt),* $(,)? }
+	)*) => {$(
+		$(#[$attr])*
+		#[derive(Debug, Clone)]
+		pub enum $name<$($ty),*> {
+			$($variant($ty),)*
+		}
 
-        for _ in 0..len {
-            builder.push(T::decode(args(), src, cursor)?);
-        }
+		impl<$($ty: Codec<$args>, $args),*> Codec<($($args,)*)> for $name<$($ty),*> {
+			fn decode(
+				args: ($($args,)*),
+				src: &impl Snip,
+				cursor: &mut ByteCursor,
+			) -> anyhow::Result<Self> {
+				let tag = VarUint::decode((), src, cursor)?.0;
+
+				match tag {
+					$($tag => Ok(Self::$variant($ty::decode(args.$tag, src, cursor)?)),)*
+					_ => anyhow::bail!(
+						"Unknown {} tag {tag} (location: {}).",
+						stringify!($name),
+						cursor.format_location(),
+					),
+				}
+			}
 
-        Ok(builder)
-    }
+			fn encode(&self, args: ($($args,)*), cursor: &mut impl BufMut) {
+				match self {
+					$(Self::$variant(inner) => {
+						VarUint($tag).encode((), cursor);
+						inner.encode(args.$tag, cursor);
+					})*
+				}
+			}
 
-    fn encode(&self, mut args: F, cursor: &mut impl BufMut) {
-        VarUint(u32::try_from(self.len()).expect("vector is too large to send over the network"))
-            .encode((), cursor);
+			fn validate(&self, args: ($($args,)*)) -> anyhow::Result<()> {
+				match self {
+					$(Self::$variant(inner) => inner.validate(args.$tag),)*
+				}
+			}
+		}
 
-        for elem in self {
-            elem.encode(args(), cursor);
+		impl<$($ty: SizedCodec<$args>, $args),*> SizedCodec<($($args,)*)> for $name<$($ty),*> {
+			fn size(&self, args: ($($args,)*)) -> usize {
+				match self {
+					$(Self::$variant(inner) => VarUint($tag).size(()) + inner.size(args.$tag),)*
+				}
+			}
+		}
+	)*};
+}
+
+one_of! {
+    /// One of two values, preceded on the wire by a `VarInt` tag giving the index of the variant
+    /// which follows. Each variant is coded with its own element of the argument tuple.
+    OneOf2 {
+        First(T0: A0) = 0,
+        Second(T1: A1) = 1,
+    }
+
+    /// Like [`OneOf2`] but with a third variant.
+    OneOf3 {
+        First(T0: A0) = 0,
+        Second(T1: A1) = 1,
+        Third(T2: A2) = 2,
+    }
+}
+
+// UUID
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub struct Uuid(pub u128);
+
+impl Uuid {
+    pub const fn from_u128(value: u128) -> Self {
+        Self(value)
+    }
+
+    pub const fn as_u128(self) -> u128 {
+        self.0
+    }
+
+    /// Parses a UUID in its canonical `8-4-4-4-12` hexadecimal form, as it appears in JSON.
+    pub fn from_hyphenated_str(str: &str) -> anyhow::Result<Self> {
+        const GROUP_LENS: [usize; 5] = [8, 4, 4, 4, 12];
+
+        let mut value = 0u128;
+        let mut groups = str.split('-');
+
+        for expected_len in GROUP_LENS {
+            let group = groups
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("UUID {str:?} has too few groups."))?;
+
+            anyhow::ensure!(
+                group.len() == expected_len && group.bytes().all(|c| c.is_ascii_hexdigit()),
+                "UUID {str:?} has a malformed group {group:?}; expected {expected_len} hex digits.",
+            );
+
+            // The group is all hex digits so this cannot fail.
+            value = (value << (expected_len * 4)) | u128::from_str_radix(group, 16).unwrap();
         }
+
+        anyhow::ensure!(groups.next().is_none(), "UUID {str:?} has too many groups.");
+
+        Ok(Self(value))
     }
 }
 
-impl<A, F, T> SizedCodec<F> for Vec<T>
-where
-    T: SizedCodec<A>,
-    F: FnMut() -> A,
-{
-    fn size(&self, mut args: F) -> usize {
-        let mut accum = VarUint(
-            u32::try_from(self.len()).expect("vector is too large to send over the network"),
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            v >> 96,
+            (v >> 80) & 0xFFFF,
+            (v >> 64) & 0xFFFF,
+            (v >> 48) & 0xFFFF,
+            v & 0xFFFF_FFFF_FFFF,
         )
-        .size(());
+    }
+}
 
-        for elem in self {
-            accum += elem.size(args());
+impl Codec<()> for Uuid {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Ok(Self(u128::decode((), src, cursor)?))
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.0.encode((), cursor)
+    }
+}
+
+impl SizedCodec<()> for Uuid {
+    fn size(&self, _args: ()) -> usize {
+        self.0.size(())
+    }
+}
+
+// Position
+/// A block position packed into a 64-bit integer: 26 bits of X, 26 bits of Z, then 12 bits of Y,
+/// each stored in two's complement.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub const MIN_XZ: i32 = -(1 << 25);
+    pub const MAX_XZ: i32 = (1 << 25) - 1;
+    pub const MIN_Y: i32 = -(1 << 11);
+    pub const MAX_Y: i32 = (1 << 11) - 1;
+
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn is_representable(&self) -> bool {
+        (Self::MIN_XZ..=Self::MAX_XZ).contains(&self.x)
+            && (Self::MIN_Y..=Self::MAX_Y).contains(&self.y)
+            && (Self::MIN_XZ..=Self::MAX_XZ).contains(&self.z)
+    }
+
+    pub fn from_packed(packed: i64) -> Self {
+        // Arithmetic shifts sign-extend each field.
+        Self {
+            x: (packed >> 38) as i32,
+            y: (packed << 52 >> 52) as i32,
+            z: (packed << 26 >> 38) as i32,
+        }
+    }
+
+    /// Packs the position into its 64-bit representation. Coordinates outside the representable
+    /// range are truncated to their low bits so callers should check [`Position::is_representable`]
+    /// (or `validate` the position) first.
+    pub fn to_packed(self) -> i64 {
+        ((self.x as i64 & 0x3FFFFFF) << 38)
+            | ((self.z as i64 & 0x3FFFFFF) << 12)
+            | (self.y as i64 & 0xFFF)
+    }
+}
+
+impl Codec<()> for Position {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Ok(Self::from_packed(i64::decode((), src, cursor)?))
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        debug_assert!(
+            self.is_representable(),
+            "Position {self:?} cannot be represented over the network."
+        );
+
+        self.to_packed().encode((), cursor);
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.is_representable(),
+            "Position {self:?} cannot be represented over the network."
+        );
+
+        Ok(())
+    }
+}
+
+impl SizedCodec<()> for Position {
+    fn size(&self, _args: ()) -> usize {
+        mem::size_of::<i64>()
+    }
+}
+
+// Angle
+/// A rotation in steps of 1/256 of a full turn.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    /// Converts an angle in degrees to the nearest representable step, wrapping angles outside of
+    /// `0..360`.
+    pub fn from_degrees(degrees: f32) -> Self {
+        let steps = (degrees.rem_euclid(360.0) / 360.0 * 256.0).round() as u32;
+        Self((steps % 256) as u8)
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        self.0 as f32 * (360.0 / 256.0)
+    }
+}
+
+impl Codec<()> for Angle {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Ok(Self(u8::decode((), src, cursor)?))
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.0.encode((), cursor)
+    }
+}
+
+impl SizedCodec<()> for Angle {
+    fn size(&self, _args: ()) -> usize {
+        self.0.size(())
+    }
+}
+
+// TeleportFlags
+/// The flags byte of the "Synchronize Player Position" packet, indicating which of the position
+/// and rotation fields are relative to the player's current values rather than absolute.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub struct TeleportFlags(u8);
+
+macro_rules! teleport_flags {
+    ($($bit:literal => $is_name:ident, $with_name:ident;)*) => {
+        impl TeleportFlags {
+            const ALL_BITS: u8 = $($bit)|*;
+
+            $(
+                pub fn $is_name(self) -> bool {
+                    self.0 & $bit != 0
+                }
+
+                pub fn $with_name(self, relative: bool) -> Self {
+                    if relative {
+                        Self(self.0 | $bit)
+                    } else {
+                        Self(self.0 & !$bit)
+                    }
+                }
+            )*
         }
+    };
+}
 
-        accum
+teleport_flags! {
+    0x01 => is_x_relative, with_x_relative;
+    0x02 => is_y_relative, with_y_relative;
+    0x04 => is_z_relative, with_z_relative;
+    0x08 => is_yaw_relative, with_yaw_relative;
+    0x10 => is_pitch_relative, with_pitch_relative;
+}
+
+impl TeleportFlags {
+    /// Flags for a teleport where every field is absolute.
+    pub fn absolute() -> Self {
+        Self(0)
+    }
+
+    /// Builds flags from their wire representation, returning `None` if an unknown bit is set.
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        (bits & !Self::ALL_BITS == 0).then_some(Self(bits))
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+impl Codec<()> for TeleportFlags {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let bits = u8::decode((), src, cursor)?;
+        let Some(flags) = Self::from_bits(bits) else {
+            anyhow::bail!(
+                "Teleport flags {bits:#04x} have unknown bit(s) set (location: {}).",
+                cursor.format_location(),
+            );
+        };
+
+        Ok(flags)
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.0.encode((), cursor)
+    }
+}
+
+impl SizedCodec<()> for TeleportFlags {
+    fn size(&self, _args: ()) -> usize {
+        self.0.size(())
+    }
+}
+
+// FixedPoint
+/// A number encoded as an `i32` holding the value scaled by `2^SHIFT`, which legacy packets use for
+/// positions (with a `SHIFT` of 5) and velocities. Encoding rounds to the nearest representable
+/// value.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct FixedPoint<const SHIFT: u32>(pub f64);
+
+impl<const SHIFT: u32> FixedPoint<SHIFT> {
+    const SCALE: f64 = (1u64 << SHIFT) as f64;
+
+    pub fn from_raw(raw: i32) -> Self {
+        Self(raw as f64 / Self::SCALE)
+    }
+
+    /// The scaled value sent over the network, saturating if it doesn't fit in an `i32`.
+    pub fn to_raw(self) -> i32 {
+        (self.0 * Self::SCALE).round() as i32
+    }
+}
+
+impl<const SHIFT: u32> Codec<()> for FixedPoint<SHIFT> {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Ok(Self::from_raw(i32::decode((), src, cursor)?))
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.to_raw().encode((), cursor)
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        let scaled = (self.0 * Self::SCALE).round();
+
+        anyhow::ensure!(
+            (i32::MIN as f64..=i32::MAX as f64).contains(&scaled),
+            "Fixed-point value {} with {SHIFT} fractional bit(s) cannot be represented by an i32.",
+            self.0,
+        );
+
+        Ok(())
+    }
+}
+
+impl<const SHIFT: u32> SizedCodec<()> for FixedPoint<SHIFT> {
+    fn size(&self, _args: ()) -> usize {
+        mem::size_of::<i32>()
+    }
+}
+
+// CanonicalFloat
+/// A float which must be finite, for fields such as positions and velocities where NaN and the
+/// infinities are meaningless and most likely indicate corrupt data.
+#[derive(Debug, Copy, Clone, Default, PartialEq, PartialOrd)]
+pub struct CanonicalFloat<T>(pub T);
+
+macro_rules! impl_canonical_float {
+    ($($ty:ty),*$(,)?) => {$(
+		impl Codec<()> for CanonicalFloat<$ty> {
+			fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+				let value = <$ty>::decode((), src, cursor)?;
+				anyhow::ensure!(
+					value.is_finite(),
+					"Expected a finite {} but got {value} (location: {}).",
+					stringify!($ty),
+					cursor.format_location(),
+				);
+
+				Ok(Self(value))
+			}
+
+			fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+				self.0.encode((), cursor)
+			}
+
+			fn validate(&self, _args: ()) -> anyhow::Result<()> {
+				anyhow::ensure!(
+					self.0.is_finite(),
+					"Expected a finite {} but got {}.",
+					stringify!($ty),
+					self.0,
+				);
+
+				Ok(())
+			}
+		}
+
+		impl SizedCodec<()> for CanonicalFloat<$ty> {
+			fn size(&self, _args: ()) -> usize {
+				mem::size_of::<$ty>()
+			}
+		}
+	)*};
+}
+
+impl_canonical_float!(f32, f64);
+
+// BitSet
+/// A growable set of bits encoded as a `VarInt`-prefixed array of big-endian longs. Bit `i` is bit
+/// `i % 64` of word `i / 64`.
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
+pub struct BitSet {
+    // Never ends in a zero word so that equal sets compare equal.
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_words(mut words: Vec<u64>) -> Self {
+        while words.last() == Some(&0) {
+            words.pop();
+        }
+
+        Self { words }
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// The number of bits this set can hold without growing. Every bit at or after this index is
+    /// unset.
+    pub fn len_bits(&self) -> usize {
+        self.words.len() * 64
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.words
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        let word_idx = index / 64;
+        let mask = 1 << (index % 64);
+
+        if value {
+            if word_idx >= self.words.len() {
+                self.words.resize(word_idx + 1, 0);
+            }
+
+            self.words[word_idx] |= mask;
+        } else if let Some(word) = self.words.get_mut(word_idx) {
+            *word &= !mask;
+
+            while self.words.last() == Some(&0) {
+                self.words.pop();
+            }
+        }
+    }
+
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        iter_word_ones(&self.words)
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+}
+
+fn iter_word_ones(words: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    words.iter().enumerate().flat_map(|(word_idx, &word)| {
+        (0..64)
+            .filter(move |bit| word & (1 << bit) != 0)
+            .map(move |bit| word_idx * 64 + bit)
+    })
+}
+
+fn decode_words(
+    count: usize,
+    src: &impl Snip,
+    cursor: &mut ByteCursor,
+) -> anyhow::Result<Vec<u64>> {
+    anyhow::ensure!(
+        count.saturating_mul(8) <= cursor.len(),
+        "Bit set of {count} word(s) is longer than the remaining {} byte(s) (location: {}).",
+        cursor.len(),
+        cursor.format_location(),
+    );
+
+    (0..count).map(|_| u64::decode((), src, cursor)).collect()
+}
+
+impl Codec<()> for BitSet {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let count = VarUint::decode((), src, cursor)?.0;
+        Ok(Self::from_words(decode_words(count as usize, src, cursor)?))
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        VarUint(u32::try_from(self.words.len()).expect(TOO_BIG_ERR)).encode((), cursor);
+
+        for word in &self.words {
+            word.encode((), cursor);
+        }
+    }
+}
+
+impl SizedCodec<()> for BitSet {
+    fn size(&self, _args: ()) -> usize {
+        VarUint(u32::try_from(self.words.len()).expect(TOO_BIG_ERR)).size(()) + self.words.len() * 8
+    }
+}
+
+// FixedBitSet
+/// A set of exactly `N` bits encoded as `ceil(N / 64)` big-endian longs without a length prefix.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct FixedBitSet<const N: usize> {
+    words: Box<[u64]>,
+}
+
+impl<const N: usize> Default for FixedBitSet<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> FixedBitSet<N> {
+    const WORD_COUNT: usize = N.div_ceil(64);
+
+    pub fn new() -> Self {
+        Self {
+            words: vec![0; Self::WORD_COUNT].into_boxed_slice(),
+        }
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn len_bits(&self) -> usize {
+        N
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        assert!(
+            index < N,
+            "bit index {index} is out of bounds for a set of {N} bit(s)"
+        );
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(
+            index < N,
+            "bit index {index} is out of bounds for a set of {N} bit(s)"
+        );
+
+        let mask = 1 << (index % 64);
+        if value {
+            self.words[index / 64] |= mask;
+        } else {
+            self.words[index / 64] &= !mask;
+        }
+    }
+
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        iter_word_ones(&self.words)
+    }
+}
+
+impl<const N: usize> Codec<()> for FixedBitSet<N> {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let words = decode_words(Self::WORD_COUNT, src, cursor)?;
+
+        // Bits past `N` in the last word are padding and should be unset.
+        if let Some(index) = iter_word_ones(&words).find(|&index| index >= N) {
+            anyhow::bail!(
+                "Fixed bit set of {N} bit(s) has bit {index} set (location: {}).",
+                cursor.format_location(),
+            );
+        }
+
+        Ok(Self {
+            words: words.into_boxed_slice(),
+        })
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        for word in self.words.iter() {
+            word.encode((), cursor);
+        }
+    }
+}
+
+impl<const N: usize> SizedCodec<()> for FixedBitSet<N> {
+    fn size(&self, _args: ()) -> usize {
+        Self::WORD_COUNT * 8
+    }
+}
+
+// EnumSet
+/// A set of [`ProtoEnum`] variants encoded as a fixed bit set of `ceil(n / 8)` bytes, where `n` is
+/// the number of variants. A variant's bit is given by its ordinal rather than its id: bit `i` is
+/// bit `i % 8` of byte `i / 8`.
+#[derive_where(Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub struct EnumSet<E> {
+    bits: u64,
+    _ty: PhantomData<fn(E) -> E>,
+}
+
+impl<E: ProtoEnum + fmt::Debug> fmt::Debug for EnumSet<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<E: ProtoEnum> EnumSet<E> {
+    const BYTE_COUNT: usize = {
+        assert!(
+            E::VARIANTS.len() <= 64,
+            "EnumSet only supports enums with at most 64 variants"
+        );
+        E::VARIANTS.len().div_ceil(8)
+    };
+
+    pub fn new() -> Self {
+        Self {
+            bits: 0,
+            _ty: PhantomData,
+        }
+    }
+
+    fn mask(variant: &E) -> u64 {
+        let ordinal = E::VARIANTS
+            .iter()
+            .position(|other| other.to_id() == variant.to_id())
+            .expect("variant is missing from its enum's `VARIANTS` list");
+
+        1 << ordinal
+    }
+
+    pub fn contains(&self, variant: &E) -> bool {
+        self.bits & Self::mask(variant) != 0
+    }
+
+    /// Adds the variant to the set, returning whether it was absent.
+    pub fn insert(&mut self, variant: &E) -> bool {
+        let was_absent = !self.contains(variant);
+        self.bits |= Self::mask(variant);
+        was_absent
+    }
+
+    /// Removes the variant from the set, returning whether it was present.
+    pub fn remove(&mut self, variant: &E) -> bool {
+        let was_present = self.contains(variant);
+        self.bits &= !Self::mask(variant);
+        was_present
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.count_ones() as usize
+    }
+
+    /// Iterates over the variants in the set in declaration order.
+    pub fn iter(&self) -> impl Iterator<Item = &'static E> + '_ {
+        E::VARIANTS
+            .iter()
+            .enumerate()
+            .filter(|&(ordinal, _)| self.bits & (1 << ordinal) != 0)
+            .map(|(_, variant)| variant)
+    }
+}
+
+impl<'a, E: ProtoEnum> FromIterator<&'a E> for EnumSet<E> {
+    fn from_iter<I: IntoIterator<Item = &'a E>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for variant in iter {
+            set.insert(variant);
+        }
+        set
+    }
+}
+
+impl<E: ProtoEnum> Codec<()> for EnumSet<E> {
+    fn decode(_args: (), _src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let Some(bytes) = cursor.read_slice(Self::BYTE_COUNT) else {
+            anyhow::bail!(
+                "Expected {} byte(s) for a set of {}; found {} (location: {}).",
+                Self::BYTE_COUNT,
+                type_name::<E>(),
+                cursor.remaining().len(),
+                cursor.format_location(),
+            );
+        };
+
+        let bits = bytes
+            .iter()
+            .enumerate()
+            .fold(0u64, |bits, (i, &byte)| bits | (byte as u64) << (i * 8));
+
+        // Bits past the last variant are padding and should be unset. An enum with 64 variants has
+        // no padding, and shifting by the full width of the integer would overflow.
+        if bits.checked_shr(E::VARIANTS.len() as u32).unwrap_or(0) != 0 {
+            anyhow::bail!(
+                "Set of {} has bit {} set but the enum only has {} variant(s) (location: {}).",
+                type_name::<E>(),
+                63 - bits.leading_zeros(),
+                E::VARIANTS.len(),
+                cursor.format_location(),
+            );
+        }
+
+        Ok(Self {
+            bits,
+            _ty: PhantomData,
+        })
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        cursor.put_slice(&self.bits.to_le_bytes()[..Self::BYTE_COUNT]);
+    }
+}
+
+impl<E: ProtoEnum> SizedCodec<()> for EnumSet<E> {
+    fn size(&self, _args: ()) -> usize {
+        Self::BYTE_COUNT
+    }
+}
+
+// Byte Array
+#[derive(Debug, Clone)]
+pub struct ByteArray(Bytes);
+
+impl ByteArray {
+    pub fn new(bytes: Bytes) -> Self {
+        Self(bytes)
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.0
+    }
+}
+
+impl Codec<()> for ByteArray {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let len = VarInt::decode((), src, cursor)?.0;
+
+        let Some(data) = cursor.read_slice(len as usize) else {
+			anyhow::bail!(
+				"Expected {len} byte(s) of data for the byte array; found {} (location: {}).",
+				cursor.remaining().len(),
+				cursor.format_location(),
+			);
+		};
+
+        Ok(ByteArray(src.freeze_range(data)))
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        VarUint(u32::try_from(self.0.len()).expect(TOO_BIG_ERR)).encode((), cursor);
+        self.0.encode((), cursor);
+    }
+}
+
+impl SizedCodec<()> for ByteArray {
+    fn size(&self, _args: ()) -> usize {
+        VarUint(u32::try_from(self.0.len()).expect(TOO_BIG_ERR)).size(()) + self.0.len()
+    }
+}
+
+// LengthDelimited
+/// A structure prefixed by its `VarUint` length in bytes. Decoding fails if the inner value does
+/// not consume exactly that many bytes, catching desyncs caused by misunderstood layouts.
+///
+/// For example, a `LengthDelimited<sb_status::Packet>` field would be encoded as the byte length
+/// of the nested packet, followed by its id and body.
+#[derive(Debug, Clone)]
+pub struct LengthDelimited<T>(pub T);
+
+impl<A: Clone, T: SizedCodec<A>> Codec<A> for LengthDelimited<T> {
+    fn decode(args: A, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let len = VarUint::decode((), src, cursor)?.0;
+
+        let Some(mut region) = cursor.sub_reader(len as usize) else {
+			anyhow::bail!(
+				"Expected {len} byte(s) of data for the length-delimited {}; found {} (location: {}).",
+				type_name::<T>(),
+				cursor.remaining().len(),
+				cursor.format_location(),
+			);
+		};
+
+        let value = T::decode(args, src, &mut region)?;
+
+        anyhow::ensure!(
+            region.is_empty(),
+            "Length-delimited {} left {} trailing byte(s) unconsumed (location: {}).",
+            type_name::<T>(),
+            region.len(),
+            region.format_location(),
+        );
+
+        Ok(Self(value))
+    }
+
+    fn encode(&self, args: A, cursor: &mut impl BufMut) {
+        VarUint(u32::try_from(self.0.size(args.clone())).expect(TOO_BIG_ERR)).encode((), cursor);
+        self.0.encode(args, cursor);
+    }
+
+    fn validate(&self, args: A) -> anyhow::Result<()> {
+        self.0.validate(args)
+    }
+}
+
+impl<A: Clone, T: SizedCodec<A>> SizedCodec<A> for LengthDelimited<T> {
+    fn size(&self, args: A) -> usize {
+        let size = self.0.size(args);
+        VarUint(u32::try_from(size).expect(TOO_BIG_ERR)).size(()) + size
+    }
+}
+
+// Vec
+/// Vectors are encoded as `VarUint`-prefixed arrays. This alias exists to make that explicit in
+/// packet definitions.
+pub type PrefixedArray<T> = Vec<T>;
+
+/// The most memory [`decode_elements`] reserves up front. Arrays needing more than this still decode
+/// but grow their buffer as elements actually arrive.
+const MAX_PREALLOC_BYTES: usize = 1 << 20;
+
+/// Decodes exactly `len` elements. The length is checked against the number of bytes remaining
+/// before anything is allocated so that a bogus length prefix can't make us reserve a huge buffer.
+/// This assumes that every element occupies at least one byte, which is true of every array in the
+/// protocol. Element errors are annotated with the element's index.
+fn decode_elements<A, T: Codec<A>>(
+    len: usize,
+    mut args: impl FnMut() -> A,
+    src: &impl Snip,
+    cursor: &mut ByteCursor,
+) -> anyhow::Result<Vec<T>> {
+    anyhow::ensure!(
+        len <= cursor.len(),
+        "Array of {len} element(s) is longer than the remaining {} byte(s) (location: {}).",
+        cursor.len(),
+        cursor.format_location(),
+    );
+
+    // Elements can be much larger in memory than on the wire so a length which passed the check
+    // above could still reserve far more memory than the packet itself occupies.
+    let mut builder = Vec::with_capacity(len.min(MAX_PREALLOC_BYTES / mem::size_of::<T>().max(1)));
+
+    for i in 0..len {
+        let elem = T::decode(args(), src, cursor)
+            .with_context(|| format!("Failed to decode element {i} of {len}-element array."))?;
+
+        builder.push(elem);
+    }
+
+    Ok(builder)
+}
+
+impl<A, F, T> Codec<F> for Vec<T>
+where
+    T: Codec<A>,
+    F: FnMut() -> A,
+{
+    fn decode(args: F, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let len = VarUint::decode((), src, cursor)?.0;
+        decode_elements(len as usize, args, src, cursor)
+    }
+
+    fn encode(&self, mut args: F, cursor: &mut impl BufMut) {
+        VarUint(u32::try_from(self.len()).expect("vector is too large to send over the network"))
+            .encode((), cursor);
+
+        for elem in self {
+            elem.encode(args(), cursor);
+        }
+    }
+
+    fn validate(&self, mut args: F) -> anyhow::Result<()> {
+        for elem in self {
+            elem.validate(args())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<A, F, T> SizedCodec<F> for Vec<T>
+where
+    T: SizedCodec<A>,
+    F: FnMut() -> A,
+{
+    fn size(&self, mut args: F) -> usize {
+        let mut accum = VarUint(
+            u32::try_from(self.len()).expect("vector is too large to send over the network"),
+        )
+        .size(());
+
+        for elem in self {
+            accum += elem.size(args());
+        }
+
+        accum
+    }
+}
+
+// ArrayLen
+/// Codec arguments for a `Vec` whose length is not prefixed but instead known from elsewhere in
+/// the packet. Exactly `len` elements are decoded, each with arguments produced by `args`.
+/// Encoding writes every element in the vector and `validate` checks that there are `len` of them.
+#[derive(Debug, Copy, Clone)]
+pub struct ArrayLen<F>(pub usize, pub F);
+
+impl<A, F, T> Codec<ArrayLen<F>> for Vec<T>
+where
+    T: Codec<A>,
+    F: FnMut() -> A,
+{
+    fn decode(
+        ArrayLen(len, args): ArrayLen<F>,
+        src: &impl Snip,
+        cursor: &mut ByteCursor,
+    ) -> anyhow::Result<Self> {
+        decode_elements(len, args, src, cursor)
+    }
+
+    fn encode(&self, ArrayLen(_, mut args): ArrayLen<F>, cursor: &mut impl BufMut) {
+        for elem in self {
+            elem.encode(args(), cursor);
+        }
+    }
+
+    fn validate(&self, ArrayLen(len, mut args): ArrayLen<F>) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.len() == len,
+            "Array must contain exactly {len} element(s) but contains {}.",
+            self.len(),
+        );
+
+        for elem in self {
+            elem.validate(args())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<A, F, T> SizedCodec<ArrayLen<F>> for Vec<T>
+where
+    T: SizedCodec<A>,
+    F: FnMut() -> A,
+{
+    fn size(&self, ArrayLen(_, mut args): ArrayLen<F>) -> usize {
+        self.iter().map(|elem| elem.size(args())).sum()
+    }
+}
+
+// ArrayMaxLen
+/// Codec arguments for a `VarUint`-prefixed `Vec` which may contain at most `max_len` elements.
+/// This is the runtime counterpart to [`BoundedVec`] for limits which aren't known statically.
+#[derive(Debug, Copy, Clone)]
+pub struct ArrayMaxLen<F>(pub u32, pub F);
+
+impl<A, F, T> Codec<ArrayMaxLen<F>> for Vec<T>
+where
+    T: Codec<A>,
+    F: FnMut() -> A,
+{
+    fn decode(
+        ArrayMaxLen(max_len, args): ArrayMaxLen<F>,
+        src: &impl Snip,
+        cursor: &mut ByteCursor,
+    ) -> anyhow::Result<Self> {
+        let len = VarUint::decode((), src, cursor)?.0;
+
+        anyhow::ensure!(
+            len <= max_len,
+            "Array can contain at most {max_len} element(s) but its length is specified as {len} \
+             (location: {}).",
+            cursor.format_location(),
+        );
+
+        decode_elements(len as usize, args, src, cursor)
+    }
+
+    fn encode(&self, ArrayMaxLen(_, args): ArrayMaxLen<F>, cursor: &mut impl BufMut) {
+        self.encode(args, cursor);
+    }
+
+    fn validate(&self, ArrayMaxLen(max_len, args): ArrayMaxLen<F>) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.len() <= max_len as usize,
+            "Array can contain at most {max_len} element(s) but ended up containing {}.",
+            self.len(),
+        );
+
+        self.validate(args)
+    }
+}
+
+impl<A, F, T> SizedCodec<ArrayMaxLen<F>> for Vec<T>
+where
+    T: SizedCodec<A>,
+    F: FnMut() -> A,
+{
+    fn size(&self, ArrayMaxLen(_, args): ArrayMaxLen<F>) -> usize {
+        self.size(args)
+    }
+}
+
+// BoundedVec
+/// A `VarUint`-prefixed array which may contain at most `MAX_LEN` elements. The length is checked
+/// before anything is allocated so peers can't make us reserve huge buffers for arrays they never
+/// send.
+#[derive(Debug, Clone, Default)]
+pub struct BoundedVec<T, const MAX_LEN: u32>(pub Vec<T>);
+
+impl<A, F, T, const MAX_LEN: u32> Codec<F> for BoundedVec<T, MAX_LEN>
+where
+    T: Codec<A>,
+    F: FnMut() -> A,
+{
+    fn decode(args: F, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let len = VarUint::decode((), src, cursor)?.0;
+
+        anyhow::ensure!(
+            len <= MAX_LEN,
+            "Array can contain at most {MAX_LEN} element(s) but its length is specified as {len} \
+			 (location: {}).",
+            cursor.format_location(),
+        );
+
+        Ok(Self(decode_elements(len as usize, args, src, cursor)?))
+    }
+
+    fn encode(&self, args: F, cursor: &mut impl BufMut) {
+        debug_assert!(
+            self.0.len() <= MAX_LEN as usize,
+            "Array can contain at most {MAX_LEN} element(s) but ended up containing {}.",
+            self.0.len(),
+        );
+
+        self.0.encode(args, cursor);
+    }
+
+    fn validate(&self, args: F) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.0.len() <= MAX_LEN as usize,
+            "Array can contain at most {MAX_LEN} element(s) but ended up containing {}.",
+            self.0.len(),
+        );
+
+        self.0.validate(args)
+    }
+}
+
+impl<A, F, T, const MAX_LEN: u32> SizedCodec<F> for BoundedVec<T, MAX_LEN>
+where
+    T: SizedCodec<A>,
+    F: FnMut() -> A,
+{
+    fn size(&self, args: F) -> usize {
+        self.0.size(args)
+    }
+}
+
+// NullableArray
+/// An array whose `VarInt` length prefix may be negative (conventionally `-1`) to indicate that
+/// the array is absent entirely. This is distinct from an array which is present but empty.
+#[derive(Debug, Clone)]
+pub struct NullableArray<T>(pub Option<Vec<T>>);
+
+impl<A, F, T> Codec<F> for NullableArray<T>
+where
+    T: Codec<A>,
+    F: FnMut() -> A,
+{
+    fn decode(args: F, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let Ok(len) = u32::try_from(VarInt::decode((), src, cursor)?.0) else {
+            return Ok(Self(None));
+        };
+
+        let builder = decode_elements(len as usize, args, src, cursor)?;
+        Ok(Self(Some(builder)))
+    }
+
+    fn encode(&self, args: F, cursor: &mut impl BufMut) {
+        if let Some(inner) = &self.0 {
+            inner.encode(args, cursor);
+        } else {
+            VarInt(-1).encode((), cursor);
+        }
+    }
+
+    fn validate(&self, args: F) -> anyhow::Result<()> {
+        if let Some(inner) = &self.0 {
+            inner.validate(args)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<A, F, T> SizedCodec<F> for NullableArray<T>
+where
+    T: SizedCodec<A>,
+    F: FnMut() -> A,
+{
+    fn size(&self, args: F) -> usize {
+        if let Some(inner) = &self.0 {
+            inner.size(args)
+        } else {
+            VarInt(-1).size(())
+        }
+    }
+}
+
+// EntityMetadata
+/// The list of metadata entries sent in the "Set Entity Metadata" packet, terminated on the wire by
+/// the index `0xFF`.
+///
+/// Value types are not length-prefixed so we cannot skip over a value whose layout we don't know.
+/// By default (i.e. with the `()` argument or [`MetaDecodeMode::Strict`]), entries with an
+/// unrecognized type id therefore fail decoding with that id. This replaces the earlier behavior of
+/// always preserving unknown values: every type in the protocol version we implement is now
+/// modelled, so an unknown id almost always means a malformed packet and silently capturing the
+/// rest of the buffer would hide that.
+///
+/// Proxies straddling protocol versions can still opt into preserving unknown values by decoding
+/// with [`MetaDecodeMode::PreserveUnknown`]. In that mode, the first entry with an unknown type
+/// captures every remaining byte of the buffer (including any entries after it and the terminator)
+/// as [`MetaValue::Unknown`] and decoding stops there. This means:
+///
+/// - Unknown values can only be preserved when the metadata is the last field of its packet, which
+///   is the case for "Set Entity Metadata". Embedding `EntityMetadata` anywhere else would make an
+///   unknown value swallow the fields which follow it.
+/// - An `Unknown` entry must be the last entry of the list. Entries after it are only available as
+///   raw bytes. `validate` rejects lists where this isn't the case.
+///
+/// Encoding, sizing, and validation behave the same in either mode.
+#[derive(Debug, Clone, Default)]
+pub struct EntityMetadata(pub Vec<MetaEntry>);
+
+/// How [`EntityMetadata`] treats entries whose type id it doesn't recognize when decoding.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub enum MetaDecodeMode {
+    /// Fail decoding with the unknown type id.
+    #[default]
+    Strict,
+
+    /// Keep the unknown entry and the rest of the buffer as a trailing [`MetaValue::Unknown`].
+    PreserveUnknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetaEntry {
+    pub index: u8,
+    pub value: MetaValue,
+}
+
+const META_TERMINATOR: u8 = 0xFF;
+
+impl Codec<MetaDecodeMode> for EntityMetadata {
+    fn decode(
+        mode: MetaDecodeMode,
+        src: &impl Snip,
+        cursor: &mut ByteCursor,
+    ) -> anyhow::Result<Self> {
+        let mut entries = Vec::new();
+
+        loop {
+            let index = u8::decode((), src, cursor)?;
+            if index == META_TERMINATOR {
+                break;
+            }
+
+            let type_id = VarInt::decode((), src, cursor)?;
+            let value = MetaValue::decode_body(type_id, mode, src, cursor)?;
+            let is_unknown = matches!(value, MetaValue::Unknown { .. });
+
+            entries.push(MetaEntry { index, value });
+
+            if is_unknown {
+                break;
+            }
+        }
+
+        Ok(Self(entries))
+    }
+
+    fn encode(&self, _mode: MetaDecodeMode, cursor: &mut impl BufMut) {
+        for entry in &self.0 {
+            entry.index.encode((), cursor);
+            entry.value.type_id().encode((), cursor);
+            entry.value.encode_body(cursor);
+        }
+
+        // Unknown values already contain the terminator in their raw bytes.
+        if !self.ends_with_unknown() {
+            META_TERMINATOR.encode((), cursor);
+        }
+    }
+
+    fn validate(&self, _mode: MetaDecodeMode) -> anyhow::Result<()> {
+        for (i, entry) in self.0.iter().enumerate() {
+            anyhow::ensure!(
+                entry.index != META_TERMINATOR,
+                "Metadata entry {i} uses the reserved index {META_TERMINATOR}."
+            );
+
+            if let MetaValue::Unknown { type_id, .. } = &entry.value {
+                anyhow::ensure!(
+                    i == self.0.len() - 1,
+                    "Metadata entry {i} has unknown type {} but is not the last entry.",
+                    type_id.0,
+                );
+            }
+
+            entry.value.validate_body()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SizedCodec<MetaDecodeMode> for EntityMetadata {
+    fn size(&self, _mode: MetaDecodeMode) -> usize {
+        let mut accum = 0;
+
+        for entry in &self.0 {
+            accum +=
+                entry.index.size(()) + entry.value.type_id().size(()) + entry.value.size_body();
+        }
+
+        if !self.ends_with_unknown() {
+            accum += META_TERMINATOR.size(());
+        }
+
+        accum
+    }
+}
+
+impl Codec<()> for EntityMetadata {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Self::decode(MetaDecodeMode::Strict, src, cursor)
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.encode(MetaDecodeMode::Strict, cursor)
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        self.validate(MetaDecodeMode::Strict)
+    }
+}
+
+impl SizedCodec<()> for EntityMetadata {
+    fn size(&self, _args: ()) -> usize {
+        self.size(MetaDecodeMode::Strict)
+    }
+}
+
+impl EntityMetadata {
+    fn ends_with_unknown(&self) -> bool {
+        matches!(
+            self.0.last(),
+            Some(MetaEntry {
+                value: MetaValue::Unknown { .. },
+                ..
+            })
+        )
+    }
+}
+
+codec_struct! {
+    #[derive(Debug, Copy, Clone)]
+    pub struct MetaVector3 {
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+    }
+
+    #[derive(Debug, Copy, Clone)]
+    pub struct MetaQuaternion {
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        pub w: f32,
+    }
+
+    #[derive(Debug, Copy, Clone)]
+    pub struct MetaVillagerData {
+        pub kind: VarInt,
+        pub profession: VarInt,
+        pub level: VarInt,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct MetaGlobalPos {
+        pub dimension: Identifier,
+        pub position: Position,
+    }
+}
+
+// Particle
+/// A particle type and the data which that type requires, as sent in entity metadata.
+///
+/// Adapted from: https://wiki.vg/index.php?title=Particles&oldid=18375
+#[derive(Debug, Clone)]
+pub enum Particle {
+    /// A particle type which takes no data.
+    Simple(VarInt),
+    /// Any of `block`, `block_marker`, or `falling_dust`, which share a layout.
+    Block {
+        id: VarInt,
+        block_state: VarInt,
+    },
+    Dust {
+        color: MetaVector3,
+        scale: f32,
+    },
+    DustColorTransition {
+        from: MetaVector3,
+        scale: f32,
+        to: MetaVector3,
+    },
+    SculkCharge {
+        roll: f32,
+    },
+    Item(Option<LegacyItemStack>),
+    Vibration {
+        source: VibrationSource,
+        ticks: VarInt,
+    },
+    Shriek {
+        delay: VarInt,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum VibrationSource {
+    Block(Position),
+    Entity { id: VarInt, eye_height: f32 },
+}
+
+impl Particle {
+    pub const BLOCK: i32 = 2;
+    pub const BLOCK_MARKER: i32 = 3;
+    pub const DUST: i32 = 14;
+    pub const DUST_COLOR_TRANSITION: i32 = 15;
+    pub const FALLING_DUST: i32 = 25;
+    pub const SCULK_CHARGE: i32 = 31;
+    pub const ITEM: i32 = 40;
+    pub const VIBRATION: i32 = 41;
+    pub const SHRIEK: i32 = 93;
+
+    pub fn id(&self) -> VarInt {
+        VarInt(match self {
+            Self::Simple(id) | Self::Block { id, .. } => id.0,
+            Self::Dust { .. } => Self::DUST,
+            Self::DustColorTransition { .. } => Self::DUST_COLOR_TRANSITION,
+            Self::SculkCharge { .. } => Self::SCULK_CHARGE,
+            Self::Item(_) => Self::ITEM,
+            Self::Vibration { .. } => Self::VIBRATION,
+            Self::Shriek { .. } => Self::SHRIEK,
+        })
+    }
+
+    fn is_block(id: i32) -> bool {
+        matches!(id, Self::BLOCK | Self::BLOCK_MARKER | Self::FALLING_DUST)
+    }
+
+    fn has_data(id: i32) -> bool {
+        Self::is_block(id)
+            || matches!(
+                id,
+                Self::DUST
+                    | Self::DUST_COLOR_TRANSITION
+                    | Self::SCULK_CHARGE
+                    | Self::ITEM
+                    | Self::VIBRATION
+                    | Self::SHRIEK
+            )
+    }
+}
+
+const VIBRATION_SOURCE_BLOCK: &str = "minecraft:block";
+const VIBRATION_SOURCE_ENTITY: &str = "minecraft:entity";
+
+impl VibrationSource {
+    fn source_type(&self) -> Identifier {
+        Identifier(NetString::from_static_str(match self {
+            Self::Block(_) => VIBRATION_SOURCE_BLOCK,
+            Self::Entity { .. } => VIBRATION_SOURCE_ENTITY,
+        }))
+    }
+}
+
+impl Codec<()> for Particle {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let id = VarInt::decode((), src, cursor)?;
+
+        Ok(match id.0 {
+            id if Self::is_block(id) => Self::Block {
+                id: VarInt(id),
+                block_state: VarInt::decode((), src, cursor)?,
+            },
+            Self::DUST => Self::Dust {
+                color: MetaVector3::decode((), src, cursor)?,
+                scale: f32::decode((), src, cursor)?,
+            },
+            Self::DUST_COLOR_TRANSITION => Self::DustColorTransition {
+                from: MetaVector3::decode((), src, cursor)?,
+                scale: f32::decode((), src, cursor)?,
+                to: MetaVector3::decode((), src, cursor)?,
+            },
+            Self::SCULK_CHARGE => Self::SculkCharge {
+                roll: f32::decode((), src, cursor)?,
+            },
+            Self::ITEM => Self::Item(Codec::decode((), src, cursor)?),
+            Self::VIBRATION => {
+                let source_type = Identifier::decode((), src, cursor)?;
+                let source = match &*source_type.0 {
+                    VIBRATION_SOURCE_BLOCK => {
+                        VibrationSource::Block(Position::decode((), src, cursor)?)
+                    }
+                    VIBRATION_SOURCE_ENTITY => VibrationSource::Entity {
+                        id: VarInt::decode((), src, cursor)?,
+                        eye_height: f32::decode((), src, cursor)?,
+                    },
+                    other => anyhow::bail!(
+                        "Unknown vibration position source type {other:?} (location: {}).",
+                        cursor.format_location(),
+                    ),
+                };
+
+                Self::Vibration {
+                    source,
+                    ticks: VarInt::decode((), src, cursor)?,
+                }
+            }
+            Self::SHRIEK => Self::Shriek {
+                delay: VarInt::decode((), src, cursor)?,
+            },
+            _ => Self::Simple(id),
+        })
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.id().encode((), cursor);
+
+        match self {
+            Self::Simple(_) => {}
+            Self::Block { block_state, .. } => block_state.encode((), cursor),
+            Self::Dust { color, scale } => {
+                color.encode((), cursor);
+                scale.encode((), cursor);
+            }
+            Self::DustColorTransition { from, scale, to } => {
+                from.encode((), cursor);
+                scale.encode((), cursor);
+                to.encode((), cursor);
+            }
+            Self::SculkCharge { roll } => roll.encode((), cursor),
+            Self::Item(stack) => stack.encode((), cursor),
+            Self::Vibration { source, ticks } => {
+                source.source_type().encode((), cursor);
+                match source {
+                    VibrationSource::Block(pos) => pos.encode((), cursor),
+                    VibrationSource::Entity { id, eye_height } => {
+                        id.encode((), cursor);
+                        eye_height.encode((), cursor);
+                    }
+                }
+                ticks.encode((), cursor);
+            }
+            Self::Shriek { delay } => delay.encode((), cursor),
+        }
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        match self {
+            Self::Simple(id) => anyhow::ensure!(
+                !Self::has_data(id.0),
+                "Particle type {} requires data but was sent without it.",
+                id.0,
+            ),
+            Self::Block { id, .. } => anyhow::ensure!(
+                Self::is_block(id.0),
+                "Particle type {} does not take a block state.",
+                id.0,
+            ),
+            Self::Item(stack) => stack.validate(())?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl SizedCodec<()> for Particle {
+    fn size(&self, _args: ()) -> usize {
+        self.id().size(())
+            + match self {
+                Self::Simple(_) => 0,
+                Self::Block { block_state, .. } => block_state.size(()),
+                Self::Dust { color, scale } => color.size(()) + scale.size(()),
+                Self::DustColorTransition { from, scale, to } => {
+                    from.size(()) + scale.size(()) + to.size(())
+                }
+                Self::SculkCharge { roll } => roll.size(()),
+                Self::Item(stack) => stack.size(()),
+                Self::Vibration { source, ticks } => {
+                    let data = match source {
+                        VibrationSource::Block(pos) => pos.size(()),
+                        VibrationSource::Entity { id, eye_height } => {
+                            id.size(()) + eye_height.size(())
+                        }
+                    };
+
+                    source.source_type().size(()) + data + ticks.size(())
+                }
+                Self::Shriek { delay } => delay.size(()),
+            }
+    }
+}
+
+macro_rules! meta_values {
+    ($($id:literal => $name:ident($ty:ty $(=> $config:expr)?)),*$(,)?) => {
+		#[derive(Debug, Clone)]
+		pub enum MetaValue {
+			$($name($ty),)*
+			/// A value of a type we don't know how to decode, only produced when decoding with
+			/// [`MetaDecodeMode::PreserveUnknown`]. `raw` contains every byte following the type id
+			/// until the end of the buffer. See [`EntityMetadata`] for details.
+			Unknown { type_id: VarInt, raw: Bytes },
+		}
+
+		impl MetaValue {
+			pub fn type_id(&self) -> VarInt {
+				match self {
+					$(Self::$name(_) => VarInt($id),)*
+					Self::Unknown { type_id, .. } => *type_id,
+				}
+			}
+
+			fn decode_body(
+				type_id: VarInt,
+				mode: MetaDecodeMode,
+				src: &impl Snip,
+				cursor: &mut ByteCursor,
+			) -> anyhow::Result<Self> {
+				match type_id.0 {
+					$($id => Ok(Self::$name(Codec::decode({ $($config)? }, src, cursor)?)),)*
+					_ if mode == MetaDecodeMode::PreserveUnknown => {
+						Ok(Self::Unknown { type_id, raw: Bytes::decode((), src, cursor)? })
+					}
+					_ => anyhow::bail!(
+						"Unknown entity metadata type id {} (location: {}).",
+						type_id.0,
+						cursor.format_location(),
+					),
+				}
+			}
+
+			fn encode_body(&self, cursor: &mut impl BufMut) {
+				match self {
+					$(Self::$name(value) => value.encode({ $($config)? }, cursor),)*
+					Self::Unknown { raw, .. } => raw.encode((), cursor),
+				}
+			}
+
+			fn validate_body(&self) -> anyhow::Result<()> {
+				match self {
+					$(Self::$name(value) => value.validate({ $($config)? }),)*
+					Self::Unknown { .. } => Ok(()),
+				}
+			}
+
+			fn size_body(&self) -> usize {
+				match self {
+					$(Self::$name(value) => value.size({ $($config)? }),)*
+					Self::Unknown { raw, .. } => raw.len(),
+				}
+			}
+		}
+	};
+}
+
+// Adapted from: https://wiki.vg/index.php?title=Entity_metadata&oldid=18319#Entity_Metadata_Format
+meta_values! {
+    0 => Byte(i8),
+    1 => VarInt(VarInt),
+    2 => VarLong(VarLong),
+    3 => Float(f32),
+    4 => String(NetString => 32767),
+    5 => Chat(Chat),
+    6 => OptChat(Option<Chat>),
+    7 => Slot(Option<LegacyItemStack>),
+    8 => Boolean(bool),
+    9 => Rotation(MetaVector3),
+    10 => Position(Position),
+    11 => OptPosition(Option<Position>),
+    12 => Direction(VarInt),
+    13 => OptUuid(Option<Uuid>),
+    14 => BlockId(VarInt),
+    15 => OptBlockId(VarInt),
+    16 => Nbt(RawNbt),
+    17 => Particle(Particle),
+    18 => VillagerData(MetaVillagerData),
+    19 => OptVarInt(VarInt),
+    20 => Pose(VarInt),
+    21 => CatVariant(VarInt),
+    22 => FrogVariant(VarInt),
+    23 => OptGlobalPos(Option<MetaGlobalPos>),
+    24 => PaintingVariant(VarInt),
+    25 => SnifferState(VarInt),
+    26 => Vector3(MetaVector3),
+    27 => Quaternion(MetaQuaternion),
+}
+
+// RawNbt
+/// An NBT tag (conventionally a compound) kept in its encoded form. Decoding walks the tag's
+/// structure to find where it ends but doesn't otherwise interpret it. This uses the pre-1.20.2
+/// network format where the root tag is named.
+#[derive(Debug, Clone)]
+pub struct RawNbt(Bytes);
+
+impl RawNbt {
+    pub fn from_bytes_unchecked(bytes: Bytes) -> Self {
+        Self(bytes)
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    fn skip_named(src: &impl Snip, cursor: &mut ByteCursor, depth: u32) -> anyhow::Result<u8> {
+        let tag = u8::decode((), src, cursor)?;
+        if tag != 0 {
+            Self::skip_string(src, cursor)?;
+            Self::skip_payload(tag, src, cursor, depth)?;
+        }
+
+        Ok(tag)
+    }
+
+    fn skip_string(src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<()> {
+        let len = u16::decode((), src, cursor)?;
+        Self::skip_bytes(len as usize, cursor)
+    }
+
+    fn skip_array(
+        elem_size: usize,
+        src: &impl Snip,
+        cursor: &mut ByteCursor,
+    ) -> anyhow::Result<()> {
+        let len = i32::decode((), src, cursor)?;
+        let Some(size) = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_mul(elem_size))
+        else {
+            anyhow::bail!(
+                "NBT array has an invalid length of {len} (location: {}).",
+                cursor.format_location(),
+            );
+        };
+
+        Self::skip_bytes(size, cursor)
+    }
+
+    fn skip_bytes(count: usize, cursor: &mut ByteCursor) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            cursor.read_slice(count).is_some(),
+            "Expected {count} byte(s) of NBT data; found {} (location: {}).",
+            cursor.remaining().len(),
+            cursor.format_location(),
+        );
+
+        Ok(())
+    }
+
+    fn skip_payload(
+        tag: u8,
+        src: &impl Snip,
+        cursor: &mut ByteCursor,
+        depth: u32,
+    ) -> anyhow::Result<()> {
+        match tag {
+            1 => Self::skip_bytes(1, cursor),
+            2 => Self::skip_bytes(2, cursor),
+            3 | 5 => Self::skip_bytes(4, cursor),
+            4 | 6 => Self::skip_bytes(8, cursor),
+            7 => Self::skip_array(1, src, cursor),
+            8 => Self::skip_string(src, cursor),
+            9 => {
+                let depth = Self::descend(depth, cursor)?;
+                let elem_tag = u8::decode((), src, cursor)?;
+                let len = i32::decode((), src, cursor)?;
+
+                for _ in 0..len.max(0) {
+                    Self::skip_payload(elem_tag, src, cursor, depth)?;
+                }
+
+                Ok(())
+            }
+            10 => {
+                let depth = Self::descend(depth, cursor)?;
+                while Self::skip_named(src, cursor, depth)? != 0 {}
+
+                Ok(())
+            }
+            11 => Self::skip_array(4, src, cursor),
+            12 => Self::skip_array(8, src, cursor),
+            _ => anyhow::bail!(
+                "Unknown NBT tag type {tag} (location: {}).",
+                cursor.format_location(),
+            ),
+        }
+    }
+
+    fn descend(depth: u32, cursor: &ByteCursor) -> anyhow::Result<u32> {
+        anyhow::ensure!(
+            depth < DEFAULT_MAX_NBT_DEPTH,
+            "NBT is nested more than {DEFAULT_MAX_NBT_DEPTH} level(s) deep (location: {}).",
+            cursor.format_location(),
+        );
+
+        Ok(depth + 1)
+    }
+}
+
+impl Codec<()> for RawNbt {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let start = cursor.remaining();
+        Self::skip_named(src, cursor, 0)?;
+        let data = &start[..start.len() - cursor.remaining().len()];
+
+        Ok(Self(src.freeze_range(data)))
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.0.encode((), cursor);
+    }
+}
+
+impl SizedCodec<()> for RawNbt {
+    fn size(&self, _args: ()) -> usize {
+        self.0.len()
+    }
+}
+
+codec_struct! {
+    /// A non-empty inventory slot in the format used before 1.20.5, which is still what 1.20.1
+    /// sends in entity metadata. Slots in this format are sent as an `Option<LegacyItemStack>`.
+    #[derive(Debug, Clone)]
+    pub struct LegacyItemStack {
+        pub item_id: RegistryId<ItemKind>,
+        pub count: i8,
+        // The item's NBT data, which is a lone `TAG_End` byte if it has none.
+        pub nbt: RawNbt,
+    }
+}
+
+// Slot
+/// An inventory slot in the component-based format introduced in 1.20.5: a `VarInt` item count
+/// followed, for non-empty slots, by the item id and the components added to and removed from the
+/// item's defaults. As in vanilla, a count of zero or less decodes as an empty slot.
+#[derive(Debug, Clone, Default)]
+pub struct Slot(pub Option<ItemStack>);
+
+#[derive(Debug, Clone)]
+pub struct ItemStack {
+    /// The number of items in the stack. This must be positive since an empty stack is encoded as
+    /// an empty slot.
+    pub count: i32,
+    pub item_id: RegistryId<ItemKind>,
+    /// The added components. Both component lists' lengths precede both lists on the wire.
+    pub added_components: Vec<ItemComponent>,
+    /// The type ids of the default components the item no longer has.
+    pub removed_components: Vec<RegistryId<ItemComponentKind>>,
+}
+
+codec_struct! {
+    /// A component added to an item stack.
+    ///
+    /// Each component type has its own data layout which we don't model yet. For now, the data is
+    /// assumed to be a single NBT tag so slots with components of other types will fail to decode.
+    #[derive(Debug, Clone)]
+    pub struct ItemComponent {
+        pub type_id: RegistryId<ItemComponentKind>,
+        pub data: RawNbt,
+    }
+}
+
+impl Slot {
+    pub fn empty() -> Self {
+        Self(None)
+    }
+
+    /// Creates a slot holding `count` items of type `item_id` with their default components.
+    pub fn new(item_id: RegistryId<ItemKind>, count: i32) -> Self {
+        Self(Some(ItemStack {
+            count,
+            item_id,
+            added_components: Vec::new(),
+            removed_components: Vec::new(),
+        }))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub fn stack(&self) -> Option<&ItemStack> {
+        self.0.as_ref()
+    }
+}
+
+impl Codec<()> for Slot {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        let count = VarInt::decode((), src, cursor)?.0;
+        if count <= 0 {
+            return Ok(Self::empty());
+        }
+
+        let item_id = RegistryId::decode((), src, cursor)?;
+        let added_count = VarUint::decode((), src, cursor)?.0 as usize;
+        let removed_count = VarUint::decode((), src, cursor)?.0 as usize;
+        let added_components = Vec::decode(ArrayLen(added_count, || ()), src, cursor)?;
+        let removed_components = Vec::decode(ArrayLen(removed_count, || ()), src, cursor)?;
+
+        Ok(Self(Some(ItemStack {
+            count,
+            item_id,
+            added_components,
+            removed_components,
+        })))
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        let Some(stack) = &self.0 else {
+            VarInt(0).encode((), cursor);
+            return;
+        };
+
+        VarInt(stack.count).encode((), cursor);
+        stack.item_id.encode((), cursor);
+        VarUint(u32::try_from(stack.added_components.len()).expect(TOO_BIG_ERR)).encode((), cursor);
+        VarUint(u32::try_from(stack.removed_components.len()).expect(TOO_BIG_ERR))
+            .encode((), cursor);
+
+        for component in &stack.added_components {
+            component.encode((), cursor);
+        }
+
+        for component in &stack.removed_components {
+            component.encode((), cursor);
+        }
+    }
+
+    fn validate(&self, _args: ()) -> anyhow::Result<()> {
+        let Some(stack) = &self.0 else { return Ok(()) };
+
+        anyhow::ensure!(
+            stack.count > 0,
+            "Item stack must have a positive count but has a count of {}.",
+            stack.count,
+        );
+
+        for component in &stack.added_components {
+            component.validate(())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SizedCodec<()> for Slot {
+    fn size(&self, _args: ()) -> usize {
+        let Some(stack) = &self.0 else {
+            return VarInt(0).size(());
+        };
+
+        VarInt(stack.count).size(())
+            + stack.item_id.size(())
+            + VarUint(u32::try_from(stack.added_components.len()).expect(TOO_BIG_ERR)).size(())
+            + VarUint(u32::try_from(stack.removed_components.len()).expect(TOO_BIG_ERR)).size(())
+            + stack
+                .added_components
+                .iter()
+                .map(|component| component.size(()))
+                .sum::<usize>()
+            + stack
+                .removed_components
+                .iter()
+                .map(|component| component.size(()))
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use super::*;
+
+    fn encode<A>(value: &impl Codec<A>, args: A) -> Bytes {
+        let mut buf = Vec::new();
+        value.encode(args, &mut buf);
+        Bytes::from(buf)
+    }
+
+    #[test]
+    fn nullable_array_distinguishes_empty_from_absent() {
+        let empty = NullableArray::<u8>::decode_bytes(|| (), &Bytes::from_static(b"\x00")).unwrap();
+        assert_eq!(empty.0, Some(vec![]));
+
+        let absent =
+            NullableArray::<u8>::decode_bytes(|| (), &Bytes::from_static(b"\xff\xff\xff\xff\x0f"))
+                .unwrap();
+        assert_eq!(absent.0, None);
+
+        assert_eq!(
+            &encode(&NullableArray::<u8>(Some(vec![])), || ())[..],
+            b"\x00"
+        );
+        assert_eq!(
+            &encode(&NullableArray::<u8>(None), || ())[..],
+            b"\xff\xff\xff\xff\x0f"
+        );
+        assert_eq!(
+            &encode(&NullableArray(Some(vec![1u8, 2])), || ())[..],
+            b"\x02\x01\x02"
+        );
+    }
+
+    #[test]
+    fn json_value_validates_its_serialized_length() {
+        let chat = |len| {
+            JsonValue(RootChatComponent::from(ChatComponent::text(
+                "a".repeat(len),
+            )))
+        };
+
+        // `{"text":"..."}` adds 11 characters of overhead.
+        assert!(chat(262_144 - 11).validate(()).is_ok());
+
+        let err = chat(262_144 - 10).validate(()).unwrap_err();
+        assert!(err.to_string().contains("at most 262144"), "{err:#}");
+    }
+
+    #[test]
+    fn net_string_limits_count_utf16_code_units() {
+        // Each emoji is a single `char` but two UTF-16 code units.
+        let fits = NetString::from_string("😀".repeat(8));
+        let too_long = NetString::from_string("😀".repeat(9));
+        assert_eq!(too_long.utf16_len(), 18);
+
+        assert!(fits.validate(16).is_ok());
+        assert!(too_long.validate(16).is_err());
+
+        let decoded = NetString::decode_bytes(16, &encode(&fits, None::<u32>)).unwrap();
+        assert_eq!(&*decoded, &*fits);
+        assert!(NetString::decode_bytes(16, &encode(&too_long, None::<u32>)).is_err());
+    }
+
+    #[test]
+    fn empty_net_string_differs_from_absent_option() {
+        let empty = || NetString::from_string(String::new());
+
+        assert_eq!(&encode(&empty(), None::<u32>)[..], b"\x00");
+        assert_eq!(&encode(&None::<NetString>, None::<u32>)[..], b"\x00");
+        assert_eq!(&encode(&Some(empty()), None::<u32>)[..], b"\x01\x00");
+
+        let decoded = Option::<NetString>::decode_bytes(None, &Bytes::from_static(b"\x01\x00"));
+        assert_eq!(decoded.unwrap().as_deref(), Some(""));
+
+        let decoded = Option::<NetString>::decode_bytes(None, &Bytes::from_static(b"\x00"));
+        assert!(decoded.unwrap().is_none());
+    }
+
+    #[test]
+    fn entity_metadata_decodes_1_20_1_value_types() {
+        #[rustfmt::skip]
+        let bytes = Bytes::from_static(&[
+            // Index 0: VarLong 300
+            0x00, 2, 0xAC, 0x02,
+            // Index 1: a 1.20.1 slot holding 64 of item 1 without NBT
+            0x01, 7, 0x01, 0x01, 0x40, 0x00,
+            // Index 2: a red dust particle of scale 1
+            0x02, 17, 14, 0x3F, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x3F, 0x80, 0, 0,
+            // Index 3: an absent global position
+            0x03, 23, 0x00,
+            // Index 4: an empty NBT tag
+            0x04, 16, 0x00,
+            0xFF,
+        ]);
+
+        let metadata = EntityMetadata::decode_exact((), &bytes).unwrap();
+        let values = metadata
+            .0
+            .iter()
+            .map(|entry| &entry.value)
+            .collect::<Vec<_>>();
+
+        assert!(matches!(values[0], MetaValue::VarLong(VarLong(300))));
+        assert!(matches!(
+            values[1],
+            MetaValue::Slot(Some(stack)) if stack.item_id.into_raw() == 1 && stack.count == 64,
+        ));
+        assert!(matches!(
+            values[2],
+            MetaValue::Particle(Particle::Dust { color, scale: 1.0 }) if color.x == 1.0,
+        ));
+        assert!(matches!(values[3], MetaValue::OptGlobalPos(None)));
+        assert!(matches!(values[4], MetaValue::Nbt(nbt) if &nbt.bytes()[..] == b"\x00"));
+
+        assert_eq!(encode(&metadata, ()), bytes);
+        assert_eq!(metadata.size(()), bytes.len());
+    }
+
+    #[test]
+    fn entity_metadata_requires_a_terminator_and_known_types() {
+        // Two entries terminated correctly.
+        let bytes = Bytes::from_static(&[0x00, 0, 0x05, 0x01, 8, 0x01, 0xFF]);
+        let metadata = EntityMetadata::decode_exact((), &bytes).unwrap();
+        assert_eq!(metadata.0.len(), 2);
+        assert!(matches!(metadata.0[0].value, MetaValue::Byte(5)));
+        assert!(matches!(metadata.0[1].value, MetaValue::Boolean(true)));
+
+        // The same entries without their terminator.
+        assert!(EntityMetadata::decode_exact((), &bytes.slice(..bytes.len() - 1)).is_err());
+
+        let err =
+            EntityMetadata::decode_exact((), &Bytes::from_static(&[0x00, 99, 0xFF])).unwrap_err();
+        assert!(err.to_string().contains("type id 99"), "{err:#}");
+    }
+
+    #[test]
+    fn position_sign_extends_boundary_coordinates() {
+        let positions = [
+            Position::new(Position::MIN_XZ, Position::MIN_Y, Position::MIN_XZ),
+            Position::new(Position::MAX_XZ, Position::MAX_Y, Position::MAX_XZ),
+            Position::new(-33_554_432, -1, 33_554_431),
+            Position::new(-1, -2048, -1),
+        ];
+
+        for pos in positions {
+            assert!(pos.validate(()).is_ok());
+            assert_eq!(Position::decode_bytes((), &encode(&pos, ())).unwrap(), pos);
+        }
+
+        // The example from wiki.vg.
+        let packed = 0b01000110_00000111_01100011_00101100_00010101_10110100_10000011_00111111;
+        assert_eq!(
+            Position::from_packed(packed),
+            Position::new(18_357_644, 831, -20_882_616),
+        );
+
+        let too_far = Position::new(Position::MAX_XZ + 1, 0, 0);
+        assert!(too_far.validate(()).is_err());
+
+        let too_low = Position::new(0, Position::MIN_Y - 1, 0);
+        assert!(too_low.validate(()).is_err());
+    }
+
+    #[test]
+    fn angle_converts_to_and_from_degrees() {
+        for (degrees, step) in [(0.0, 0), (90.0, 64), (180.0, 128), (270.0, 192), (360.0, 0)] {
+            let angle = Angle::from_degrees(degrees);
+            assert_eq!(angle, Angle(step));
+            assert_eq!(Angle::decode_bytes((), &encode(&angle, ())).unwrap(), angle);
+        }
+
+        assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+        assert_eq!(Angle::from_degrees(359.9), Angle(0));
+
+        let step = 360.0 / 256.0;
+        for i in -2000..2000 {
+            let degrees = i as f32 * 0.37;
+            let back = Angle::from_degrees(degrees).to_degrees();
+            let diff = (back - degrees.rem_euclid(360.0)).abs();
+            assert!(diff.min(360.0 - diff) <= step, "{degrees} -> {back}");
+        }
+    }
+
+    #[test]
+    fn uuid_round_trips_as_bytes_and_hyphenated_string() {
+        let str = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+        let uuid = Uuid::from_u128(0x069a79f4_44e9_4726_a5be_fca90e38aaf5);
+
+        assert_eq!(Uuid::from_hyphenated_str(str).unwrap(), uuid);
+        assert_eq!(uuid.to_string(), str);
+
+        let bytes = encode(&uuid, ());
+        assert_eq!(
+            &bytes[..],
+            b"\x06\x9a\x79\xf4\x44\xe9\x47\x26\xa5\xbe\xfc\xa9\x0e\x38\xaa\xf5"
+        );
+        assert_eq!(
+            Uuid::decode_exact((), &bytes).unwrap().as_u128(),
+            uuid.as_u128()
+        );
+
+        for malformed in [
+            "",
+            "069a79f444e94726a5befca90e38aaf5",
+            "069a79f4-44e9-4726-a5be",
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5-00",
+            "069a79f4-44e9-4726-a5be-fca90e38aaf",
+            "069a79g4-44e9-4726-a5be-fca90e38aaf5",
+            "+69a79f4-44e9-4726-a5be-fca90e38aaf5",
+        ] {
+            assert!(
+                Uuid::from_hyphenated_str(malformed).is_err(),
+                "{malformed:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn bit_set_trims_trailing_zero_words() {
+        let mut set = BitSet::new();
+        set.set(1, true);
+        set.set(64, true);
+        set.set(200, true);
+        set.set(200, false);
+
+        assert!(set.get(1) && set.get(64) && !set.get(2) && !set.get(200));
+        assert_eq!(set.len_bits(), 128);
+        assert_eq!(set.iter_ones().collect::<Vec<_>>(), [1, 64]);
+
+        let bytes = encode(&set, ());
+        assert_eq!(&bytes[..], b"\x02\0\0\0\0\0\0\0\x02\0\0\0\0\0\0\0\x01");
+        assert_eq!(set.size(()), bytes.len());
+
+        // Trailing zero words are accepted and dropped.
+        let padded =
+            Bytes::from_static(b"\x03\0\0\0\0\0\0\0\x02\0\0\0\0\0\0\0\x01\0\0\0\0\0\0\0\0");
+        assert_eq!(BitSet::decode_exact((), &padded).unwrap(), set);
+
+        assert!(BitSet::decode_exact((), &Bytes::from_static(b"\x02\0\0\0\0")).is_err());
+    }
+
+    #[test]
+    fn fixed_bit_set_has_no_length_prefix() {
+        let mut set = FixedBitSet::<20>::new();
+        set.set(0, true);
+        set.set(19, true);
+
+        let bytes = encode(&set, ());
+        assert_eq!(&bytes[..], b"\0\0\0\0\0\x08\0\x01");
+        assert_eq!(set.size(()), 8);
+        assert_eq!(FixedBitSet::<20>::decode_exact((), &bytes).unwrap(), set);
+        assert_eq!(set.iter_ones().collect::<Vec<_>>(), [0, 19]);
+
+        assert_eq!(FixedBitSet::<64>::new().size(()), 8);
+        assert_eq!(FixedBitSet::<65>::new().size(()), 16);
+
+        // Bit 20 is padding for a set of 20 bits.
+        let padding = Bytes::from_static(b"\0\0\0\0\0\x10\0\0");
+        assert!(FixedBitSet::<20>::decode_exact((), &padding).is_err());
+    }
+
+    #[test]
+    fn arrays_reject_lengths_past_the_remaining_bytes() {
+        let empty = PrefixedArray::<u8>::decode_exact(|| (), &Bytes::from_static(b"\x00")).unwrap();
+        assert!(empty.is_empty());
+
+        let empty = Vec::<u8>::decode_exact(ArrayLen(0, || ()), &Bytes::new()).unwrap();
+        assert!(empty.is_empty());
+        assert!(encode(&empty, ArrayLen(0, || ())).is_empty());
+
+        let three =
+            Vec::<u8>::decode_exact(ArrayLen(3, || ()), &Bytes::from_static(b"abc")).unwrap();
+        assert_eq!(three, b"abc");
+
+        // A length prefix of `i32::MAX` followed by two bytes.
+        let absurd = Bytes::from_static(b"\xff\xff\xff\xff\x07\x01\x02");
+        let err = PrefixedArray::<u8>::decode_exact(|| (), &absurd).unwrap_err();
+        assert!(err.to_string().contains("remaining 2 byte(s)"), "{err:#}");
+
+        let err =
+            Vec::<u8>::decode_exact(ArrayLen(4, || ()), &Bytes::from_static(b"abc")).unwrap_err();
+        assert!(err.to_string().contains("remaining 3 byte(s)"), "{err:#}");
+    }
+
+    #[test]
+    fn net_string_stops_validating_once_over_the_limit() {
+        fn net_bytes(data: &[u8]) -> Bytes {
+            let mut buf = Vec::new();
+            VarUint(data.len() as u32).encode((), &mut buf);
+            buf.extend_from_slice(data);
+            Bytes::from(buf)
+        }
+
+        // Multi-byte characters straddle the internal window boundaries.
+        let valid = "aé😀".repeat(8_000);
+        let decoded = NetString::decode_exact(Some(32767), &net_bytes(valid.as_bytes())).unwrap();
+        assert_eq!(&*decoded, valid);
+
+        // The invalid byte at the very end is never reached because the length limit is exceeded
+        // long before it.
+        let mut long = vec![b'a'; 90_000];
+        long.push(0xFF);
+        let err = NetString::decode_exact(Some(32767), &net_bytes(&long)).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("String is too long"), "{err:#}");
+
+        let at_least = msg
+            .split("at least ")
+            .nth(1)
+            .and_then(|rest| rest.split(' ').next())
+            .and_then(|count| count.parse::<usize>().ok())
+            .unwrap();
+        assert!((32768..40_000).contains(&at_least), "{err:#}");
+
+        // Within the limit, the same invalid byte is reported.
+        let err = NetString::decode_exact(Some(32767), &net_bytes(&long[80_000..])).unwrap_err();
+        assert!(err.to_string().contains("not valid UTF8"), "{err:#}");
+    }
+
+    #[test]
+    fn chat_builder_matches_struct_literals() {
+        let built = ChatComponent::text("You were kicked from ")
+            .color("red")
+            .bold()
+            .append(ChatComponent::text("127.0.0.1").color("yellow").italic())
+            .append(ChatComponent::text("!"));
+
+        let literal = ChatComponent {
+            text: Some("You were kicked from ".to_string()),
+            color: Some("red".to_string()),
+            bold: Some(true),
+            extra: vec![
+                ChatComponent {
+                    text: Some("127.0.0.1".to_string()),
+                    color: Some("yellow".to_string()),
+                    italic: Some(true),
+                    ..Default::default()
+                },
+                ChatComponent {
+                    text: Some("!".to_string()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let built = serde_json::to_value(&built).unwrap();
+        assert_eq!(built, serde_json::to_value(&literal).unwrap());
+        assert_eq!(
+            built,
+            serde_json::json!({
+                "text": "You were kicked from ",
+                "bold": true,
+                "color": "red",
+                "extra": [
+                    { "text": "127.0.0.1", "italic": true, "color": "yellow" },
+                    { "text": "!" },
+                ],
+            }),
+        );
+    }
+
+    proto_enum! {
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        enum TestHand {
+            Main = 0,
+            Off = 1,
+            Both = 300,
+        }
+    }
+
+    #[test]
+    fn proto_enum_maps_ids_and_rejects_unknown_ones() {
+        assert_eq!(TestHand::from_id(1), Some(TestHand::Off));
+        assert_eq!(TestHand::Both.to_id(), 300);
+        assert_eq!(TestHand::VARIANTS.len(), 3);
+
+        let bytes = encode(&TestHand::Both, ());
+        assert_eq!(&bytes[..], b"\xac\x02");
+        assert_eq!(TestHand::decode_exact((), &bytes).unwrap(), TestHand::Both);
+
+        let err = TestHand::decode_exact((), &Bytes::from_static(b"\x02")).unwrap_err();
+        assert!(err.to_string().contains("unknown"), "{err:#}");
+        assert!(err.to_string().contains("id 2"), "{err:#}");
+    }
+
+    proto_enum! {
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        enum TestFlag {
+            Invulnerable = 0,
+            Flying = 1,
+            AllowFlying = 2,
+        }
+    }
+
+    #[test]
+    fn enum_set_encodes_ordinals_as_bits() {
+        let mut set = EnumSet::<TestFlag>::new();
+        assert!(set.insert(&TestFlag::Invulnerable));
+        assert!(set.insert(&TestFlag::AllowFlying));
+        assert!(!set.insert(&TestFlag::AllowFlying));
+
+        let bytes = encode(&set, ());
+        assert_eq!(&bytes[..], [0b0000_0101]);
+        assert_eq!(set.size(()), 1);
+
+        let decoded = EnumSet::<TestFlag>::decode_exact((), &bytes).unwrap();
+        assert_eq!(decoded, set);
+        assert_eq!(
+            decoded.iter().copied().collect::<Vec<_>>(),
+            [TestFlag::Invulnerable, TestFlag::AllowFlying],
+        );
+
+        assert!(set.remove(&TestFlag::Invulnerable));
+        assert!(!set.contains(&TestFlag::Invulnerable));
+        assert_eq!(set.len(), 1);
+
+        let err =
+            EnumSet::<TestFlag>::decode_exact((), &Bytes::from_static(&[0b0000_1001])).unwrap_err();
+        assert!(err.to_string().contains("bit 3"), "{err:#}");
+    }
+
+    #[test]
+    fn slot_encodes_empty_and_simple_stacks() {
+        let empty = encode(&Slot::empty(), ());
+        assert_eq!(&empty[..], b"\x00");
+        assert!(Slot::decode_exact((), &empty).unwrap().is_empty());
+
+        let stack = encode(&Slot::new(RegistryId::new(1), 64), ());
+        assert_eq!(&stack[..], b"\x40\x01\x00\x00");
+        assert_eq!(Slot::new(RegistryId::new(1), 64).size(()), stack.len());
+
+        let decoded = Slot::decode_exact((), &stack).unwrap();
+        let stack = decoded.stack().unwrap();
+        assert_eq!((stack.count, stack.item_id.into_raw()), (64, 1));
+        assert!(stack.added_components.is_empty() && stack.removed_components.is_empty());
+
+        // A stack with an added NBT component and a removed component.
+        let bytes = Bytes::from_static(b"\x01\x05\x01\x01\x07\x00\x09");
+        let decoded = Slot::decode_exact((), &bytes).unwrap();
+        let stack = decoded.stack().unwrap();
+        assert_eq!(stack.added_components[0].type_id.into_raw(), 7);
+        assert_eq!(stack.removed_components[0].into_raw(), 9);
+        assert_eq!(encode(&decoded, ()), bytes);
+
+        assert!(Slot::new(RegistryId::new(1), 0).validate(()).is_err());
+    }
+
+    #[test]
+    fn identifiers_default_their_namespace_and_reject_invalid_characters() {
+        let decode = |text: &'static str| {
+            let id = NetString::from_static_str(text);
+            Identifier::decode_exact((), &encode(&id, None::<u32>))
+        };
+
+        let id = decode("stone").unwrap();
+        assert_eq!((id.namespace(), id.path()), ("minecraft", "stone"));
+
+        let id = decode("minecraft:stone").unwrap();
+        assert_eq!((id.namespace(), id.path()), ("minecraft", "stone"));
+
+        let err = decode("Stone").unwrap_err();
+        assert!(
+            format!("{err:#}").contains("invalid character 'S'"),
+            "{err:#}"
+        );
+        assert!(decode(":stone").is_err());
+
+        let id = Identifier(NetString::from_static_str("minecraft:Stone"));
+        assert!(id.validate(()).is_err());
+    }
+
+    #[test]
+    fn registry_ids_encode_as_var_ints_but_keep_their_kind() {
+        let block = RegistryId::<BlockKind>::new(300);
+        let encoded = encode(&block, ());
+        assert_eq!(encoded[..], [0xAC, 0x02]);
+        assert_eq!(block.size(()), encoded.len());
+        assert_eq!(
+            RegistryId::<BlockKind>::decode_exact((), &encoded).unwrap(),
+            block
+        );
+        assert_eq!(format!("{block:?}"), "RegistryId<BlockKind>(300)");
+
+        // Ids of different registries are distinct types and only convert through their raw value.
+        assert_ne!(
+            TypeId::of::<RegistryId<BlockKind>>(),
+            TypeId::of::<RegistryId<ItemKind>>()
+        );
+        let item = RegistryId::<ItemKind>::from(block.into_raw());
+        assert_eq!(i32::from(item), 300);
+    }
+
+    #[test]
+    fn chat_write_json_matches_serde_json() {
+        let component = ChatComponent {
+            click_event: Some(ChatClickEvent {
+                action: ChatClickAction::OpenUrl,
+                value: "https://example.com/?q=\"raft\"".to_string(),
+            }),
+            hover_event: Some(ChatHoverEvent {
+                show_item: Some(ChatShownItem {
+                    id: "minecraft:stone".to_string(),
+                    count: 64,
+                    tag: Some("{display:{Name:'\\u00a7a'}}".to_string()),
+                }),
+                ..Default::default()
+            }),
+            insertion: Some("tab\tnewline\n\u{1}".to_string()),
+            ..ChatComponent::text("Hello, \"world\" \u{e9}\u{1F600}")
+                .color("gold")
+                .bold()
+                .append(ChatComponent::text("child").italic())
+        };
+
+        let single = RootChatComponent::from(component.clone());
+        let multiple =
+            RootChatComponent(SmallVec::from_vec(vec![component, ChatComponent::text("")]));
+
+        for root in [single, multiple] {
+            let mut written = Vec::new();
+            root.write_json(&mut written).unwrap();
+            assert_eq!(
+                String::from_utf8(written).unwrap(),
+                serde_json::to_string(&root).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn arrays_claiming_billions_of_elements_fail_before_allocating() {
+        let claim = |len: u32| {
+            let mut buf = Vec::new();
+            VarInt(len as i32).encode((), &mut buf);
+            Bytes::from(buf)
+        };
+
+        // Lengths are `VarInt`s so four billion arrives as a negative length.
+        let err = Vec::<u64>::decode_exact(|| (), &claim(4_000_000_000)).unwrap_err();
+        assert!(err.to_string().contains("negative value"), "{err:#}");
+
+        let err = Vec::<u64>::decode_exact(|| (), &claim(i32::MAX as u32)).unwrap_err();
+        assert!(
+            err.to_string().contains("Array of 2147483647 element(s)"),
+            "{err:#}"
+        );
+
+        let err = BoundedVec::<u64, 16>::decode_exact(|| (), &claim(17)).unwrap_err();
+        assert!(err.to_string().contains("at most 16 element(s)"), "{err:#}");
+
+        let err =
+            Vec::<u64>::decode_exact(ArrayLen(4_000_000_000, || ()), &Bytes::new()).unwrap_err();
+        assert!(err.to_string().contains("remaining 0 byte(s)"), "{err:#}");
+    }
+
+    #[test]
+    fn canonical_floats_reject_nan_and_infinities() {
+        let decode_f32 = |bits: u32| {
+            CanonicalFloat::<f32>::decode_exact((), &Bytes::copy_from_slice(&bits.to_be_bytes()))
+        };
+        let decode_f64 = |bits: u64| {
+            CanonicalFloat::<f64>::decode_exact((), &Bytes::copy_from_slice(&bits.to_be_bytes()))
+        };
+
+        // The canonical quiet NaN, a NaN with a payload, and both infinities.
+        for bits in [0x7FC0_0000, 0x7F80_0001, 0x7F80_0000, 0xFF80_0000] {
+            let err = decode_f32(bits).unwrap_err();
+            assert!(err.to_string().contains("Expected a finite f32"), "{err:#}");
+        }
+        for bits in [0x7FF8_0000_0000_0000, 0x7FF0_0000_0000_0000] {
+            assert!(decode_f64(bits).is_err());
+        }
+
+        assert_eq!(decode_f32(1.5f32.to_bits()).unwrap(), CanonicalFloat(1.5));
+        assert_eq!(
+            decode_f32(f32::MAX.to_bits()).unwrap(),
+            CanonicalFloat(f32::MAX)
+        );
+        assert_eq!(
+            decode_f64((-0.25f64).to_bits()).unwrap(),
+            CanonicalFloat(-0.25)
+        );
+
+        // Non-finite values are also refused when sending.
+        assert!(CanonicalFloat(f64::NAN).validate(()).is_err());
+        assert!(CanonicalFloat(f32::INFINITY).validate(()).is_err());
+        assert!(CanonicalFloat(0.0f32).validate(()).is_ok());
+        assert_eq!(
+            &encode(&CanonicalFloat(1.5f32), ())[..],
+            1.5f32.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn legacy_codes_become_styled_runs() {
+        let root = RootChatComponent::from_legacy("§cHello §lWorld");
+        let [component] = &root.0[..] else {
+            panic!("expected a single root component, got {root:?}");
+        };
+        assert_eq!(component.text.as_deref(), Some(""));
+
+        let [hello, world] = &component.extra[..] else {
+            panic!("expected two runs, got {:?}", component.extra);
+        };
+        assert_eq!(hello.text.as_deref(), Some("Hello "));
+        assert_eq!(hello.color.as_deref(), Some("red"));
+        assert_eq!(hello.bold, None);
+        assert_eq!(world.text.as_deref(), Some("World"));
+        assert_eq!(world.color.as_deref(), Some("red"));
+        assert_eq!(world.bold, Some(true));
+
+        assert_eq!(root.to_legacy(), "§cHello §lWorld");
+
+        // Colors reset formats, unknown codes are dropped, and a trailing `§` is kept as text.
+        assert_eq!(
+            RootChatComponent::from_legacy("§l§9a§zb§").to_legacy(),
+            "§9ab§"
+        );
+    }
+
+    #[test]
+    fn one_of_dispatches_on_its_tag() {
+        type Either = OneOf2<VarInt, NetString>;
+
+        let encoded = encode(&Either::First(VarInt(300)), ((), 16));
+        assert_eq!(&encoded[..], [0x00, 0xAC, 0x02]);
+        let OneOf2::First(value) = Either::decode_exact(((), 16), &encoded).unwrap() else {
+            panic!("expected the first variant");
+        };
+        assert_eq!(value.0, 300);
+
+        let second = Either::Second(NetString::from_static_str("stone"));
+        let encoded = encode(&second, ((), 16));
+        assert_eq!(&encoded[..], b"\x01\x05stone");
+        assert_eq!(second.size(((), 16)), encoded.len());
+        let OneOf2::Second(value) = Either::decode_exact(((), 16), &encoded).unwrap() else {
+            panic!("expected the second variant");
+        };
+        assert_eq!(&*value, "stone");
+
+        // Each variant is checked with its own arguments.
+        assert!(second.validate(((), 4)).is_err());
+        assert!(Either::decode_exact(((), 4), &encoded).is_err());
+
+        let err = Either::decode_exact(((), 16), &Bytes::from_static(&[0x02, 0x00])).unwrap_err();
+        assert!(err.to_string().contains("Unknown OneOf2 tag 2"), "{err:#}");
+
+        type Three = OneOf3<u8, u8, u8>;
+
+        let encoded = encode(&Three::Third(7), ((), (), ()));
+        assert_eq!(&encoded[..], [0x02, 0x07]);
+        assert!(matches!(
+            Three::decode_exact(((), (), ()), &encoded).unwrap(),
+            OneOf3::Third(7)
+        ));
+        assert!(Three::decode_exact(((), (), ()), &Bytes::from_static(&[0x03, 0x07])).is_err());
+    }
+
+    #[test]
+    fn click_events_only_accept_known_actions() {
+        for action in ChatClickAction::ALL {
+            assert_eq!(ChatClickAction::from_name(action.name()), Some(action));
+            assert_eq!(serde_json::to_value(action).unwrap(), action.name());
+        }
+
+        let component = |action: &str| {
+            serde_json::from_value::<RootChatComponent>(serde_json::json!({
+                "text": "a",
+                "clickEvent": { "action": action, "value": "/help" },
+            }))
+        };
+
+        let root = component("run_command").unwrap();
+        let event = root.0[0].click_event.as_ref().unwrap();
+        assert_eq!(event.action, ChatClickAction::RunCommand);
+        assert_eq!(event.value, "/help");
+
+        assert!(component("open_file").is_err());
+        assert_eq!(ChatClickAction::from_name("open_file"), None);
+    }
+
+    #[test]
+    fn entity_metadata_can_preserve_unknown_types() {
+        // A known entry followed by one of type 99, another entry, and the terminator.
+        let bytes = Bytes::from_static(&[0x00, 0, 0x05, 0x01, 99, 0xAA, 0xBB, 0x02, 8, 0x01, 0xFF]);
+
+        // Strict decoding is the default.
+        assert!(EntityMetadata::decode_exact((), &bytes).is_err());
+        assert!(EntityMetadata::decode_exact(MetaDecodeMode::Strict, &bytes).is_err());
+
+        let metadata =
+            EntityMetadata::decode_exact(MetaDecodeMode::PreserveUnknown, &bytes).unwrap();
+        assert_eq!(metadata.0.len(), 2);
+        assert!(matches!(metadata.0[0].value, MetaValue::Byte(5)));
+        let MetaValue::Unknown { type_id, raw } = &metadata.0[1].value else {
+            panic!("expected an unknown value");
+        };
+        assert_eq!(type_id.0, 99);
+        assert_eq!(&raw[..], [0xAA, 0xBB, 0x02, 8, 0x01, 0xFF]);
+
+        // The unknown entry's raw bytes already end with the terminator.
+        metadata.validate(()).unwrap();
+        assert_eq!(encode(&metadata, ()), bytes);
+        assert_eq!(metadata.size(()), bytes.len());
+
+        // Unknown entries can only come last.
+        let mut reordered = metadata.clone();
+        reordered.0.swap(0, 1);
+        assert!(reordered.validate(()).is_err());
     }
 }
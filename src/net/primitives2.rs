@@ -2,21 +2,25 @@ use std::{fmt, io::Write, mem, str};
 
 use anyhow::Context;
 use bytes::Bytes;
+use derive_where::derive_where;
 use either::Either;
 
+use super::primitives::{split_identifier, validate_identifier};
+
 use crate::util::{
     proto::{
         byte_stream::{ByteCursor, ByteSize, ByteWriteStream, WriteCodepointCounter},
-        core::{schema_codec_struct, Codec},
+        core::{schema_codec_enum, schema_codec_struct, seq_codec_struct, Codec},
         decode_schema::{DeserializeSchema, SchemaView, ValidatedSchemaView},
         decode_seq::{
-            ArgForwardToFullAdapter, DecodeSeq, DecodeSeqExt, EndPosSummary, SeqDecodeCodec,
-            SeqDecoderArgForward, SeqDecoderFull, SeqDecoderSimple, SimpleToFullAdapter,
+            ArgForwardToFullAdapter, DecodeSeq, DecodeSeqExt, EndPosSummary, ReadCursor,
+            SeqDecodeCodec, SeqDecoderArgForward, SeqDecoderFull, SeqDecoderSimple,
+            SimpleToFullAdapter,
         },
         encode::{EncodeCodec, SerializeFrom, SerializeInto, WriteStreamFor},
         json_document::{JsonDocument, JsonSchema},
     },
-    var_int::{decode_var_i32_streaming, encode_var_u32},
+    var_int::{decode_var_i32_streaming, decode_var_i64_streaming, encode_var_u32, encode_var_u64},
 };
 
 // === Codec === //
@@ -49,6 +53,96 @@ mod sealed {
 
 use sealed::OurDecoders;
 
+// === Errors === //
+
+/// The ways in which encoding or decoding a [`MineCodec`] value can fail. Codecs still return
+/// `anyhow::Result` so that they compose with the rest of the codec but the errors they raise wrap
+/// a `ProtoError`, which callers can recover with `downcast_ref` to tell truncated input apart from
+/// malformed input.
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+pub enum ProtoError {
+    #[error(
+        "Unexpected end-of-stream while reading {what}: expected at least {expected} byte(s), got \
+         {available} (location: {location})."
+    )]
+    UnexpectedEof {
+        what: &'static str,
+        expected: usize,
+        available: usize,
+        location: String,
+    },
+
+    #[error(
+        "{what} claims to have {len} element(s) but only {available} byte(s) remain, which is \
+         too few since every element takes up at least one byte (location: {location})."
+    )]
+    TooManyElements {
+        what: &'static str,
+        len: u32,
+        available: usize,
+        location: String,
+    },
+
+    #[error("Invalid variant for {what}: got {value} (location: {location}).")]
+    InvalidVariant {
+        what: &'static str,
+        value: i64,
+        location: String,
+    },
+
+    #[error("Unexpected negative {what} with value {value} (location: {location}).")]
+    Negative {
+        what: &'static str,
+        value: i64,
+        location: String,
+    },
+
+    #[error(
+        "{what} is too long: it can be at most {max} {unit} long but is {len} {unit} long \
+         (location: {location})."
+    )]
+    LengthOverflow {
+        what: &'static str,
+        unit: &'static str,
+        len: u64,
+        max: u64,
+        location: String,
+    },
+
+    #[error("String byte data was not valid UTF8 (location: {location}).")]
+    InvalidUtf8 { location: String },
+
+    #[error("Invalid {what}: expected {expected} but got {actual}.")]
+    InvalidValue {
+        what: &'static str,
+        expected: &'static str,
+        actual: String,
+    },
+
+    #[error(
+        "{what} is too long to encode: it can be at most {max} {unit} long but is {len} {unit} \
+         long."
+    )]
+    EncodeOverflow {
+        what: &'static str,
+        unit: &'static str,
+        len: u64,
+        max: u64,
+    },
+}
+
+impl ProtoError {
+    /// Returns whether decoding failed only because the input ended early. When decoding from a
+    /// stream, this means that more bytes should be buffered before trying again rather than that
+    /// the peer violated the protocol.
+    pub fn is_incomplete(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<Self>(),
+            Some(Self::UnexpectedEof { .. })
+        )
+    }
+}
+
 // === Numerics === //
 
 // Primitives
@@ -71,15 +165,13 @@ macro_rules! impl_numerics {
 				cursor: &mut ByteCursor<'a>,
 				_args: &mut (),
 			) -> anyhow::Result<Self::View<'a>> {
-				let arr = cursor.read_arr()
-					.ok_or_else(|| anyhow::anyhow!(
-						"Unexpected end-of-stream while reading {}: expected {} byte(s), got {} \
-						 (location: {}).",
-						stringify!($ty),
-						mem::size_of::<Self>(),
-						cursor.remaining().len(),
-						cursor.format_location(),
-					))?;
+				let arr = cursor.try_read_arr()
+					.map_err(|available| ProtoError::UnexpectedEof {
+						what: stringify!($ty),
+						expected: mem::size_of::<$ty>(),
+						available,
+						location: cursor.format_location().to_string(),
+					})?;
 
 				Ok(<$ty>::from_be_bytes(arr))
 			}
@@ -96,6 +188,21 @@ macro_rules! impl_numerics {
 
 impl_numerics!(i8, u8, i16, u16, i32, u32, i64, f32, f64, u128);
 
+// Copyable primitives can be serialized through a shared reference by serializing a copy of them.
+macro_rules! impl_serialize_by_copy {
+	($($ty:ty),*$(,)?) => {$(
+		impl SerializeInto<MineCodec, $ty, ()> for &'_ $ty {
+			fn serialize(&mut self, stream: &mut impl WriteStreamFor<MineCodec>, args: &mut ()) -> anyhow::Result<()> {
+				<$ty>::serialize_from(&mut { **self }, stream, args)
+			}
+		}
+	)*};
+}
+
+impl_serialize_by_copy!(
+    i8, u8, i16, u16, i32, u32, i64, f32, f64, u128, bool, VarInt, VarUint, VarLong, VarULong,
+);
+
 impl DecodeSeq<MineCodec, ()> for bool {
     type Decoder = SimpleToFullAdapter<OurDecoders>;
 }
@@ -117,10 +224,12 @@ impl SeqDecoderSimple<bool, MineCodec, ()> for OurDecoders {
         match byte {
             0 => Ok(false),
             1 => Ok(true),
-            _ => anyhow::bail!(
-                "Invalid variant for boolean: expected 0 or 1, got {byte} (location: {})",
-                cursor.format_location(),
-            ),
+            _ => Err(ProtoError::InvalidVariant {
+                what: "boolean",
+                value: byte.into(),
+                location: cursor.format_location().to_string(),
+            }
+            .into()),
         }
     }
 }
@@ -156,8 +265,18 @@ impl SeqDecoderSimple<VarInt, MineCodec, ()> for OurDecoders {
         cursor: &mut ByteCursor<'a>,
         _args: &mut (),
     ) -> anyhow::Result<Self::View<'a>> {
-        decode_var_i32_streaming(cursor)?
-            .ok_or_else(|| anyhow::anyhow!("Unterminated VarInt (location: {})", cursor.pos()))
+        let start = cursor.pos();
+        decode_var_i32_streaming(cursor)?.ok_or_else(|| {
+            // An unterminated value consumes the rest of the stream and needs at least one more
+            // byte.
+            ProtoError::UnexpectedEof {
+                what: "VarInt",
+                expected: cursor.pos() - start + 1,
+                available: cursor.pos() - start,
+                location: cursor.format_location().to_string(),
+            }
+            .into()
+        })
     }
 }
 
@@ -205,10 +324,12 @@ impl SeqDecoderSimple<VarUint, MineCodec, ()> for OurDecoders {
     ) -> anyhow::Result<Self::View<'a>> {
         let value = VarInt::decode(cursor, ())?.0;
         u32::try_from(value).map_err(|_| {
-            anyhow::anyhow!(
-                "Unexpected negative VarUint with value {value} (location: {}).",
-                cursor.format_location()
-            )
+            ProtoError::Negative {
+                what: "VarUint",
+                value: value.into(),
+                location: cursor.format_location().to_string(),
+            }
+            .into()
         })
     }
 }
@@ -234,6 +355,115 @@ impl SerializeInto<MineCodec, VarUint, ()> for u32 {
     }
 }
 
+// VarLong
+#[derive(Debug, Copy, Clone)]
+pub struct VarLong(pub i64);
+
+impl DecodeSeq<MineCodec, ()> for VarLong {
+    type Decoder = SimpleToFullAdapter<OurDecoders>;
+}
+
+impl SeqDecoderSimple<VarLong, MineCodec, ()> for OurDecoders {
+    type Summary = EndPosSummary<usize>;
+    type View<'a> = i64;
+
+    fn reify_view(view: &Self::View<'_>) -> VarLong {
+        VarLong(*view)
+    }
+
+    fn decode<'a>(
+        _bind: [&'a (); 0],
+        cursor: &mut ByteCursor<'a>,
+        _args: &mut (),
+    ) -> anyhow::Result<Self::View<'a>> {
+        let start = cursor.pos();
+        decode_var_i64_streaming(cursor)?.ok_or_else(|| {
+            ProtoError::UnexpectedEof {
+                what: "VarLong",
+                expected: cursor.pos() - start + 1,
+                available: cursor.pos() - start,
+                location: cursor.format_location().to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+impl SerializeInto<MineCodec, VarLong, ()> for VarLong {
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        _args: &mut (),
+    ) -> anyhow::Result<()> {
+        encode_var_u64(&mut stream.as_write(), self.0)?;
+        Ok(())
+    }
+}
+
+impl SerializeInto<MineCodec, VarLong, ()> for i64 {
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut (),
+    ) -> anyhow::Result<()> {
+        VarLong(*self).serialize(stream, args)
+    }
+}
+
+// VarULong
+#[derive(Debug, Copy, Clone)]
+pub struct VarULong(pub u64);
+
+impl DecodeSeq<MineCodec, ()> for VarULong {
+    type Decoder = SimpleToFullAdapter<OurDecoders>;
+}
+
+impl SeqDecoderSimple<VarULong, MineCodec, ()> for OurDecoders {
+    type Summary = EndPosSummary<usize>;
+    type View<'a> = u64;
+
+    fn reify_view(view: &Self::View<'_>) -> VarULong {
+        VarULong(*view)
+    }
+
+    fn decode<'a>(
+        _bind: [&'a (); 0],
+        cursor: &mut ByteCursor<'a>,
+        _args: &mut (),
+    ) -> anyhow::Result<Self::View<'a>> {
+        let value = VarLong::decode(cursor, ())?.0;
+        u64::try_from(value).map_err(|_| {
+            ProtoError::Negative {
+                what: "VarULong",
+                value,
+                location: cursor.format_location().to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+impl SerializeInto<MineCodec, VarULong, ()> for VarULong {
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut (),
+    ) -> anyhow::Result<()> {
+        let value = i64::try_from(self.0).context("Attempted to send oversized VarULong")?;
+        VarLong(value).serialize(stream, args)
+    }
+}
+
+impl SerializeInto<MineCodec, VarULong, ()> for u64 {
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut (),
+    ) -> anyhow::Result<()> {
+        VarULong(*self).serialize(stream, args)
+    }
+}
+
 // === Strings === //
 
 // String
@@ -258,53 +488,60 @@ impl SeqDecoderFull<String, MineCodec, Option<u32>> for OurDecoders {
 
         // Validate length
         if let Some(max_len) = *max_len {
+            // Each UTF-16 code unit takes at most 3 bytes in UTF-8. Characters outside the basic
+            // multilingual plane take 4 bytes but also occupy two UTF-16 code units.
             let max_size = max_len
-                .checked_mul(4)
+                .checked_mul(3)
                 .filter(|&v| i32::try_from(v).is_ok())
                 .unwrap_or_else(|| {
                     panic!(
-						"Strings with a maximum codepoint length of {max_len} are untenable due to \
-						 encoding constraints."
+						"Strings with a maximum length of {max_len} UTF-16 code unit(s) are untenable \
+						 due to encoding constraints."
 					)
                 });
 
-            anyhow::ensure!(
-                size <= max_size,
-                "String byte stream is too long. The string is limited to {max_len} codepoint(s), \
-				 which can be encoded in up to {max_size} bytes, but the size of the string in \
-				 bytes is specified as {size} (location: {}).",
-                cursor.format_location(),
-            );
+            // The string is limited to `max_len` UTF-16 code units, which can be encoded in up to
+            // `max_size` bytes.
+            if size > max_size {
+                return Err(ProtoError::LengthOverflow {
+                    what: "String",
+                    unit: "byte(s)",
+                    len: size.into(),
+                    max: max_size.into(),
+                    location: cursor.format_location().to_string(),
+                }
+                .into());
+            }
         }
 
         // Fetch bytes
-        let data = cursor.read_slice(size as usize).ok_or_else(|| {
-            anyhow::anyhow!(
-                "Packet did not contain the necessary bytes to form the string. Available: {}, \
-				 Expected: {} (location: {}).",
-                cursor.remaining().len(),
-                size,
-                cursor.format_location(),
-            )
+        let data = cursor.try_read_slice(size as usize).map_err(|available| {
+            ProtoError::UnexpectedEof {
+                what: "string",
+                expected: size as usize,
+                available,
+                location: cursor.format_location().to_string(),
+            }
         })?;
 
         // Validate bytes
-        let mut codepoints = WriteCodepointCounter::default();
-        codepoints.write_all(data)?;
-        let codepoints = codepoints.codepoints().ok_or_else(|| {
-            anyhow::anyhow!(
-                "String byte data was not valid UTF8 (location: {})",
-                cursor.format_location(),
-            )
+        let mut counter = WriteCodepointCounter::default();
+        counter.write_all(data)?;
+        let actual_len = counter.utf16_len().ok_or(ProtoError::InvalidUtf8 {
+            location: cursor.format_location().to_string(),
         })?;
 
         if let Some(max_len) = *max_len {
-            anyhow::ensure!(
-                codepoints <= max_len as usize,
-                "String is too long: can contain at most {max_len} codepoint(s) but \
-				 contains {codepoints} (location: {}).",
-                cursor.format_location(),
-            );
+            if actual_len > max_len as usize {
+                return Err(ProtoError::LengthOverflow {
+                    what: "String",
+                    unit: "UTF-16 code unit(s)",
+                    len: actual_len as u64,
+                    max: max_len.into(),
+                    location: cursor.format_location().to_string(),
+                }
+                .into());
+            }
         }
 
         Ok(start_pos)
@@ -327,15 +564,23 @@ impl SeqDecoderFull<String, MineCodec, Option<u32>> for OurDecoders {
 
     fn skip(
         summary: &Self::Summary,
-        skip_to_start: impl Fn(&mut ByteCursor),
+        skip_to_start: impl Fn(&mut ByteCursor) -> anyhow::Result<()>,
         cursor: &mut ByteCursor,
         _args: &mut Option<u32>,
-    ) {
+    ) -> anyhow::Result<()> {
+        skip_to_start(cursor)?;
         debug_assert_eq!(cursor.pos(), *summary);
 
-        skip_to_start(cursor);
-        let byte_len = VarInt::decode(cursor, ()).unwrap().0;
-        let _ = cursor.read_slice(byte_len as usize);
+        let byte_len = VarInt::decode(cursor, ())?.0;
+        cursor.try_advance(byte_len as usize).ok_or_else(|| {
+            ProtoError::UnexpectedEof {
+                what: "skipped string",
+                expected: byte_len as usize,
+                available: cursor.remaining_len(),
+                location: cursor.format_location().to_string(),
+            }
+            .into()
+        })
     }
 }
 
@@ -351,17 +596,25 @@ impl<T: fmt::Display> SerializeInto<MineCodec, String, Option<u32>> for T {
 
         // Validate length
         if let Some(max_len) = *args {
-            let curr_len = counter.codepoints().unwrap();
-            anyhow::ensure!(
-				curr_len <= max_len as usize,
-				"String {:?} has a max length of {max_len} codepoint(s) but was {curr_len} codepoint(s) long.",
-				self.to_string(),
-			);
+            let curr_len = counter.utf16_len().unwrap();
+            if curr_len > max_len as usize {
+                return Err(ProtoError::EncodeOverflow {
+                    what: "String",
+                    unit: "UTF-16 code unit(s)",
+                    len: curr_len as u64,
+                    max: max_len.into(),
+                }
+                .into());
+            }
         }
 
         // Write out the packet
-        let len = i32::try_from(counter.bytes())
-            .map_err(|_| anyhow::anyhow!("String max length overflew an i32."))?;
+        let len = i32::try_from(counter.bytes()).map_err(|_| ProtoError::EncodeOverflow {
+            what: "String",
+            unit: "byte(s)",
+            len: counter.bytes() as u64,
+            max: i32::MAX as u64,
+        })?;
 
         VarInt(len).serialize(stream, &mut ())?;
         write!(stream.as_write(), "{self}")?;
@@ -392,6 +645,14 @@ pub struct Identifier(pub String);
 
 impl Identifier {
     pub const MAX_LEN: u32 = 32767;
+
+    pub fn namespace(&self) -> &str {
+        split_identifier(&self.0).0
+    }
+
+    pub fn path(&self) -> &str {
+        split_identifier(&self.0).1
+    }
 }
 
 impl fmt::Display for Identifier {
@@ -400,6 +661,10 @@ impl fmt::Display for Identifier {
     }
 }
 
+impl DecodeSeq<MineCodec, ()> for Identifier {
+    type Decoder = OurDecoders;
+}
+
 impl SeqDecoderFull<Identifier, MineCodec, ()> for OurDecoders {
     type Summary = <String as DecodeSeqExt<MineCodec, Option<u32>>>::Summary;
     type View<'a> = &'a str;
@@ -409,7 +674,18 @@ impl SeqDecoderFull<Identifier, MineCodec, ()> for OurDecoders {
     }
 
     fn summarize(cursor: &mut ByteCursor, _args: &mut ()) -> anyhow::Result<Self::Summary> {
-        String::summarize(cursor, &mut Some(Identifier::MAX_LEN))
+        let summary = String::summarize(cursor, &mut Some(Identifier::MAX_LEN))?;
+
+        // Safety: `summary` was just produced from this cursor's buffer.
+        let id = unsafe { String::view(&summary, cursor.clone(), Some(Identifier::MAX_LEN)) };
+        validate_identifier(id).with_context(|| {
+            format!(
+                "Invalid identifier (location: {}).",
+                cursor.format_location()
+            )
+        })?;
+
+        Ok(summary)
     }
 
     unsafe fn view<'a>(
@@ -422,10 +698,10 @@ impl SeqDecoderFull<Identifier, MineCodec, ()> for OurDecoders {
 
     fn skip(
         summary: &Self::Summary,
-        skip_to_start: impl Fn(&mut ByteCursor),
+        skip_to_start: impl Fn(&mut ByteCursor) -> anyhow::Result<()>,
         cursor: &mut ByteCursor,
         _args: &mut (),
-    ) {
+    ) -> anyhow::Result<()> {
         <String as DecodeSeqExt<MineCodec, _>>::skip(
             summary,
             skip_to_start,
@@ -457,6 +733,10 @@ pub trait MineProtoJsonValue: DeserializeSchema<JsonSchema, ()> {
     const MAX_LEN: u32;
 }
 
+impl<V: MineProtoJsonValue> DecodeSeq<MineCodec, ()> for Json<V> {
+    type Decoder = OurDecoders;
+}
+
 impl<V: MineProtoJsonValue> SeqDecoderFull<Json<V>, MineCodec, ()> for OurDecoders {
     type Summary = (JsonDocument, usize);
     type View<'a> = V::ValidatedView<'a>;
@@ -490,11 +770,11 @@ impl<V: MineProtoJsonValue> SeqDecoderFull<Json<V>, MineCodec, ()> for OurDecode
 
     fn skip(
         summary: &Self::Summary,
-        _skip_to_start: impl Fn(&mut ByteCursor),
+        _skip_to_start: impl Fn(&mut ByteCursor) -> anyhow::Result<()>,
         cursor: &mut ByteCursor,
         _args: &mut (),
-    ) {
-        cursor.set_pos(summary.1);
+    ) -> anyhow::Result<()> {
+        cursor.set_summary_pos(summary.1)
     }
 }
 
@@ -503,40 +783,132 @@ pub type Chat = Json<ChatRoot>;
 
 pub type ChatRoot = Either<Vec<ChatComponent>, ChatComponent>;
 
+/// The maximum length of a string within a chat component. Components are already limited by
+/// [`ChatRoot`]'s overall size but this keeps a single field from taking up the entire budget.
+const CHAT_STRING_MAX_LEN: u32 = 32767;
+
 schema_codec_struct! {
     pub struct chat_component::ChatComponent(JsonSchema) {
-        text: Option<String>,
-        translate: Option<String>,
-        keybind: Option<String>,
+        text: Option<String> => u32 : CHAT_STRING_MAX_LEN,
+        translate: Option<String> => u32 : CHAT_STRING_MAX_LEN,
+        keybind: Option<String> => u32 : CHAT_STRING_MAX_LEN,
         bold: Option<bool>,
         italic: Option<bool>,
         underlined: Option<bool>,
         strikethrough: Option<bool>,
         obfuscated: Option<bool>,
-        font: Option<String>,
-        color: Option<String>,
-        insertion: Option<String>,
+        font: Option<String> => u32 : CHAT_STRING_MAX_LEN,
+        color: Option<String> => u32 : CHAT_STRING_MAX_LEN,
+        insertion: Option<String> => u32 : CHAT_STRING_MAX_LEN,
         click_event: Option<ChatClickEvent>,
         hover_event: Option<ChatHoverEvent>,
+        extra: Option<Vec<ChatComponent>>,
     }
 
-    pub struct chat_click_event::ChatClickEvent(JsonSchema) {
-        action: String,
+    pub struct chat_click_value::ChatClickValue(JsonSchema) {
         value: String,
     }
 
-    pub struct chat_hover_event::ChatHoverEvent(JsonSchema) {
+    pub struct chat_hover_event::ChatHoverEvent(JsonSchema, validate = validate_hover_event) {
+        action: String,
         show_text: Option<String>,
         show_item: Option<ChatShownItem>,
         show_entity: Option<String>,
     }
 
-
     pub struct chat_shown_item::ChatShownItem(JsonSchema) {
         id: String,
         count: u8,
         tag: Option<String>,
+    }
+}
+
+schema_codec_enum! {
+    pub enum chat_click_event::ChatClickEvent(JsonSchema, validate = validate_click_event) by "action" {
+        OpenUrl(ChatClickValue) = "open_url",
+        RunCommand(ChatClickValue) = "run_command",
+        SuggestCommand(ChatClickValue) = "suggest_command",
+        ChangePage(ChatClickValue) = "change_page",
+        CopyToClipboard(ChatClickValue) = "copy_to_clipboard",
+    }
+}
+
+fn validate_click_event(view: &chat_click_event::View<'_>) -> anyhow::Result<()> {
+    use chat_click_event::View;
+
+    match view {
+        View::OpenUrl(view) => {
+            // Like the vanilla client, we only allow links to websites.
+            let url = view.value()?;
+            let rest = url
+                .strip_prefix("https://")
+                .or_else(|| url.strip_prefix("http://"));
+
+            if !rest.is_some_and(|rest| !rest.is_empty() && !rest.contains(char::is_whitespace)) {
+                return Err(ProtoError::InvalidValue {
+                    what: "`open_url` click event value",
+                    expected: "an HTTP(S) URL",
+                    actual: format!("{:?}", &*url),
+                }
+                .into());
+            }
+        }
+        View::RunCommand(view) => {
+            let command = view.value()?;
+            if !command.starts_with('/') {
+                return Err(ProtoError::InvalidValue {
+                    what: "`run_command` click event value",
+                    expected: "a command starting with `/`",
+                    actual: format!("{:?}", &*command),
+                }
+                .into());
+            }
+        }
+        View::ChangePage(view) => {
+            let page = view.value()?;
+            if !page.parse::<u32>().is_ok_and(|page| page > 0) {
+                return Err(ProtoError::InvalidValue {
+                    what: "`change_page` click event value",
+                    expected: "a positive page number",
+                    actual: format!("{:?}", &*page),
+                }
+                .into());
+            }
+        }
+        View::SuggestCommand(_) | View::CopyToClipboard(_) => {}
+    }
+
+    Ok(())
 }
+
+fn validate_hover_event(view: &chat_hover_event::View<'_>) -> anyhow::Result<()> {
+    let action = view.action()?;
+
+    // Only the payload named by the action is shown so any others are ignored.
+    let is_shown = match &*action {
+        "show_text" => view.show_text()?.is_some(),
+        "show_item" => view.show_item()?.is_some(),
+        "show_entity" => view.show_entity()?.is_some(),
+        _ => {
+            return Err(ProtoError::InvalidValue {
+                what: "hover event action",
+                expected: "one of `show_text`, `show_item`, or `show_entity`",
+                actual: format!("{:?}", &*action),
+            }
+            .into())
+        }
+    };
+
+    if !is_shown {
+        return Err(ProtoError::InvalidValue {
+            what: "hover event",
+            expected: "a payload matching its action",
+            actual: format!("no `{}` field", &*action),
+        }
+        .into());
+    }
+
+    Ok(())
 }
 
 impl MineProtoJsonValue for ChatRoot {
@@ -579,6 +951,17 @@ impl SerializeInto<MineCodec, TrailingByteArray, ()> for TrailingByteArray {
     }
 }
 
+impl SerializeInto<MineCodec, TrailingByteArray, ()> for &'_ TrailingByteArray {
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        _args: &mut (),
+    ) -> anyhow::Result<()> {
+        stream.push(&self.0)?;
+        Ok(())
+    }
+}
+
 impl SerializeInto<MineCodec, TrailingByteArray, ()> for &'_ [u8] {
     fn serialize(
         &mut self,
@@ -622,8 +1005,9 @@ where
         mut cursor: ByteCursor<'a>,
         args: A,
     ) -> Self::View<'a> {
-        // Skip the boolean field
-        cursor.advance(1);
+        // Skip the boolean field, which the summary guarantees is present.
+        let skipped = cursor.try_advance(1);
+        debug_assert!(skipped.is_some());
 
         // Produce the view
         summary
@@ -634,11 +1018,11 @@ where
 
     fn skip(
         summary: &Self::Summary,
-        _skip_to_start: impl Fn(&mut ByteCursor),
+        _skip_to_start: impl Fn(&mut ByteCursor) -> anyhow::Result<()>,
         cursor: &mut ByteCursor,
         _args: &mut A,
-    ) {
-        cursor.set_pos(summary.1);
+    ) -> anyhow::Result<()> {
+        cursor.set_summary_pos(summary.1)
     }
 }
 
@@ -662,5 +1046,797 @@ where
     }
 }
 
+impl<T, V, A> SerializeInto<MineCodec, Option<T>, A> for &'_ Option<V>
+where
+    for<'r> &'r V: SerializeInto<MineCodec, T, A>,
+{
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut A,
+    ) -> anyhow::Result<()> {
+        Option::<T>::serialize_from(&mut self.as_ref(), stream, args)
+    }
+}
+
+// RemainingOptional
+/// An optional value with no presence prefix: it is present if and only if any bytes remain in the
+/// packet, so it can only be used as the trailing field of a packet.
+#[derive(Debug, Clone)]
+pub struct RemainingOptional<T>(pub Option<T>);
+
+impl<T, A> DecodeSeq<MineCodec, A> for RemainingOptional<T>
+where
+    T: DecodeSeqExt<MineCodec, A>,
+{
+    type Decoder = OurDecoders;
+}
+
+impl<T, A> SeqDecoderFull<RemainingOptional<T>, MineCodec, A> for OurDecoders
+where
+    T: DecodeSeqExt<MineCodec, A>,
+{
+    type Summary = (Option<T::Summary>, usize);
+    type View<'a> = Option<T::View<'a>>;
+
+    fn reify_view(view: &Self::View<'_>) -> RemainingOptional<T> {
+        RemainingOptional(view.as_ref().map(|view| T::reify_view(view)))
+    }
+
+    fn summarize(cursor: &mut ByteCursor, args: &mut A) -> anyhow::Result<Self::Summary> {
+        if cursor.is_empty() {
+            Ok((None, cursor.pos()))
+        } else {
+            Ok((Some(T::summarize(cursor, args)?), cursor.pos()))
+        }
+    }
+
+    unsafe fn view<'a>(
+        summary: &'a Self::Summary,
+        cursor: ByteCursor<'a>,
+        args: A,
+    ) -> Self::View<'a> {
+        summary
+            .0
+            .as_ref()
+            .map(|summary| T::view(summary, cursor, args))
+    }
+
+    fn skip(
+        summary: &Self::Summary,
+        _skip_to_start: impl Fn(&mut ByteCursor) -> anyhow::Result<()>,
+        cursor: &mut ByteCursor,
+        _args: &mut A,
+    ) -> anyhow::Result<()> {
+        cursor.set_summary_pos(summary.1)
+    }
+}
+
+impl<T, V, A> SerializeInto<MineCodec, RemainingOptional<T>, A> for RemainingOptional<V>
+where
+    V: SerializeInto<MineCodec, T, A>,
+{
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut A,
+    ) -> anyhow::Result<()> {
+        RemainingOptional::<T>::serialize_from(&mut self.0, stream, args)
+    }
+}
+
+impl<T, V, A> SerializeInto<MineCodec, RemainingOptional<T>, A> for &'_ RemainingOptional<V>
+where
+    for<'r> &'r V: SerializeInto<MineCodec, T, A>,
+{
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut A,
+    ) -> anyhow::Result<()> {
+        RemainingOptional::<T>::serialize_from(&mut self.0.as_ref(), stream, args)
+    }
+}
+
+impl<T, V, A> SerializeInto<MineCodec, RemainingOptional<T>, A> for Option<V>
+where
+    V: SerializeInto<MineCodec, T, A>,
+{
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut A,
+    ) -> anyhow::Result<()> {
+        if let Some(inner) = self {
+            T::serialize_from(inner, stream, args)?;
+        }
+
+        Ok(())
+    }
+}
+
 // Vec
-// TODO
+impl<T, A> DecodeSeq<MineCodec, A> for Vec<T>
+where
+    T: DecodeSeqExt<MineCodec, A>,
+    A: Clone,
+{
+    type Decoder = OurDecoders;
+}
+
+/// The summary of a `VarUint`-prefixed array: the start position and summary of every element,
+/// followed by the position of the end of the array.
+pub type VecSummary<S> = (Vec<(usize, S)>, usize);
+
+#[derive_where(Clone; A)]
+pub struct VecView<'a, T, A>
+where
+    T: DecodeSeqExt<MineCodec, A>,
+{
+    // Safety invariant: the cursor and all the summary's elements have the same backing buffer.
+    summary: &'a VecSummary<T::Summary>,
+    cursor: ByteCursor<'a>,
+    args: A,
+}
+
+impl<'a, T, A> VecView<'a, T, A>
+where
+    T: DecodeSeqExt<MineCodec, A>,
+    A: Clone,
+{
+    pub fn len(&self) -> usize {
+        self.summary.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summary.0.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<T::View<'a>> {
+        let (pos, summary) = self.summary.0.get(index)?;
+
+        Some(unsafe {
+            // Safety: by invariant, the element's summary was generated using this cursor's
+            // backing buffer.
+            T::view(
+                summary,
+                self.cursor.clone().with_pos(*pos),
+                self.args.clone(),
+            )
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T::View<'a>> + '_ {
+        (0..self.len()).map(|index| self.get(index).unwrap())
+    }
+}
+
+impl<T, A> fmt::Debug for VecView<'_, T, A>
+where
+    T: DecodeSeqExt<MineCodec, A>,
+    A: Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, A> SeqDecoderFull<Vec<T>, MineCodec, A> for OurDecoders
+where
+    T: DecodeSeqExt<MineCodec, A>,
+    A: Clone,
+{
+    type Summary = VecSummary<T::Summary>;
+    type View<'a> = VecView<'a, T, A>;
+
+    fn reify_view(view: &Self::View<'_>) -> Vec<T> {
+        view.iter().map(|view| T::reify_view(&view)).collect()
+    }
+
+    fn summarize(cursor: &mut ByteCursor, args: &mut A) -> anyhow::Result<Self::Summary> {
+        let len = VarUint::decode(cursor, ())?.0;
+
+        // Every element takes up at least one byte so this prevents a bogus length from causing a
+        // huge allocation.
+        if len as usize > cursor.len() {
+            return Err(ProtoError::TooManyElements {
+                what: "Array",
+                len,
+                available: cursor.len(),
+                location: cursor.format_location().to_string(),
+            }
+            .into());
+        }
+
+        let mut elements = Vec::with_capacity(len as usize);
+
+        for i in 0..len {
+            let start = cursor.pos();
+            let summary = T::summarize(cursor, args)
+                .with_context(|| format!("Failed to decode element {i} of {len}-element array."))?;
+
+            elements.push((start, summary));
+        }
+
+        Ok((elements, cursor.pos()))
+    }
+
+    unsafe fn view<'a>(
+        summary: &'a Self::Summary,
+        cursor: ByteCursor<'a>,
+        args: A,
+    ) -> Self::View<'a> {
+        // Safety: the caller guarantees that the summary was generated using this cursor's backing
+        // buffer, and so too were every one of its element summaries.
+        VecView {
+            summary,
+            cursor,
+            args,
+        }
+    }
+
+    fn skip(
+        summary: &Self::Summary,
+        _skip_to_start: impl Fn(&mut ByteCursor) -> anyhow::Result<()>,
+        cursor: &mut ByteCursor,
+        _args: &mut A,
+    ) -> anyhow::Result<()> {
+        cursor.set_summary_pos(summary.1)
+    }
+}
+
+impl<T, V, A> SerializeInto<MineCodec, Vec<T>, A> for Vec<V>
+where
+    V: SerializeInto<MineCodec, T, A>,
+{
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut A,
+    ) -> anyhow::Result<()> {
+        let mut len = u32::try_from(self.len()).context("Attempted to send oversized array")?;
+        VarUint::serialize_from(&mut len, stream, &mut ())?;
+
+        for elem in self {
+            T::serialize_from(elem, stream, args)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, V, A> SerializeInto<MineCodec, Vec<T>, A> for &'_ Vec<V>
+where
+    for<'r> &'r V: SerializeInto<MineCodec, T, A>,
+{
+    fn serialize(
+        &mut self,
+        stream: &mut impl WriteStreamFor<MineCodec>,
+        args: &mut A,
+    ) -> anyhow::Result<()> {
+        let mut len = u32::try_from(self.len()).context("Attempted to send oversized array")?;
+        VarUint::serialize_from(&mut len, stream, &mut ())?;
+
+        for elem in self.iter() {
+            T::serialize_from(&mut &*elem, stream, args)?;
+        }
+
+        Ok(())
+    }
+}
+
+// === Structures === //
+
+seq_codec_struct! {
+    pub struct property::Property(MineCodec) {
+        name: String => Option<u32> : Some(32767),
+        value: String => Option<u32> : Some(32767),
+        signature: Option<String> => Option<u32> : Some(32767),
+    }
+
+    pub struct sb_handshake::Handshake(MineCodec) {
+        protocol_version: VarInt,
+        server_address: String => Option<u32> : Some(255),
+        server_port: u16,
+        next_state: VarInt,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::var_int::encode_var_u32;
+
+    fn net_string(text: &str) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode_var_u32(&mut buf, text.len() as i32).unwrap();
+        buf.extend_from_slice(text.as_bytes());
+        buf
+    }
+
+    fn decode_chat(text: &str) -> anyhow::Result<Chat> {
+        <Chat as DecodeSeqExt<MineCodec, ()>>::decode(&mut ByteCursor::new(&net_string(text)), ())
+    }
+
+    #[test]
+    fn click_events_check_their_value_against_their_action() {
+        let click = |action: &str, value: &str| {
+            decode_chat(&format!(
+                r#"{{"text":"a","click_event":{{"action":"{action}","value":"{value}"}}}}"#
+            ))
+        };
+
+        click("open_url", "https://example.com/?q=raft").unwrap();
+        click("run_command", "/help").unwrap();
+        click("copy_to_clipboard", "anything at all").unwrap();
+
+        assert_eq!(
+            proto_error(click("open_url", "example.com")),
+            ProtoError::InvalidValue {
+                what: "`open_url` click event value",
+                expected: "an HTTP(S) URL",
+                actual: r#""example.com""#.to_string(),
+            },
+        );
+        assert!(click("open_url", "file:///etc/passwd").is_err());
+        assert!(click("open_url", "https://").is_err());
+        assert!(click("run_command", "help").is_err());
+        assert!(click("change_page", "0").is_err());
+        assert!(click("open_file", "/tmp").is_err());
+    }
+
+    #[test]
+    fn hover_events_check_the_payload_named_by_their_action() {
+        let hover = |event: &str| decode_chat(&format!(r#"{{"text":"a","hover_event":{event}}}"#));
+
+        hover(r#"{"action":"show_text","show_text":"hi"}"#).unwrap();
+
+        // Payloads for other actions are ignored.
+        hover(r#"{"action":"show_entity","show_entity":"Notch","show_text":"hi"}"#).unwrap();
+
+        assert_eq!(
+            proto_error(hover(r#"{"action":"show_item","show_text":"hi"}"#)),
+            ProtoError::InvalidValue {
+                what: "hover event",
+                expected: "a payload matching its action",
+                actual: "no `show_item` field".to_string(),
+            },
+        );
+        assert!(matches!(
+            proto_error(hover(r#"{"action":"show_achievement","show_text":"hi"}"#)),
+            ProtoError::InvalidValue {
+                what: "hover event action",
+                ..
+            },
+        ));
+        assert!(hover(r#"{"show_text":"hi"}"#).is_err());
+    }
+
+    fn nested_extra_chain(depth: usize) -> String {
+        let mut text = String::new();
+        for _ in 0..depth {
+            text.push_str(r#"{"text":"a","extra":["#);
+        }
+        text.push_str(r#"{"text":"a"}"#);
+        for _ in 0..depth {
+            text.push_str("]}");
+        }
+        text
+    }
+
+    #[test]
+    fn chat_validation_bails_on_deep_extra_chains() {
+        // Each level nests an object and an array, so this stays within the parser's limit while
+        // exceeding the validator's.
+        let Err(err) = decode_chat(&nested_extra_chain(40)) else {
+            panic!("a 40-deep `extra` chain should be rejected");
+        };
+        assert!(err.to_string().contains("maximum nesting depth"), "{err:#}");
+
+        assert!(decode_chat(&nested_extra_chain(8)).is_ok());
+    }
+
+    #[test]
+    fn vec_of_var_ints_views_and_reifies() {
+        let mut buf = Vec::new();
+        Vec::<VarInt>::serialize_from(&mut vec![1, -1, 300], &mut buf, &mut ()).unwrap();
+        buf.push(0xAA);
+
+        let mut cursor = ByteCursor::new(&buf);
+        let bound = Vec::<VarInt>::summarize_bound(&mut cursor, &mut ()).unwrap();
+        assert_eq!(cursor.remaining(), [0xAA]);
+
+        let view = bound.view(());
+        assert_eq!(view.len(), 3);
+        assert_eq!(view.get(2), Some(300));
+        assert_eq!(view.get(3), None);
+        assert_eq!(view.iter().collect::<Vec<_>>(), [1, -1, 300]);
+
+        let reified = Vec::<VarInt>::reify_view(&view);
+        assert_eq!(
+            reified.iter().map(|v| v.0).collect::<Vec<_>>(),
+            [1, -1, 300]
+        );
+
+        let empty =
+            <Vec<VarInt> as DecodeSeqExt<MineCodec, ()>>::decode(&mut ByteCursor::new(&[0]), ())
+                .unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn vec_of_strings_views_and_reifies() {
+        let mut buf = Vec::new();
+        Vec::<String>::serialize_from(&mut vec!["a", "", "ccc"], &mut buf, &mut Some(16)).unwrap();
+
+        let mut cursor = ByteCursor::new(&buf);
+        let bound = Vec::<String>::summarize_bound(&mut cursor, &mut Some(16)).unwrap();
+        assert!(cursor.remaining().is_empty());
+
+        let view = bound.view(Some(16));
+        assert_eq!(view.get(2), Some("ccc"));
+        assert_eq!(view.iter().collect::<Vec<_>>(), ["a", "", "ccc"]);
+        assert_eq!(Vec::<String>::reify_view(&view), ["a", "", "ccc"]);
+
+        // Element limits are passed down to every element.
+        assert!(Vec::<String>::summarize_bound(&mut ByteCursor::new(&buf), &mut Some(2)).is_err());
+
+        // The length prefix claims more elements than there are bytes.
+        let Err(err) = <Vec<String> as DecodeSeqExt<MineCodec, Option<u32>>>::decode(
+            &mut ByteCursor::new(&[0x05, 0x00]),
+            None,
+        ) else {
+            panic!("an over-long array should be rejected");
+        };
+        assert!(
+            matches!(
+                err.downcast_ref::<ProtoError>(),
+                Some(ProtoError::TooManyElements {
+                    len: 5,
+                    available: 1,
+                    ..
+                }),
+            ),
+            "{err:#}"
+        );
+    }
+
+    fn proto_error<T>(result: anyhow::Result<T>) -> ProtoError {
+        let Err(err) = result else {
+            panic!("expected decoding to fail");
+        };
+
+        err.downcast_ref::<ProtoError>()
+            .unwrap_or_else(|| panic!("expected a `ProtoError` but got {err:#}"))
+            .clone()
+    }
+
+    #[test]
+    fn errors_tell_truncated_input_from_invalid_input() {
+        fn decode<T: DecodeSeqExt<MineCodec, ()>>(bytes: &[u8]) -> anyhow::Result<T> {
+            T::decode(&mut ByteCursor::new(bytes), ())
+        }
+
+        assert_eq!(
+            proto_error(decode::<u32>(&[0x00, 0x01])),
+            ProtoError::UnexpectedEof {
+                what: "u32",
+                expected: 4,
+                available: 2,
+                location: "0 byte(s) from the packet frame start".to_string(),
+            },
+        );
+        assert!(matches!(
+            proto_error(decode::<VarInt>(&[0x80, 0x80])),
+            ProtoError::UnexpectedEof {
+                what: "VarInt",
+                available: 2,
+                ..
+            },
+        ));
+        assert!(matches!(
+            proto_error(String::decode(&mut ByteCursor::new(&[0x05, b'a']), None)),
+            ProtoError::UnexpectedEof {
+                what: "string",
+                expected: 5,
+                available: 1,
+                ..
+            },
+        ));
+
+        assert_eq!(
+            proto_error(decode::<bool>(&[0x02])),
+            ProtoError::InvalidVariant {
+                what: "boolean",
+                value: 2,
+                location: "1 byte(s) from the packet frame start".to_string(),
+            },
+        );
+        assert!(matches!(
+            proto_error(decode::<VarUint>(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F])),
+            ProtoError::Negative {
+                what: "VarUint",
+                value: -1,
+                ..
+            },
+        ));
+        assert!(matches!(
+            proto_error(String::decode(&mut ByteCursor::new(&[0x01, 0xFF]), None)),
+            ProtoError::InvalidUtf8 { .. },
+        ));
+
+        // Truncation is only reported when more input could have completed the value.
+        assert!(ProtoError::is_incomplete(
+            &decode::<u32>(&[0x00]).unwrap_err()
+        ));
+        assert!(!ProtoError::is_incomplete(
+            &decode::<bool>(&[0x02]).unwrap_err()
+        ));
+    }
+
+    #[test]
+    fn over_long_strings_fail_to_encode() {
+        let mut buf = Vec::new();
+        let err = String::serialize_from(&mut "abc", &mut buf, &mut Some(2)).unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<ProtoError>(),
+            Some(&ProtoError::EncodeOverflow {
+                what: "String",
+                unit: "UTF-16 code unit(s)",
+                len: 3,
+                max: 2,
+            }),
+        );
+    }
+
+    #[test]
+    fn property_decodes_mutates_and_re_encodes() {
+        let mut bytes = net_string("textures");
+        bytes.extend_from_slice(&net_string("e30="));
+        bytes.push(0x00);
+
+        let mut cursor = ByteCursor::new(&bytes);
+        let bound = Property::summarize_bound(&mut cursor, &mut ()).unwrap();
+        assert!(cursor.remaining().is_empty());
+
+        let view = bound.view(());
+        assert_eq!(view.name(), "textures");
+        assert_eq!(view.value(), "e30=");
+        assert_eq!(view.signature(), None);
+
+        let mut property = Property::reify_view(&view);
+        property.signature = Some("sig".to_string());
+
+        let mut encoded = Vec::new();
+        Property::serialize_from(&mut property, &mut encoded, &mut ()).unwrap();
+
+        let mut expected = bytes[..bytes.len() - 1].to_vec();
+        expected.push(0x01);
+        expected.extend_from_slice(&net_string("sig"));
+        assert_eq!(encoded, expected);
+
+        let decoded =
+            <Property as DecodeSeqExt<MineCodec, ()>>::decode(&mut ByteCursor::new(&encoded), ())
+                .unwrap();
+        assert_eq!(decoded.signature.as_deref(), Some("sig"));
+    }
+
+    fn decode_identifier(text: &str) -> anyhow::Result<Identifier> {
+        <Identifier as DecodeSeqExt<MineCodec, ()>>::decode(
+            &mut ByteCursor::new(&net_string(text)),
+            (),
+        )
+    }
+
+    #[test]
+    fn identifiers_default_their_namespace_and_reject_invalid_characters() {
+        let id = decode_identifier("stone").unwrap();
+        assert_eq!((id.namespace(), id.path()), ("minecraft", "stone"));
+
+        let id = decode_identifier("minecraft:stone").unwrap();
+        assert_eq!((id.namespace(), id.path()), ("minecraft", "stone"));
+
+        let id = decode_identifier("raft:blocks/log.oak").unwrap();
+        assert_eq!((id.namespace(), id.path()), ("raft", "blocks/log.oak"));
+
+        let err = decode_identifier("Stone").unwrap_err();
+        assert!(
+            format!("{err:#}").contains("invalid character 'S'"),
+            "{err:#}"
+        );
+        assert!(decode_identifier("mine/craft:stone").is_err());
+        assert!(decode_identifier("minecraft:").is_err());
+    }
+
+    schema_codec_struct! {
+        struct capped_name::CappedName(JsonSchema) {
+            name: String => u32 : 8,
+        }
+    }
+
+    fn validate_capped_name(text: &str) -> anyhow::Result<()> {
+        let document = JsonDocument::parse(text)?;
+        <CappedName as DeserializeSchema<JsonSchema, ()>>::view_object(
+            &document,
+            Some(document.root()),
+            (),
+        )?
+        .validate_deep()
+    }
+
+    #[test]
+    fn schema_string_fields_enforce_their_max_len() {
+        validate_capped_name(r#"{"name":"ééééébcd"}"#).unwrap();
+
+        let err = validate_capped_name(r#"{"name":"éééééabcd"}"#).unwrap_err();
+        assert!(err.to_string().contains("max length of 8"), "{err:#}");
+    }
+
+    schema_codec_struct! {
+        struct circle::Circle(JsonSchema) {
+            radius: u8,
+        }
+
+        struct rect::Rect(JsonSchema) {
+            width: u8,
+            height: u8,
+        }
+    }
+
+    schema_codec_enum! {
+        enum shape::Shape(JsonSchema) by "type" {
+            Circle(Circle) = "circle",
+            Rect(Rect) = "rect",
+        }
+    }
+
+    fn decode_shape(text: &str) -> anyhow::Result<Shape> {
+        let document = JsonDocument::parse(text)?;
+        let view = <Shape as DeserializeSchema<JsonSchema, ()>>::view_object(
+            &document,
+            Some(document.root()),
+            (),
+        )?;
+        view.validate_deep()?;
+        view.try_reify()
+    }
+
+    #[test]
+    fn schema_enums_dispatch_on_their_tag_field() {
+        let Shape::Circle(circle) = decode_shape(r#"{"type":"circle","radius":3}"#).unwrap() else {
+            panic!("expected a circle");
+        };
+        assert_eq!(circle.radius, 3);
+
+        // The tag can appear anywhere in the object.
+        let Shape::Rect(rect) = decode_shape(r#"{"width":4,"height":5,"type":"rect"}"#).unwrap()
+        else {
+            panic!("expected a rect");
+        };
+        assert_eq!((rect.width, rect.height), (4, 5));
+
+        // Fields are read according to the tagged variant.
+        assert!(decode_shape(r#"{"type":"rect","radius":3}"#).is_err());
+
+        let Err(err) = decode_shape(r#"{"type":"triangle"}"#) else {
+            panic!("expected an unknown tag to be rejected");
+        };
+        assert!(
+            err.to_string()
+                .contains(r#"Unknown Shape `type` "triangle""#),
+            "{err:#}"
+        );
+        assert!(decode_shape(r#"{"radius":3}"#).is_err());
+        assert!(decode_shape(r#"{"type":1,"radius":3}"#).is_err());
+    }
+
+    #[test]
+    fn var_longs_round_trip_their_extremes() {
+        for (value, len) in [(0, 1), (i64::MAX, 9), (i64::MIN, 10), (-1, 10)] {
+            let mut buf = Vec::new();
+            VarLong::serialize_from(&mut { value }, &mut buf, &mut ()).unwrap();
+            assert_eq!(buf.len(), len, "{value}");
+
+            let mut cursor = ByteCursor::new(&buf);
+            let decoded =
+                <VarLong as DecodeSeqExt<MineCodec, ()>>::decode(&mut cursor, ()).unwrap();
+            assert_eq!(decoded.0, value);
+            assert!(cursor.is_empty());
+        }
+
+        let mut buf = Vec::new();
+        VarULong::serialize_from(&mut (i64::MAX as u64), &mut buf, &mut ()).unwrap();
+        let decoded =
+            <VarULong as DecodeSeqExt<MineCodec, ()>>::decode(&mut ByteCursor::new(&buf), ())
+                .unwrap();
+        assert_eq!(decoded.0, i64::MAX as u64);
+
+        assert!(VarULong::serialize_from(&mut { u64::MAX }, &mut Vec::new(), &mut ()).is_err());
+
+        let mut buf = Vec::new();
+        VarLong::serialize_from(&mut -1, &mut buf, &mut ()).unwrap();
+        assert!(
+            <VarULong as DecodeSeqExt<MineCodec, ()>>::decode(&mut ByteCursor::new(&buf), ())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn handshakes_encode_by_reference() {
+        let handshake = Handshake {
+            protocol_version: VarInt(763),
+            server_address: "localhost".to_string(),
+            server_port: 25565,
+            next_state: VarInt(1),
+        };
+
+        let mut encoded = Vec::new();
+        Handshake::serialize_from(&mut &handshake, &mut encoded, &mut ()).unwrap();
+
+        let mut expected = vec![0xFB, 0x05];
+        expected.extend_from_slice(&net_string("localhost"));
+        expected.extend_from_slice(&25565u16.to_be_bytes());
+        expected.push(0x01);
+        assert_eq!(encoded, expected);
+
+        // The packet is still ours to use after being encoded.
+        let decoded =
+            <Handshake as DecodeSeqExt<MineCodec, ()>>::decode(&mut ByteCursor::new(&encoded), ())
+                .unwrap();
+        assert_eq!(decoded.protocol_version.0, handshake.protocol_version.0);
+        assert_eq!(decoded.server_address, handshake.server_address);
+        assert_eq!(decoded.server_port, handshake.server_port);
+        assert_eq!(decoded.next_state.0, handshake.next_state.0);
+    }
+
+    #[test]
+    fn skipping_with_crafted_summaries_errors() {
+        let buf = [0x01, 0x2A];
+
+        // A summary which agrees with the buffer skips to the end of the value.
+        let mut cursor = ByteCursor::new(&buf);
+        let summary =
+            <Option<u8> as DecodeSeqExt<MineCodec, ()>>::summarize(&mut cursor, &mut ()).unwrap();
+        let mut cursor = ByteCursor::new(&buf);
+        <Option<u8> as DecodeSeqExt<MineCodec, ()>>::skip(
+            &summary,
+            |_| Ok(()),
+            &mut cursor,
+            &mut (),
+        )
+        .unwrap();
+        assert!(cursor.is_empty());
+
+        // One claiming the value ends past the buffer is rejected without moving the cursor.
+        let mut cursor = ByteCursor::new(&buf);
+        let err = <Option<u8> as DecodeSeqExt<MineCodec, ()>>::skip(
+            &(summary.0, 999),
+            |_| Ok(()),
+            &mut cursor,
+            &mut (),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Summary position 999"), "{err:#}");
+        assert_eq!(cursor.pos(), 0);
+
+        // Strings re-read their length while skipping so a buffer which doesn't match the summary
+        // is reported rather than silently exhausting the cursor.
+        let mut cursor = ByteCursor::new(&[0x05, b'a']);
+        let err =
+            <String as DecodeSeqExt<MineCodec, _>>::skip(&0, |_| Ok(()), &mut cursor, &mut None)
+                .unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<ProtoError>(),
+                Some(ProtoError::UnexpectedEof {
+                    expected: 5,
+                    available: 1,
+                    ..
+                }),
+            ),
+            "{err:#}"
+        );
+    }
+}
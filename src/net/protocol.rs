@@ -1,21 +1,27 @@
 use super::primitives::{
-    codec_struct, ByteArray, Chat, Codec, Identifier, NetString, SizedCodec, Uuid, VarInt,
+    codec_struct, proto_enum, BoundedVec, ByteArray, Chat, Codec, Identifier, JsonValue, NetString,
+    Position, RawNbt, SizedCodec, Uuid, VarInt,
 };
-use super::transport::{FramedPacket, UnframedPacket};
+use super::transport::{Clientbound, FramedPacket, Serverbound, UnframedPacket};
 
 use crate::util::{bytes_integration::Snip, proto::byte_stream::ByteCursor};
 
 use bytes::{BufMut, Bytes};
 use std::any::type_name;
 
+#[cfg(feature = "arbitrary")]
+use {super::fuzzing::ArbitraryCodec, arbitrary::Unstructured};
+
 // === Core === //
 
 macro_rules! derive_protocol {
-    ($(
+    (@introduced_in) => { ProtocolVersion(i32::MIN) };
+    (@introduced_in $since:expr) => { $since };
+    (|$version:ident| $(
 		$(#[$wrapper_attr:meta])*
-		$wrapper_vis:vis mod $wrapper_name:ident {$(
+		$wrapper_vis:vis mod $wrapper_name:ident($direction:ident) {$(
 			$(#[$packet_attr:meta])*
-			struct $packet_name:ident($id:literal) {
+			struct $packet_name:ident($id:literal $(, since $since:expr)?) {
 				$($field_name:ident: $field_ty:ty $(=> $field_config:expr)?),*
 				$(,)?
 			}
@@ -33,41 +39,117 @@ macro_rules! derive_protocol {
 
 			pub use Packet::*;
 
-			impl Codec<()> for Packet {
+			impl Codec<ProtocolVersion> for Packet {
 				#[allow(unused_variables)]
-				fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+				fn decode(version: ProtocolVersion, src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
 					let id = VarInt::decode((), src, cursor)?.0;
 
 					match id {
-						$($id => Ok($packet_name::decode((), src, cursor)?.into()),)*
+						$($id if version >= $packet_name::INTRODUCED_IN => {
+							Ok($packet_name::decode(version, src, cursor)?.into())
+						})*
 						_ => anyhow::bail!("Unknown packet with ID {id} in state {}", type_name::<Self>()),
 					}
 				}
 
 				#[allow(unused_variables)]
-			    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+			    fn encode(&self, version: ProtocolVersion, cursor: &mut impl BufMut) {
 					#[allow(unreachable_patterns)]
 					match self {
 						$(Self::$packet_name(packet) => {
 							VarInt($id).encode((), cursor);
-							packet.encode((), cursor);
+							packet.encode(version, cursor);
+						})*
+						_ => unreachable!(),
+					}
+				}
+
+				#[allow(unused_variables)]
+				fn validate(&self, version: ProtocolVersion) -> anyhow::Result<()> {
+					#[allow(unreachable_patterns)]
+					match self {
+						$(Self::$packet_name(packet) => {
+							anyhow::ensure!(
+								version >= $packet_name::INTRODUCED_IN,
+								"{} is not part of protocol version {}",
+								stringify!($packet_name),
+								version.0,
+							);
+							packet.validate(version)
 						})*
 						_ => unreachable!(),
 					}
 				}
 			}
 
-			impl SizedCodec<()> for Packet {
-				fn size(&self, _args: ()) -> usize {
+			impl Codec<()> for Packet {
+				fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+					Self::decode(ProtocolVersion::CURRENT, src, cursor)
+				}
+
+			    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+					self.encode(ProtocolVersion::CURRENT, cursor)
+				}
+
+				fn validate(&self, _args: ()) -> anyhow::Result<()> {
+					self.validate(ProtocolVersion::CURRENT)
+				}
+			}
+
+			impl Packet {
+				pub fn name(&self) -> &'static str {
+					#[allow(unreachable_patterns)]
+					match self {
+						$(Self::$packet_name(_) => stringify!($packet_name),)*
+						_ => unreachable!(),
+					}
+				}
+			}
+
+			impl SizedCodec<ProtocolVersion> for Packet {
+				#[allow(unused_variables)]
+				fn size(&self, version: ProtocolVersion) -> usize {
 					#[allow(unreachable_patterns)]
 					match self {
-						$(Self::$packet_name(packet) => VarInt($id).size(()) + packet.size(()),)*
+						$(Self::$packet_name(packet) => VarInt($id).size(()) + packet.size(version),)*
 						_ => unreachable!(),
 					}
 				}
 			}
 
-			impl FramedPacket for Packet {}
+			impl SizedCodec<()> for Packet {
+				fn size(&self, _args: ()) -> usize {
+					self.size(ProtocolVersion::CURRENT)
+				}
+			}
+
+			impl FramedPacket for Packet {
+				type Direction = $direction;
+			}
+
+			#[cfg(feature = "arbitrary")]
+			impl ArbitraryCodec<()> for Packet {
+				fn arbitrary(_args: (), u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+					let variants: &[fn(&mut Unstructured<'_>) -> arbitrary::Result<Packet>] = &[
+						$(|u| {
+							if ProtocolVersion::CURRENT < $packet_name::INTRODUCED_IN {
+								return Err(arbitrary::Error::IncorrectFormat);
+							}
+
+							Ok(<$packet_name as ArbitraryCodec<()>>::arbitrary((), u)?.into())
+						},)*
+					];
+
+					u.choose(variants)?(u)
+				}
+			}
+
+			#[cfg(feature = "arbitrary")]
+			impl<'a> arbitrary::Arbitrary<'a> for Packet {
+				fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+					<Self as ArbitraryCodec<()>>::arbitrary((), u)
+				}
+			}
 
 			$(
 				impl From<$packet_name> for Packet {
@@ -76,6 +158,11 @@ macro_rules! derive_protocol {
 					}
 				}
 
+				impl $packet_name {
+					/// The first protocol version in which this packet exists.
+					const INTRODUCED_IN: ProtocolVersion = derive_protocol!(@introduced_in $($since)?);
+				}
+
 				impl UnframedPacket for $packet_name {
 					type Framed = Packet;
 
@@ -88,7 +175,7 @@ macro_rules! derive_protocol {
 			codec_struct! {$(
 				$(#[$packet_attr])*
 				#[derive(Debug, Clone)]
-				pub struct $packet_name {
+				pub struct $packet_name($version: ProtocolVersion = ProtocolVersion::CURRENT) {
 					$(pub $field_name: $field_ty $(=> $field_config)?,)*
 				}
 			)*}
@@ -98,23 +185,58 @@ macro_rules! derive_protocol {
 
 // === Packet Protocol === //
 
+/// The name of the game version whose protocol is implemented below.
+pub const VERSION_NAME: &str = "1.20.1";
+
+/// The protocol version number of [`VERSION_NAME`], as sent in the handshake and status response.
+pub const PROTOCOL_VERSION: i32 = 763;
+
+/// A protocol version number. Packets can be encoded and decoded using the layout of a specific
+/// version by passing it as their codec argument, while the `()` argument uses [`Self::CURRENT`].
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ProtocolVersion(pub i32);
+
+impl ProtocolVersion {
+    pub const CURRENT: Self = Self(PROTOCOL_VERSION);
+
+    /// 1.19, where `LoginStart` started carrying the player's chat signing key.
+    pub const V1_19: Self = Self(759);
+
+    /// 1.19.3, where the player's UUID became an optional field of `LoginStart`.
+    pub const V1_19_3: Self = Self(761);
+
+    /// 1.20.2, where the player's UUID became a required field of `LoginStart`.
+    pub const V1_20_2: Self = Self(764);
+
+    /// 1.20.5, which added cookies and known packs.
+    pub const V1_20_5: Self = Self(766);
+}
+
+// Field configurations can refer to the version with which a packet is being encoded or decoded as
+// `version`.
+// Packets introduced in a later version are marked with `since` and are treated as unknown in, or
+// refuse to validate for, earlier versions.
 derive_protocol! {
+    |version|
+
     // === Handshake === //
 
-    pub mod sb_handshake {
+    pub mod cb_handshake(Clientbound) {}
+
+    pub mod sb_handshake(Serverbound) {
         struct Handshake(0) {
             version: VarInt,
             server_addr: NetString => 255,
             port: u16,
-            next_state: VarInt,
+            next_state: structs::HandshakeIntent,
         }
     }
 
     // === Status === //
 
-    pub mod cb_status {
+    pub mod cb_status(Clientbound) {
         struct StatusResponse(0) {
-            json_resp: NetString,
+            json_resp: JsonValue<structs::StatusResponse>,
         }
 
         struct PingResponse(1) {
@@ -122,7 +244,7 @@ derive_protocol! {
         }
     }
 
-    pub mod sb_status {
+    pub mod sb_status(Serverbound) {
         struct StatusRequest(0) {}
 
         struct PingRequest(1) {
@@ -132,7 +254,7 @@ derive_protocol! {
 
     // === Login === //
 
-    pub mod cb_login {
+    pub mod cb_login(Clientbound) {
         struct Disconnect(0) {
             reason: Chat,
         }
@@ -158,12 +280,20 @@ derive_protocol! {
             channel: Identifier,
             data: Bytes,
         }
+
+        struct CookieRequest(5, since ProtocolVersion::V1_20_5) {
+            key: Identifier,
+        }
     }
 
-    pub mod sb_login {
+    pub mod sb_login(Serverbound) {
+        /// The UUID's layout depends on the protocol version, so encoding or sizing this packet
+        /// panics in versions where [`validate`](Codec::validate) rejects it: those where
+        /// `LoginStart` carries a chat signing key, and those requiring a UUID when none is set.
+        /// Stream writes validate before encoding and report these as errors instead.
         struct LoginStart(0) {
             name: NetString => 16,
-            player_uuid: Option<Uuid>,
+            player_uuid: Option<Uuid> => structs::LoginUuidLayout::for_version(version),
         }
 
         struct EncryptionResponse(1) {
@@ -175,6 +305,161 @@ derive_protocol! {
             message_id: VarInt,
             data: Option<Bytes>,
         }
+
+        struct CookieResponse(4, since ProtocolVersion::V1_20_5) {
+            key: Identifier,
+            payload: Option<ByteArray>,
+        }
+    }
+
+    // === Play === //
+
+    pub mod cb_play(Clientbound) {
+        struct Disconnect(26) {
+            reason: Chat,
+        }
+
+        struct KeepAlive(35) {
+            id: i64,
+        }
+
+        struct Login(40) {
+            entity_id: i32,
+            is_hardcore: bool,
+            game_mode: u8,
+            previous_game_mode: i8,
+            dimension_names: Vec<Identifier> => || {},
+            registry_codec: RawNbt,
+            dimension_type: Identifier,
+            dimension_name: Identifier,
+            hashed_seed: i64,
+            max_players: VarInt,
+            view_distance: VarInt,
+            simulation_distance: VarInt,
+            reduced_debug_info: bool,
+            enable_respawn_screen: bool,
+            is_debug: bool,
+            is_flat: bool,
+            death_location: Option<structs::DeathLocation>,
+            portal_cooldown: VarInt,
+        }
+    }
+
+    pub mod sb_play(Serverbound) {
+        struct KeepAlive(18) {
+            id: i64,
+        }
+    }
+
+    // === Configuration === //
+
+    pub mod cb_config(Clientbound) {
+        struct KnownPacks(14, since ProtocolVersion::V1_20_5) {
+            packs: BoundedVec<structs::KnownPack, 64> => || {},
+        }
+    }
+
+    pub mod sb_config(Serverbound) {
+        struct KnownPacks(7, since ProtocolVersion::V1_20_5) {
+            packs: BoundedVec<structs::KnownPack, 64> => || {},
+        }
+    }
+}
+
+impl cb_play::Login {
+    /// Creates a login packet placing the player in survival mode in the specified dimension, which
+    /// is the only dimension the client is told about. The remaining fields take the values a
+    /// vanilla server would send with its default configuration.
+    pub fn new(
+        entity_id: i32,
+        registry_codec: RawNbt,
+        dimension_type: Identifier,
+        dimension_name: Identifier,
+    ) -> Self {
+        Self {
+            entity_id,
+            is_hardcore: false,
+            game_mode: 0,
+            previous_game_mode: -1,
+            dimension_names: vec![dimension_name.clone()],
+            registry_codec,
+            dimension_type,
+            dimension_name,
+            hashed_seed: 0,
+            max_players: VarInt(20),
+            view_distance: VarInt(10),
+            simulation_distance: VarInt(10),
+            reduced_debug_info: false,
+            enable_respawn_screen: true,
+            is_debug: false,
+            is_flat: false,
+            death_location: None,
+            portal_cooldown: VarInt(0),
+        }
+    }
+}
+
+// === Protocol States === //
+
+/// The protocol state a connection is in, as tracked at runtime. Each variant corresponds to a
+/// [`ProtocolState`] marker in [`state`].
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum PeerState {
+    Handshake,
+    Status,
+    Login,
+    Play,
+}
+
+pub trait ProtocolState {
+    /// The set of packets which a client can send to the server while in this state.
+    type Serverbound: FramedPacket<Direction = Serverbound> + Codec<ProtocolVersion>;
+
+    /// The set of packets which the server can send to a client while in this state.
+    type Clientbound: FramedPacket<Direction = Clientbound> + Codec<ProtocolVersion>;
+}
+
+pub mod state {
+    use super::*;
+
+    #[derive(Debug, Copy, Clone, Default)]
+    pub struct Handshake;
+
+    impl ProtocolState for Handshake {
+        type Serverbound = sb_handshake::Packet;
+        type Clientbound = cb_handshake::Packet;
+    }
+
+    #[derive(Debug, Copy, Clone, Default)]
+    pub struct Status;
+
+    impl ProtocolState for Status {
+        type Serverbound = sb_status::Packet;
+        type Clientbound = cb_status::Packet;
+    }
+
+    #[derive(Debug, Copy, Clone, Default)]
+    pub struct Login;
+
+    impl ProtocolState for Login {
+        type Serverbound = sb_login::Packet;
+        type Clientbound = cb_login::Packet;
+    }
+
+    #[derive(Debug, Copy, Clone, Default)]
+    pub struct Play;
+
+    impl ProtocolState for Play {
+        type Serverbound = sb_play::Packet;
+        type Clientbound = cb_play::Packet;
+    }
+
+    #[derive(Debug, Copy, Clone, Default)]
+    pub struct Configuration;
+
+    impl ProtocolState for Configuration {
+        type Serverbound = sb_config::Packet;
+        type Clientbound = cb_config::Packet;
     }
 }
 
@@ -183,12 +468,866 @@ derive_protocol! {
 pub mod structs {
     use super::*;
 
+    use crate::net::primitives::{RootChatComponent, SerializableJsonValue};
+    use anyhow::Context;
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use rsa::{
+        pkcs1v15::{Signature, VerifyingKey},
+        signature::Verifier,
+        RsaPublicKey,
+    };
+    use serde::{Deserialize, Serialize};
+    use sha1::Sha1;
+
+    proto_enum! {
+        /// The state a client asks to switch to at the end of its handshake.
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        pub enum HandshakeIntent {
+            Status = 1,
+            Login = 2,
+            /// Logging in after being transferred from another server, which was added in 1.20.5.
+            Transfer = 3,
+        }
+    }
+
+    /// How `LoginStart` encodes the player's UUID, which changed several times around 1.19.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    pub enum LoginUuidLayout {
+        /// The UUID is not sent.
+        Absent,
+        /// 1.19 through 1.19.2 send the player's chat signing key alongside an optional UUID. Our
+        /// `LoginStart` doesn't model the key so it can't be decoded or encoded in these versions.
+        Unsupported,
+        /// The UUID is prefixed by a boolean indicating whether it's present.
+        Optional,
+        /// The UUID is always present.
+        Required,
+    }
+
+    impl LoginUuidLayout {
+        pub fn for_version(version: ProtocolVersion) -> Self {
+            if version >= ProtocolVersion::V1_20_2 {
+                Self::Required
+            } else if version >= ProtocolVersion::V1_19_3 {
+                Self::Optional
+            } else if version >= ProtocolVersion::V1_19 {
+                Self::Unsupported
+            } else {
+                Self::Absent
+            }
+        }
+    }
+
+    const MISSING_UUID_ERR: &str = "The player's UUID is required in this protocol version.";
+
+    const UNSUPPORTED_LAYOUT_ERR: &str =
+        "`LoginStart` carries a chat signing key in this protocol version, which isn't supported.";
+
+    // `encode` and `size` assume `validate` has passed and panic on the values it rejects.
+    impl Codec<LoginUuidLayout> for Option<Uuid> {
+        fn decode(
+            layout: LoginUuidLayout,
+            src: &impl Snip,
+            cursor: &mut ByteCursor,
+        ) -> anyhow::Result<Self> {
+            match layout {
+                LoginUuidLayout::Absent => Ok(None),
+                LoginUuidLayout::Unsupported => anyhow::bail!(
+                    "{UNSUPPORTED_LAYOUT_ERR} (location: {})",
+                    cursor.format_location(),
+                ),
+                LoginUuidLayout::Optional => Self::decode((), src, cursor),
+                LoginUuidLayout::Required => Ok(Some(Uuid::decode((), src, cursor)?)),
+            }
+        }
+
+        fn encode(&self, layout: LoginUuidLayout, cursor: &mut impl BufMut) {
+            match layout {
+                LoginUuidLayout::Absent => {}
+                LoginUuidLayout::Unsupported => panic!("{UNSUPPORTED_LAYOUT_ERR}"),
+                LoginUuidLayout::Optional => self.encode((), cursor),
+                LoginUuidLayout::Required => self.expect(MISSING_UUID_ERR).encode((), cursor),
+            }
+        }
+
+        fn validate(&self, layout: LoginUuidLayout) -> anyhow::Result<()> {
+            anyhow::ensure!(
+                layout != LoginUuidLayout::Unsupported,
+                UNSUPPORTED_LAYOUT_ERR,
+            );
+            anyhow::ensure!(
+                self.is_some() || layout != LoginUuidLayout::Required,
+                MISSING_UUID_ERR,
+            );
+
+            Ok(())
+        }
+    }
+
+    impl SizedCodec<LoginUuidLayout> for Option<Uuid> {
+        fn size(&self, layout: LoginUuidLayout) -> usize {
+            match layout {
+                LoginUuidLayout::Absent => 0,
+                LoginUuidLayout::Unsupported => panic!("{UNSUPPORTED_LAYOUT_ERR}"),
+                LoginUuidLayout::Optional => self.size(()),
+                LoginUuidLayout::Required => self.expect(MISSING_UUID_ERR).size(()),
+            }
+        }
+    }
+
+    #[cfg(feature = "arbitrary")]
+    impl ArbitraryCodec<LoginUuidLayout> for Option<Uuid> {
+        fn arbitrary(layout: LoginUuidLayout, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+            match layout {
+                LoginUuidLayout::Absent => Ok(None),
+                LoginUuidLayout::Unsupported => Err(arbitrary::Error::IncorrectFormat),
+                LoginUuidLayout::Optional => Self::arbitrary((), u),
+                LoginUuidLayout::Required => Ok(Some(Uuid::arbitrary((), u)?)),
+            }
+        }
+    }
+
     codec_struct! {
         #[derive(Debug, Clone)]
         pub struct Property {
             name: NetString => 32767,
             value: NetString => 32767,
+            // `None` when the property is unsigned. This is not the same as `Some` with an empty
+            // signature, which the client would try (and fail) to verify.
             signature: Option<NetString> => 32767,
         }
+
+        #[derive(Debug, Clone)]
+        pub struct DeathLocation {
+            pub dimension_name: Identifier,
+            pub location: Position,
+        }
+
+        /// A data pack which either the client or the server has available, exchanged during the
+        /// configuration state so that the server can skip sending registry data the client
+        /// already knows.
+        #[derive(Debug, Clone)]
+        pub struct KnownPack {
+            pub namespace: Identifier,
+            pub id: NetString => 32767,
+            pub version: NetString => 32767,
+        }
+
+        /// A sound event, sent inline within an `IdOr<SoundEvent>` when it isn't in the client's
+        /// registry.
+        #[derive(Debug, Clone)]
+        pub struct SoundEvent {
+            pub name: Identifier,
+            // The fixed distance at which the sound can be heard. When absent, the range depends on
+            // the sound's volume.
+            pub fixed_range: Option<f32>,
+        }
+    }
+
+    impl Property {
+        /// The name of the property holding a player's skin and cape.
+        pub const TEXTURES: &'static str = "textures";
+
+        pub fn new(name: NetString, value: NetString, signature: Option<NetString>) -> Self {
+            Self {
+                name,
+                value,
+                signature,
+            }
+        }
+
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        pub fn value(&self) -> &str {
+            &self.value
+        }
+
+        pub fn signature(&self) -> Option<&str> {
+            self.signature.as_deref()
+        }
+
+        /// Decodes the value of a [`Property::TEXTURES`] property, which is a base64-encoded JSON
+        /// document.
+        pub fn textures(&self) -> anyhow::Result<TexturesPayload> {
+            anyhow::ensure!(
+                self.name() == Self::TEXTURES,
+                "Expected a {:?} property but found a {:?} property.",
+                Self::TEXTURES,
+                self.name(),
+            );
+
+            let json = BASE64_STANDARD
+                .decode(self.value())
+                .context("textures property is not valid base64")?;
+
+            serde_json::from_slice(&json).context("textures property is not valid JSON")
+        }
+
+        /// The URL of the player's skin or `None` if they use the default skin.
+        pub fn texture_url(&self) -> anyhow::Result<Option<String>> {
+            Ok(self.textures()?.textures.skin.map(|skin| skin.url))
+        }
+
+        /// Checks the property's signature, which is a base64-encoded SHA1withRSA signature of its
+        /// value. `key` should be the session server's public key, which Mojang publishes at
+        /// `https://api.minecraftservices.com/publickeys`. Fails if the property is unsigned.
+        pub fn verify_signature(&self, key: &RsaPublicKey) -> anyhow::Result<()> {
+            let Some(signature) = self.signature() else {
+                anyhow::bail!("Property {:?} is unsigned.", self.name());
+            };
+
+            let signature = BASE64_STANDARD
+                .decode(signature)
+                .context("property signature is not valid base64")?;
+
+            VerifyingKey::<Sha1>::new(key.clone())
+                .verify(
+                    self.value().as_bytes(),
+                    &Signature::try_from(signature.as_slice())?,
+                )
+                .with_context(|| format!("property {:?} has an invalid signature", self.name()))
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TexturesPayload {
+        pub timestamp: i64,
+        pub profile_id: String,
+        pub profile_name: String,
+        pub textures: Textures,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct Textures {
+        #[serde(rename = "SKIN")]
+        pub skin: Option<Texture>,
+
+        #[serde(rename = "CAPE")]
+        pub cape: Option<Texture>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct Texture {
+        pub url: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+    #[serde(rename_all = "camelCase")]
+    pub struct StatusResponse {
+        pub version: StatusVersion,
+        pub players: StatusPlayers,
+        pub description: RootChatComponent,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub favicon: Option<String>,
+
+        #[serde(default)]
+        pub enforces_secure_chat: bool,
+
+        #[serde(default)]
+        pub previews_chat: bool,
+    }
+
+    impl SerializableJsonValue for StatusResponse {
+        const MAX_STR_LEN: u32 = 32767;
+    }
+
+    impl StatusResponse {
+        /// Creates a response advertising [`VERSION_NAME`] with no players online, no player
+        /// limit, and no favicon.
+        pub fn new(description: RootChatComponent) -> Self {
+            Self {
+                version: StatusVersion {
+                    name: VERSION_NAME.to_string(),
+                    protocol: PROTOCOL_VERSION,
+                },
+                players: StatusPlayers {
+                    max: 0,
+                    online: 0,
+                    sample: Vec::new(),
+                },
+                description,
+                favicon: None,
+                enforces_secure_chat: false,
+                previews_chat: false,
+            }
+        }
+
+        pub fn with_players(mut self, max: i32, online: i32) -> Self {
+            self.players.max = max;
+            self.players.online = online;
+            self
+        }
+
+        pub fn with_sample(mut self, name: impl Into<String>, id: Uuid) -> Self {
+            self.players.sample.push(StatusPlayerSample {
+                name: name.into(),
+                id: id.to_string(),
+            });
+            self
+        }
+
+        /// Sets the favicon from the base64 encoding of a 64x64 PNG image.
+        pub fn with_favicon(mut self, png_base64: &str) -> Self {
+            self.favicon = Some(format!("data:image/png;base64,{png_base64}"));
+            self
+        }
+
+        pub fn with_secure_chat(mut self, enforces_secure_chat: bool) -> Self {
+            self.enforces_secure_chat = enforces_secure_chat;
+            self
+        }
+
+        pub fn with_chat_previews(mut self, previews_chat: bool) -> Self {
+            self.previews_chat = previews_chat;
+            self
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+    pub struct StatusVersion {
+        pub name: String,
+        pub protocol: i32,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+    pub struct StatusPlayers {
+        pub max: i32,
+        pub online: i32,
+
+        #[serde(default)]
+        pub sample: Vec<StatusPlayerSample>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+    pub struct StatusPlayerSample {
+        pub name: String,
+        pub id: String,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::net::{
+        primitives::{ChatComponent, IdOr},
+        transport::PreFramedPacket,
+    };
+
+    /// Encodes `value`, checking that it decodes and re-encodes to the same bytes.
+    fn round_trip<T: SizedCodec<()>>(value: &T) -> Bytes {
+        let mut buf = Vec::new();
+        value.encode((), &mut buf);
+        let encoded = Bytes::from(buf);
+        assert_eq!(encoded.len(), value.size(()));
+
+        let mut buf = Vec::new();
+        T::decode_bytes((), &encoded).unwrap().encode((), &mut buf);
+        assert_eq!(encoded, buf);
+
+        encoded
+    }
+
+    #[test]
+    fn status_response_serializes_to_status_json() {
+        let status = structs::StatusResponse::new(ChatComponent::text("A Raft server").into())
+            .with_players(20, 1)
+            .with_sample("Notch", Uuid(0x069a79f4_44e9_4726_a5be_fca90e38aaf5))
+            .with_favicon("AAAA")
+            .with_secure_chat(true);
+
+        assert_eq!(
+            serde_json::to_value(&status).unwrap(),
+            serde_json::json!({
+                "version": { "name": "1.20.1", "protocol": 763 },
+                "players": {
+                    "max": 20,
+                    "online": 1,
+                    "sample": [{ "name": "Notch", "id": "069a79f4-44e9-4726-a5be-fca90e38aaf5" }],
+                },
+                "description": { "text": "A Raft server" },
+                "favicon": "data:image/png;base64,AAAA",
+                "enforcesSecureChat": true,
+                "previewsChat": false,
+            }),
+        );
+
+        // The packet field carries the typed value and round-trips through the wire encoding.
+        let packet = cb_status::StatusResponse {
+            json_resp: JsonValue(status),
+        };
+        let mut buf = Vec::new();
+        packet.encode(ProtocolVersion::CURRENT, &mut buf);
+        let decoded =
+            cb_status::StatusResponse::decode_bytes(ProtocolVersion::CURRENT, &Bytes::from(buf))
+                .unwrap();
+        assert_eq!(decoded.json_resp.0.players.sample[0].name, "Notch");
+        assert!(decoded.json_resp.0.enforces_secure_chat);
+    }
+
+    #[test]
+    fn over_long_username_fails_validation() {
+        let packet = |username: &str| cb_login::LoginSuccess {
+            uuid: Uuid(0),
+            username: NetString::from_string(username.to_string()),
+            properties: Vec::new(),
+        };
+
+        assert!(PreFramedPacket::new(packet(&"a".repeat(16))).is_ok());
+
+        let err = PreFramedPacket::new(packet(&"a".repeat(17))).unwrap_err();
+        assert!(err.to_string().contains("at most 16"), "{err:#}");
+    }
+
+    #[test]
+    fn known_packs_round_trip() {
+        let pack = |id: &str| structs::KnownPack {
+            namespace: Identifier(NetString::from_string("minecraft".to_string())),
+            id: NetString::from_string(id.to_string()),
+            version: NetString::from_string("1.20.5".to_string()),
+        };
+
+        let packet = sb_config::Packet::from(sb_config::KnownPacks {
+            packs: BoundedVec(vec![pack("core"), pack("update_1_21")]),
+        });
+        packet.validate(ProtocolVersion::V1_20_5).unwrap();
+        let mut buf = Vec::new();
+        packet.encode(ProtocolVersion::V1_20_5, &mut buf);
+        assert_eq!(buf.len(), packet.size(ProtocolVersion::V1_20_5));
+        let encoded = Bytes::from(buf);
+        let sb_config::Packet::KnownPacks(decoded) =
+            sb_config::Packet::decode_exact(ProtocolVersion::V1_20_5, &encoded).unwrap();
+
+        let ids = decoded
+            .packs
+            .0
+            .iter()
+            .map(|pack| &*pack.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["core", "update_1_21"]);
+        assert_eq!(&*decoded.packs.0[1].namespace.0, "minecraft");
+        assert_eq!(&*decoded.packs.0[1].version, "1.20.5");
+
+        // Known packs don't exist before 1.20.5.
+        assert!(packet.validate(ProtocolVersion::CURRENT).is_err());
+        let err = sb_config::Packet::decode_exact(ProtocolVersion::CURRENT, &encoded).unwrap_err();
+        assert!(
+            err.to_string().contains("Unknown packet with ID 7"),
+            "{err:#}"
+        );
+
+        // The pack list is capped.
+        let packet = cb_config::KnownPacks {
+            packs: BoundedVec(vec![pack("core"); 65]),
+        };
+        assert!(packet.validate(()).is_err());
+    }
+
+    #[test]
+    fn property_signature_distinguishes_empty_from_absent() {
+        let property = |signature: Option<&str>| {
+            structs::Property::new(
+                NetString::from_string("textures".to_string()),
+                NetString::from_string("e30=".to_string()),
+                signature.map(|signature| NetString::from_string(signature.to_string())),
+            )
+        };
+
+        let absent = round_trip(&property(None));
+        let empty = round_trip(&property(Some("")));
+        assert_eq!(empty.len(), absent.len() + 1);
+        assert_eq!(&absent[absent.len() - 1..], b"\x00");
+        assert_eq!(&empty[empty.len() - 2..], b"\x01\x00");
+
+        let decoded = structs::Property::decode_bytes((), &absent).unwrap();
+        assert_eq!(decoded.signature(), None);
+
+        let decoded = structs::Property::decode_bytes((), &empty).unwrap();
+        assert_eq!(decoded.signature(), Some(""));
+    }
+
+    // The textures property of a player wearing a skin, as sent by the session server.
+    const TEXTURES_VALUE: &str =
+        "eyJ0aW1lc3RhbXAiOjE2OTcwNDAwMDAwMDAsInByb2ZpbGVJZCI6IjA2OWE3OWY0NDRlOTQ3\
+        MjZhNWJlZmNhOTBlMzhhYWY1IiwicHJvZmlsZU5hbWUiOiJOb3RjaCIsInRleHR1cmVzIjp7IlNLSU4iOnsidXJs\
+        IjoiaHR0cDovL3RleHR1cmVzLm1pbmVjcmFmdC5uZXQvdGV4dHVyZS8yOTIwMDlhNDkyNWI1OGYwMmM3N2RhZGMz\
+        ZWNlZjA3ZWE0Yzc0NzJmNjRlMGZkYzMyY2U1NTIyNDg5MzYyNjgwIn19fQ==";
+
+    fn textures_property(signature: Option<String>) -> structs::Property {
+        structs::Property::new(
+            NetString::from_static_str(structs::Property::TEXTURES),
+            NetString::from_static_str(TEXTURES_VALUE),
+            signature.map(NetString::from_string),
+        )
+    }
+
+    #[test]
+    fn textures_property_yields_the_skin_url() {
+        let property = textures_property(None);
+
+        let textures = property.textures().unwrap();
+        assert_eq!(textures.profile_name, "Notch");
+        assert_eq!(textures.profile_id, "069a79f444e94726a5befca90e38aaf5");
+        assert!(textures.textures.cape.is_none());
+        assert_eq!(
+            property.texture_url().unwrap().as_deref(),
+            Some(
+                "http://textures.minecraft.net/texture/\
+                 292009a4925b58f02c77dadc3ecef07ea4c7472f64e0fdc32ce5522489362680"
+            ),
+        );
+
+        // Other properties aren't mistaken for textures.
+        let other = structs::Property::new(
+            NetString::from_static_str("other"),
+            NetString::from_static_str(TEXTURES_VALUE),
+            None,
+        );
+        assert!(other.texture_url().is_err());
+    }
+
+    #[test]
+    fn property_signatures_are_verified() {
+        use base64::{prelude::BASE64_STANDARD, Engine};
+        use rand::rngs::OsRng;
+        use rsa::{
+            pkcs1v15::SigningKey,
+            signature::{SignatureEncoding, Signer},
+            RsaPrivateKey, RsaPublicKey,
+        };
+        use sha1::Sha1;
+
+        // A small key keeps the test fast. The session server's key is 4096 bits.
+        let key = RsaPrivateKey::new(&mut OsRng, 512).unwrap();
+        let signature = SigningKey::<Sha1>::new(key.clone()).sign(TEXTURES_VALUE.as_bytes());
+        let signature = BASE64_STANDARD.encode(signature.to_bytes());
+        let public_key = RsaPublicKey::from(&key);
+
+        textures_property(Some(signature))
+            .verify_signature(&public_key)
+            .unwrap();
+
+        assert!(textures_property(None)
+            .verify_signature(&public_key)
+            .is_err());
+
+        let forged = BASE64_STANDARD.encode([0; 64]);
+        assert!(textures_property(Some(forged))
+            .verify_signature(&public_key)
+            .is_err());
+    }
+
+    #[test]
+    fn login_matches_vanilla_layout() {
+        let overworld = || Identifier(NetString::from_static_str("minecraft:overworld"));
+        let empty_compound = RawNbt::from_bytes_unchecked(Bytes::from_static(b"\x0a\x00\x00\x00"));
+        let packet = cb_play::Packet::from(cb_play::Login::new(
+            1,
+            empty_compound,
+            overworld(),
+            overworld(),
+        ));
+
+        // The packet as a vanilla 1.20.1 server lays it out.
+        let mut expected = vec![
+            0x28, // Packet id
+            0x00, 0x00, 0x00, 0x01, // Entity id
+            0x00, // Is hardcore
+            0x00, // Game mode
+            0xFF, // Previous game mode
+            0x01, // Dimension count
+        ];
+        let identifier = b"\x13minecraft:overworld";
+        expected.extend_from_slice(identifier); // Dimension names
+        expected.extend_from_slice(b"\x0a\x00\x00\x00"); // Registry codec
+        expected.extend_from_slice(identifier); // Dimension type
+        expected.extend_from_slice(identifier); // Dimension name
+        expected.extend_from_slice(&[
+            0, 0, 0, 0, 0, 0, 0, 0,    // Hashed seed
+            20,   // Max players
+            10,   // View distance
+            10,   // Simulation distance
+            0x00, // Reduced debug info
+            0x01, // Enable respawn screen
+            0x00, // Is debug
+            0x00, // Is flat
+            0x00, // Has death location
+            0x00, // Portal cooldown
+        ]);
+
+        assert_eq!(round_trip(&packet), expected);
+    }
+
+    #[test]
+    fn keep_alive_packets_round_trip() {
+        let id = 1_700_000_000_123;
+
+        let encoded = round_trip(&cb_play::Packet::from(cb_play::KeepAlive { id }));
+        assert_eq!(encoded[0], 35);
+        assert_eq!(&encoded[1..], id.to_be_bytes());
+        let cb_play::Packet::KeepAlive(decoded) =
+            cb_play::Packet::decode_bytes((), &encoded).unwrap()
+        else {
+            panic!("decoded the wrong packet");
+        };
+        assert_eq!(decoded.id, id);
+
+        let encoded = round_trip(&sb_play::Packet::from(sb_play::KeepAlive { id: -1 }));
+        assert_eq!(&encoded[..], b"\x12\xff\xff\xff\xff\xff\xff\xff\xff");
+        let sb_play::Packet::KeepAlive(decoded) =
+            sb_play::Packet::decode_bytes((), &encoded).unwrap();
+        assert_eq!(decoded.id, -1);
+    }
+
+    #[test]
+    fn status_response_advertises_the_current_protocol() {
+        let status =
+            structs::StatusResponse::new(ChatComponent::text("").into()).with_players(100, 0);
+        let json = serde_json::to_value(&status).unwrap();
+
+        assert_eq!(json["version"]["protocol"], PROTOCOL_VERSION);
+        assert_eq!(json["version"]["name"], VERSION_NAME);
+        assert_eq!(json["players"]["max"], 100);
+        assert_eq!(json["players"]["online"], 0);
+        assert_eq!(json["players"]["sample"], serde_json::json!([]));
+        assert!(json.get("favicon").is_none());
+    }
+
+    #[test]
+    fn sound_events_round_trip_inline_and_by_id() {
+        let sound = |fixed_range| {
+            IdOr::Inline(structs::SoundEvent {
+                name: Identifier(NetString::from_static_str("raft:ambient.hum")),
+                fixed_range,
+            })
+        };
+
+        let mut expected = vec![0, 16];
+        expected.extend_from_slice(b"raft:ambient.hum");
+        expected.push(0);
+        assert_eq!(round_trip(&sound(None)), expected);
+
+        expected.pop();
+        expected.push(1);
+        expected.extend_from_slice(&16.0f32.to_be_bytes());
+        let encoded = round_trip(&sound(Some(16.0)));
+        assert_eq!(encoded, expected);
+
+        let IdOr::Inline(decoded) =
+            IdOr::<structs::SoundEvent>::decode_bytes((), &encoded).unwrap()
+        else {
+            panic!("expected an inline sound event");
+        };
+        assert_eq!(decoded.name.path(), "ambient.hum");
+        assert_eq!(decoded.fixed_range, Some(16.0));
+
+        // Registry references carry no payload after their id.
+        let encoded = round_trip(&IdOr::<structs::SoundEvent>::Id(41));
+        assert_eq!(encoded[..], [42]);
+        assert!(matches!(
+            IdOr::<structs::SoundEvent>::decode_exact((), &encoded).unwrap(),
+            IdOr::Id(41)
+        ));
+    }
+
+    fn handshake_packet(intent: u8) -> Bytes {
+        let mut packet = vec![0x00, 0xFB, 0x05, 9];
+        packet.extend_from_slice(b"localhost");
+        packet.extend_from_slice(&25565u16.to_be_bytes());
+        packet.push(intent);
+        Bytes::from(packet)
+    }
+
+    #[test]
+    fn handshakes_reject_unknown_intents_while_decoding() {
+        for (id, intent) in [
+            (1, structs::HandshakeIntent::Status),
+            (2, structs::HandshakeIntent::Login),
+            (3, structs::HandshakeIntent::Transfer),
+        ] {
+            let sb_handshake::Packet::Handshake(packet) =
+                sb_handshake::Packet::decode_exact((), &handshake_packet(id)).unwrap();
+            assert_eq!(packet.next_state, intent);
+            assert_eq!(packet.port, 25565);
+        }
+
+        let err = sb_handshake::Packet::decode_exact((), &handshake_packet(5)).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("HandshakeIntent id 5"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn handshakes_with_trailing_bytes_are_rejected() {
+        let mut packet = handshake_packet(1).to_vec();
+        packet.push(0x2A);
+        let packet = Bytes::from(packet);
+
+        // The handshake itself is well-formed...
+        assert!(sb_handshake::Packet::decode_bytes((), &packet).is_ok());
+
+        // ...but the frame holds more than just the handshake.
+        let err = sb_handshake::Packet::decode_exact((), &packet).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Found 1 trailing byte(s) after decoding"),
+            "{err:#}"
+        );
+        assert!(
+            err.to_string()
+                .contains(&format!("location: {} byte(s)", packet.len() - 1)),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn login_start_uuid_layout_follows_the_protocol_version() {
+        const UUID: u128 = 0x069a79f4_44e9_4726_a5be_fca90e38aaf5;
+
+        let login_start = |uuid_prefix: &[u8]| {
+            let mut packet = vec![0x00, 5];
+            packet.extend_from_slice(b"Notch");
+            packet.extend_from_slice(uuid_prefix);
+            packet.extend_from_slice(&UUID.to_be_bytes());
+            Bytes::from(packet)
+        };
+        let decode = |version: i32, bytes: &Bytes| {
+            sb_login::Packet::decode_exact(ProtocolVersion(version), bytes).map(|packet| {
+                let sb_login::Packet::LoginStart(packet) = packet else {
+                    panic!("expected a login start");
+                };
+                packet
+            })
+        };
+
+        // 1.20.1 prefixes the UUID with a presence flag whereas 1.20.2 always sends it.
+        let optional = login_start(&[0x01]);
+        let required = login_start(&[]);
+
+        let packet = decode(763, &optional).unwrap();
+        assert_eq!(&*packet.name, "Notch");
+        assert_eq!(packet.player_uuid.map(|uuid| uuid.0), Some(UUID));
+        assert!(decode(764, &optional).is_err());
+
+        let packet = decode(764, &required).unwrap();
+        assert_eq!(packet.player_uuid.map(|uuid| uuid.0), Some(UUID));
+        assert!(decode(763, &required).is_err());
+
+        let mut buf = Vec::new();
+        sb_login::Packet::LoginStart(packet).encode(ProtocolVersion(763), &mut buf);
+        assert_eq!(buf, optional);
+
+        // 1.19 through 1.19.2 send a chat signing key we don't model, so their packets are
+        // rejected rather than misread.
+        for version in [759, 760] {
+            let err = decode(version, &login_start(&[0x01])).unwrap_err();
+            assert!(err.to_string().contains("chat signing key"), "{err:#}");
+        }
+
+        // Older versions send only the name.
+        let packet = decode(758, &Bytes::from_static(b"\x00\x05Notch")).unwrap();
+        assert!(packet.player_uuid.is_none());
+    }
+
+    #[test]
+    fn cookie_packets_only_exist_since_1_20_5() {
+        let key = || Identifier(NetString::from_static_str("raft:session"));
+
+        let request = cb_login::Packet::from(cb_login::CookieRequest { key: key() });
+        let mut buf = Vec::new();
+        request.encode(ProtocolVersion::V1_20_5, &mut buf);
+        assert_eq!(buf.len(), request.size(ProtocolVersion::V1_20_5));
+        assert_eq!(buf[0], 0x05);
+
+        let bytes = Bytes::from(buf);
+        let cb_login::Packet::CookieRequest(decoded) =
+            cb_login::Packet::decode_exact(ProtocolVersion::V1_20_5, &bytes).unwrap()
+        else {
+            panic!("expected a cookie request");
+        };
+        assert_eq!(&*decoded.key.0, "raft:session");
+
+        for payload in [None, Some(vec![1, 2, 3])] {
+            let response = sb_login::Packet::from(sb_login::CookieResponse {
+                key: key(),
+                payload: payload
+                    .clone()
+                    .map(|payload| ByteArray::new(Bytes::from(payload))),
+            });
+            response.validate(ProtocolVersion::V1_20_5).unwrap();
+
+            let mut buf = Vec::new();
+            response.encode(ProtocolVersion::V1_20_5, &mut buf);
+            assert_eq!(buf.len(), response.size(ProtocolVersion::V1_20_5));
+
+            let bytes = Bytes::from(buf);
+            let sb_login::Packet::CookieResponse(decoded) =
+                sb_login::Packet::decode_exact(ProtocolVersion::V1_20_5, &bytes).unwrap()
+            else {
+                panic!("expected a cookie response");
+            };
+            assert_eq!(&*decoded.key.0, "raft:session");
+            assert_eq!(
+                decoded.payload.map(|payload| payload.bytes().to_vec()),
+                payload
+            );
+
+            // Older clients don't know about cookies so we neither send nor accept them.
+            assert!(response.validate(ProtocolVersion::CURRENT).is_err());
+            let err = sb_login::Packet::decode_exact(ProtocolVersion::CURRENT, &bytes).unwrap_err();
+            assert!(
+                err.to_string().contains("Unknown packet with ID 4"),
+                "{err:#}"
+            );
+        }
+
+        assert!(request.validate(ProtocolVersion::CURRENT).is_err());
+        assert!(cb_login::Packet::decode_exact(ProtocolVersion::CURRENT, &bytes).is_err());
+    }
+
+    #[test]
+    fn login_start_validation_catches_each_panicking_layout() {
+        let login_start = |player_uuid| {
+            sb_login::Packet::from(sb_login::LoginStart {
+                name: NetString::from_static_str("Notch"),
+                player_uuid,
+            })
+        };
+
+        for (version, player_uuid, message) in [
+            (ProtocolVersion::V1_19, Some(Uuid(1)), "chat signing key"),
+            (ProtocolVersion::V1_20_2, None, "UUID is required"),
+        ] {
+            let packet = login_start(player_uuid);
+
+            let err = packet.validate(version).unwrap_err();
+            assert!(err.to_string().contains(message), "{err:#}");
+
+            // Skipping validation is a caller bug, which `encode` and `size` panic on.
+            let encode = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                packet.encode(version, &mut Vec::new())
+            }));
+            assert!(encode.is_err(), "encoding in {version:?} should panic");
+
+            let size =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| packet.size(version)));
+            assert!(size.is_err(), "sizing in {version:?} should panic");
+        }
+
+        // Both are fine once the packet matches the version's layout.
+        let packet = login_start(Some(Uuid(1)));
+        packet.validate(ProtocolVersion::V1_20_2).unwrap();
+        let mut buf = Vec::new();
+        packet.encode(ProtocolVersion::V1_20_2, &mut buf);
+        assert_eq!(buf.len(), packet.size(ProtocolVersion::V1_20_2));
     }
 }
@@ -0,0 +1,476 @@
+//! Validation of recorded packet streams. Replaying a recording through our decoders produces a
+//! per-frame report which can be diffed between runs, turning a recording into a regression test
+//! asset.
+
+use std::{any::type_name, fmt};
+
+use bytes::Bytes;
+
+use crate::util::proto::byte_stream::ByteCursor;
+
+use super::{
+    primitives::Codec,
+    protocol::{
+        cb_handshake, cb_login, cb_play, cb_status, sb_handshake, sb_login, sb_play, sb_status,
+        structs::HandshakeIntent, PeerState,
+    },
+    transport::{split_capture, Frame},
+};
+
+// === Frames === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum FrameDirection {
+    Clientbound,
+    Serverbound,
+}
+
+/// A single unframed packet body (i.e. its id followed by its fields) as it was sent over the
+/// connection.
+#[derive(Debug, Clone)]
+pub struct RecordedFrame {
+    pub direction: FrameDirection,
+    pub body: Bytes,
+}
+
+impl RecordedFrame {
+    /// Reads the frames of a raw capture of one direction of a connection. See [`split_capture`]
+    /// for the captures this supports. Since the directions are captured separately, their frames
+    /// must be interleaved in the order they were sent before being validated.
+    pub fn from_capture(
+        direction: FrameDirection,
+        capture: &[u8],
+    ) -> Result<Vec<Self>, ReplayError> {
+        split_capture(capture)
+            .map_err(|error| ReplayError::Capture { error })?
+            .into_iter()
+            .map(|frame| match frame {
+                Frame::Packet(body) => Ok(Self { direction, body }),
+                Frame::LegacyPing(_) => Err(ReplayError::LegacyPing),
+            })
+            .collect()
+    }
+}
+
+// === Errors === //
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Failed to split the capture into frames: {error:#}")]
+    Capture { error: anyhow::Error },
+
+    #[error("Legacy pings can't be replayed since they aren't packets.")]
+    LegacyPing,
+
+    #[error("Failed to decode a {direction:?} packet in the {state:?} state: {error:#}")]
+    Decode {
+        state: PeerState,
+        direction: FrameDirection,
+        error: anyhow::Error,
+    },
+
+    #[error(
+        "{packet} left {count} trailing byte(s) unconsumed (location: {location} byte(s) from the \
+         packet frame start)."
+    )]
+    TrailingBytes {
+        packet: &'static str,
+        count: usize,
+        location: usize,
+    },
+}
+
+// === Reports === //
+
+#[derive(Debug)]
+pub enum FrameOutcome {
+    Decoded { packet: &'static str },
+    Failed { error: ReplayError },
+    Skipped { reason: &'static str },
+}
+
+#[derive(Debug)]
+pub struct FrameReport {
+    pub index: usize,
+    pub direction: FrameDirection,
+    pub state: PeerState,
+    pub outcome: FrameOutcome,
+}
+
+impl fmt::Display for FrameReport {
+    /// Formats the report as a single tab-separated line of the form
+    /// `<index> <direction> <state> <ok|err|skip> <detail>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{:?}\t{:?}\t",
+            self.index, self.direction, self.state
+        )?;
+
+        match &self.outcome {
+            FrameOutcome::Decoded { packet } => write!(f, "ok\t{packet}"),
+            // Errors may span several lines when they have context attached.
+            FrameOutcome::Failed { error } => {
+                write!(f, "err\t{}", error.to_string().replace('\n', " "))
+            }
+            FrameOutcome::Skipped { reason } => write!(f, "skip\t{reason}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub frames: Vec<FrameReport>,
+}
+
+impl ReplayReport {
+    pub fn failure_count(&self) -> usize {
+        self.frames
+            .iter()
+            .filter(|frame| matches!(frame.outcome, FrameOutcome::Failed { .. }))
+            .count()
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for frame in &self.frames {
+            writeln!(f, "{frame}")?;
+        }
+
+        Ok(())
+    }
+}
+
+// === Validation === //
+
+/// Decodes every frame in sequence, following the state transitions they imply. Decoding continues
+/// past failed frames so that a single bad packet doesn't hide problems with the rest of the
+/// recording, although a frame which fails to decode can't drive a state transition.
+pub fn validate_recording<'a>(frames: impl IntoIterator<Item = &'a RecordedFrame>) -> ReplayReport {
+    let mut state = PeerState::Handshake;
+    let mut report = ReplayReport::default();
+
+    for (index, frame) in frames.into_iter().enumerate() {
+        // Frames are reported in the state they were decoded in, not the state they transitioned
+        // to.
+        let decoded_in = state;
+        let outcome = match validate_frame(&mut state, frame) {
+            Ok(outcome) => outcome,
+            Err(error) => FrameOutcome::Failed { error },
+        };
+
+        report.frames.push(FrameReport {
+            index,
+            direction: frame.direction,
+            state: decoded_in,
+            outcome,
+        });
+    }
+
+    report
+}
+
+fn validate_frame(
+    state: &mut PeerState,
+    frame: &RecordedFrame,
+) -> Result<FrameOutcome, ReplayError> {
+    use FrameDirection::*;
+
+    let packet = match (*state, frame.direction) {
+        (PeerState::Handshake, Clientbound) => {
+            decode_frame::<cb_handshake::Packet>(*state, frame)?.name()
+        }
+        (PeerState::Handshake, Serverbound) => {
+            let packet = decode_frame::<sb_handshake::Packet>(*state, frame)?;
+            let sb_handshake::Handshake(handshake) = &packet;
+
+            *state = match handshake.next_state {
+                HandshakeIntent::Status => PeerState::Status,
+                HandshakeIntent::Login | HandshakeIntent::Transfer => PeerState::Login,
+            };
+
+            packet.name()
+        }
+        (PeerState::Status, Clientbound) => {
+            decode_frame::<cb_status::Packet>(*state, frame)?.name()
+        }
+        (PeerState::Status, Serverbound) => {
+            decode_frame::<sb_status::Packet>(*state, frame)?.name()
+        }
+        (PeerState::Login, Clientbound) => {
+            let packet = decode_frame::<cb_login::Packet>(*state, frame)?;
+
+            if let cb_login::LoginSuccess(_) = &packet {
+                *state = PeerState::Play;
+            }
+
+            packet.name()
+        }
+        (PeerState::Login, Serverbound) => decode_frame::<sb_login::Packet>(*state, frame)?.name(),
+        (PeerState::Play, Clientbound) => decode_frame::<cb_play::Packet>(*state, frame)?.name(),
+        (PeerState::Play, Serverbound) => decode_frame::<sb_play::Packet>(*state, frame)?.name(),
+    };
+
+    Ok(FrameOutcome::Decoded { packet })
+}
+
+fn decode_frame<P: Codec<()>>(state: PeerState, frame: &RecordedFrame) -> Result<P, ReplayError> {
+    let mut cursor = ByteCursor::new(&frame.body);
+    let packet = P::decode((), &frame.body, &mut cursor).map_err(|error| ReplayError::Decode {
+        state,
+        direction: frame.direction,
+        error,
+    })?;
+
+    if !cursor.is_empty() {
+        return Err(ReplayError::TrailingBytes {
+            packet: type_name::<P>(),
+            count: cursor.len(),
+            location: cursor.pos(),
+        });
+    }
+
+    Ok(packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{duplex, AsyncWriteExt, DuplexStream};
+
+    use crate::net::{
+        primitives::{NetString, VarInt},
+        protocol::{state, PROTOCOL_VERSION},
+        transport::{Client, PeerRole, RawPeerStream, Server, HARD_MAX_PACKET_LEN_INCL},
+    };
+
+    // These fixtures are synthesized from the 1.20.1 packet definitions rather than recorded from a
+    // vanilla client or server. They exercise splitting and replaying raw byte streams, but only
+    // check that we agree with our own reading of the protocol, not that we interoperate with
+    // vanilla.
+
+    // A client pinging a server for its status and the server's replies.
+    const STATUS_SERVERBOUND: &[u8] = include_bytes!("fixtures/status_serverbound.bin");
+    const STATUS_CLIENTBOUND: &[u8] = include_bytes!("fixtures/status_clientbound.bin");
+
+    // A client's handshake before logging in.
+    const LOGIN_HANDSHAKE_SERVERBOUND: &[u8] =
+        include_bytes!("fixtures/login_handshake_serverbound.bin");
+
+    /// Feeds a capture to a stream over an in-memory pipe, closing the pipe once the whole capture
+    /// has been written. The pipe's buffer is deliberately tiny so that frames arrive in pieces.
+    fn replay<R: PeerRole>(capture: &'static [u8]) -> RawPeerStream<R, DuplexStream> {
+        let (mut recorder, peer) = duplex(7);
+
+        tokio::spawn(async move {
+            recorder.write_all(capture).await.unwrap();
+            recorder.shutdown().await.unwrap();
+        });
+
+        RawPeerStream::new(peer, HARD_MAX_PACKET_LEN_INCL)
+    }
+
+    fn frame(direction: FrameDirection, packet: impl Codec<()>) -> RecordedFrame {
+        let mut body = Vec::new();
+        packet.encode((), &mut body);
+
+        RecordedFrame {
+            direction,
+            body: Bytes::from(body),
+        }
+    }
+
+    #[test]
+    fn validation_reports_every_frame() {
+        use FrameDirection::*;
+
+        let handshake = sb_handshake::Packet::from(sb_handshake::Handshake {
+            version: VarInt(PROTOCOL_VERSION),
+            server_addr: NetString::from_static_str("localhost"),
+            port: 25565,
+            next_state: HandshakeIntent::Status,
+        });
+
+        let frames = [
+            frame(Serverbound, handshake),
+            frame(
+                Serverbound,
+                sb_status::Packet::from(sb_status::StatusRequest {}),
+            ),
+            // A status request followed by a stray byte.
+            RecordedFrame {
+                direction: Serverbound,
+                body: Bytes::from_static(b"\x00\x2a"),
+            },
+            frame(
+                Clientbound,
+                cb_status::Packet::from(cb_status::PingResponse { payload: 1 }),
+            ),
+            RecordedFrame {
+                direction: Clientbound,
+                body: Bytes::from_static(b"\x7f"),
+            },
+        ];
+
+        let report = validate_recording(&frames);
+        assert_eq!(report.failure_count(), 2);
+
+        let states = report
+            .frames
+            .iter()
+            .map(|frame| frame.state)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            [
+                PeerState::Handshake,
+                PeerState::Status,
+                PeerState::Status,
+                PeerState::Status,
+                PeerState::Status,
+            ],
+        );
+
+        assert!(matches!(
+            report.frames[2].outcome,
+            FrameOutcome::Failed {
+                error: ReplayError::TrailingBytes {
+                    count: 1,
+                    location: 1,
+                    ..
+                }
+            },
+        ));
+        assert!(matches!(
+            report.frames[4].outcome,
+            FrameOutcome::Failed {
+                error: ReplayError::Decode {
+                    state: PeerState::Status,
+                    direction: Clientbound,
+                    ..
+                }
+            },
+        ));
+
+        let lines = report.to_string();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "0\tServerbound\tHandshake\tok\tHandshake");
+        assert_eq!(lines[1], "1\tServerbound\tStatus\tok\tStatusRequest");
+        assert!(lines[2].starts_with("2\tServerbound\tStatus\terr\t"));
+        assert_eq!(lines[3], "3\tClientbound\tStatus\tok\tPingResponse");
+    }
+
+    #[tokio::test]
+    async fn status_capture_replays_through_stream() {
+        let mut server = replay::<Server>(STATUS_SERVERBOUND);
+
+        let sb_handshake::Handshake(handshake) = server
+            .read_packet::<state::Handshake>()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(handshake.version.0, PROTOCOL_VERSION);
+        assert_eq!(&*handshake.server_addr, "localhost");
+        assert_eq!(handshake.port, 25565);
+        assert_eq!(handshake.next_state, HandshakeIntent::Status);
+
+        assert!(matches!(
+            server
+                .read_packet::<state::Status>()
+                .await
+                .unwrap()
+                .unwrap(),
+            sb_status::StatusRequest(_),
+        ));
+        let sb_status::PingRequest(ping) = server
+            .read_packet::<state::Status>()
+            .await
+            .unwrap()
+            .unwrap()
+        else {
+            panic!("expected a ping request");
+        };
+        assert!(server.read_packet::<state::Status>().await.is_none());
+
+        let mut client = replay::<Client>(STATUS_CLIENTBOUND);
+
+        let cb_status::StatusResponse(status) = client
+            .read_packet::<state::Status>()
+            .await
+            .unwrap()
+            .unwrap()
+        else {
+            panic!("expected a status response");
+        };
+        assert_eq!(status.json_resp.0.version.protocol, PROTOCOL_VERSION);
+        assert_eq!(status.json_resp.0.players.max, 20);
+        assert!(status.json_resp.0.enforces_secure_chat);
+
+        let cb_status::PingResponse(pong) = client
+            .read_packet::<state::Status>()
+            .await
+            .unwrap()
+            .unwrap()
+        else {
+            panic!("expected a ping response");
+        };
+        assert_eq!(pong.payload, ping.payload);
+        assert!(client.read_packet::<state::Status>().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn login_handshake_capture_replays_through_stream() {
+        let mut server = replay::<Server>(LOGIN_HANDSHAKE_SERVERBOUND);
+
+        let sb_handshake::Handshake(handshake) = server
+            .read_packet::<state::Handshake>()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&*handshake.server_addr, "play.example.net");
+        assert_eq!(handshake.next_state, HandshakeIntent::Login);
+        assert!(server.read().await.is_none());
+    }
+
+    #[test]
+    fn status_captures_validate() {
+        use FrameDirection::*;
+
+        let serverbound = RecordedFrame::from_capture(Serverbound, STATUS_SERVERBOUND).unwrap();
+        let clientbound = RecordedFrame::from_capture(Clientbound, STATUS_CLIENTBOUND).unwrap();
+        assert_eq!(serverbound.len(), 3);
+        assert_eq!(clientbound.len(), 2);
+
+        // Handshake, status request, status response, ping request, ping response.
+        let frames = [
+            &serverbound[0],
+            &serverbound[1],
+            &clientbound[0],
+            &serverbound[2],
+            &clientbound[1],
+        ];
+        let report = validate_recording(frames);
+        assert_eq!(report.failure_count(), 0, "{report}");
+
+        let packets = report
+            .frames
+            .iter()
+            .map(|frame| match frame.outcome {
+                FrameOutcome::Decoded { packet } => packet,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            packets,
+            [
+                "Handshake",
+                "StatusRequest",
+                "StatusResponse",
+                "PingRequest",
+                "PingResponse",
+            ],
+        );
+    }
+}
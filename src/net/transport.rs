@@ -1,15 +1,48 @@
-use bytes::Bytes;
-use futures::SinkExt;
-use tokio::net::TcpStream;
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Read, Write},
+    marker::PhantomData,
+    mem,
+    pin::Pin,
+    slice,
+    task::{self, ready, Poll},
+    time::{Duration, Instant},
+};
+
+use aes::{
+    cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit},
+    Aes128,
+};
+
+use anyhow::Context;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use derive_where::derive_where;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use futures::{future::join_all, SinkExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::TcpStream,
+};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
 
 use crate::{
-    net::primitives::VarUint,
-    util::bytes_integration::{ByteMutReadSession, Snip},
+    net::{
+        primitives::{Chat, VarInt, VarUint},
+        protocol::{cb_login, cb_play, PeerState},
+    },
+    util::{
+        bytes_integration::{ByteMutReadSession, Snip},
+        proto::{byte_stream::ByteCursor, decode_seq::DecodeSeqExt},
+    },
 };
 
-use super::primitives::{Codec, SizedCodec, StreamingCodec};
+use super::{
+    primitives::{Codec, SizedCodec},
+    primitives2::{MineCodec, ProtoError, VarUint as VarUint2},
+    protocol::{ProtocolState, ProtocolVersion},
+};
 
 // === Streams === //
 
@@ -18,118 +51,1614 @@ use super::primitives::{Codec, SizedCodec, StreamingCodec};
 /// This seems to be an additional artificial restriction on packet length.
 ///
 /// [See wiki.vg for details.](https://wiki.vg/index.php?title=Protocol&oldid=18305#Packet_format).
-pub const HARD_MAX_PACKET_LEN_INCL: u32 = 2 << 21 - 1;
+pub const HARD_MAX_PACKET_LEN_INCL: u32 = (1 << 21) - 1;
 
-#[derive(Debug)]
-pub struct RawPeerStream {
-    stream: Framed<TcpStream, MinecraftCodec>,
+const _: () = assert!(HARD_MAX_PACKET_LEN_INCL == 2097151);
+
+/// The maximum size of a packet body once decompressed. This matches the limit enforced by the
+/// vanilla client and server.
+pub const MAX_DECOMPRESSED_LEN_INCL: u32 = 1 << 23;
+
+/// A framed connection to a peer. The underlying stream is usually a [`TcpStream`] but any byte
+/// stream will do, which lets tests run connections over in-memory pipes.
+#[derive_where(Debug; T: fmt::Debug)]
+pub struct RawPeerStream<R = Server, T = TcpStream> {
+    _role: PhantomData<fn() -> R>,
+    stream: Framed<EncryptedStream<T>, MinecraftCodec>,
+    metrics: ConnectionMetrics,
+    rate_limiter: Option<RateLimiter>,
+    version: ProtocolVersion,
 }
 
-impl RawPeerStream {
-    pub fn new(stream: TcpStream, max_recv_len: u32) -> Self {
+impl<R: PeerRole, T: AsyncRead + AsyncWrite + Unpin> RawPeerStream<R, T> {
+    pub fn new(stream: T, max_recv_len: u32) -> Self {
         Self {
+            _role: PhantomData,
             stream: Framed::new(
-                stream,
+                EncryptedStream::new(stream),
                 MinecraftCodec {
                     max_recv_len: max_recv_len.min(HARD_MAX_PACKET_LEN_INCL),
                     compression_threshold: None,
+                    is_first_frame: true,
                 },
             ),
+            metrics: ConnectionMetrics::new(),
+            rate_limiter: None,
+            version: ProtocolVersion::CURRENT,
         }
     }
 
+    /// Creates a stream which yields an error once the peer exceeds `limits`. Reads from a stream
+    /// created with [`RawPeerStream::new`] are unlimited.
+    pub fn with_limits(stream: T, max_recv_len: u32, limits: RateLimits) -> Self {
+        let mut stream = Self::new(stream, max_recv_len);
+        stream.rate_limiter = Some(RateLimiter::new(limits));
+        stream
+    }
+
+    /// The protocol version used to decode inbound packets. This is [`ProtocolVersion::CURRENT`]
+    /// until the handshake tells us otherwise.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    pub fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.version = version;
+    }
+
+    /// Reads the next frame, which may be a legacy server list ping if this is the first thing the
+    /// peer sent.
+    pub async fn read_frame(&mut self) -> Option<anyhow::Result<Frame>> {
+        let frame = self.stream.next().await;
+
+        if let Some(Ok(Frame::Packet(frame))) = &frame {
+            self.metrics.packets_read += 1;
+            self.metrics.bytes_read += frame.len() as u64;
+
+            if let Some(limiter) = &mut self.rate_limiter {
+                if let Err(err) = limiter.record(frame.len()) {
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        frame
+    }
+
     pub async fn read(&mut self) -> Option<anyhow::Result<Bytes>> {
-        self.stream.next().await
+        Some(match self.read_frame().await? {
+            Ok(Frame::Packet(packet)) => Ok(packet),
+            Ok(Frame::LegacyPing(_)) => Err(anyhow::anyhow!(
+                "received a legacy server list ping while expecting a packet"
+            )),
+            Err(err) => Err(err),
+        })
     }
 
-    pub async fn write(&mut self, packet: impl UnframedPacket) -> anyhow::Result<()> {
-        self.stream.send(packet.frame()).await
+    pub async fn read_packet<S: ProtocolState>(&mut self) -> Option<anyhow::Result<R::Inbound<S>>> {
+        let packet = match self.read().await? {
+            Ok(packet) => packet,
+            Err(err) => return Some(Err(err)),
+        };
+
+        Some(R::Inbound::<S>::decode_exact(self.version, &packet))
+    }
+
+    /// Writes a packet and flushes the stream, sending it along with any packets queued by
+    /// [`write_buffered`](Self::write_buffered).
+    pub async fn write<P>(&mut self, packet: P) -> anyhow::Result<()>
+    where
+        P: UnframedPacket,
+        P::Framed: FramedPacket<Direction = R::Outbound>,
+    {
+        // Encoding up front lets the codec frame the body without sizing the packet separately.
+        let packet = packet.pre_frame()?;
+        let size = packet.body().len();
+
+        self.stream.send(packet).await?;
+        self.metrics.packets_written += 1;
+        self.metrics.bytes_written += size as u64;
+
+        Ok(())
+    }
+
+    /// Queues a packet without flushing the stream so that bulk sends (e.g. streaming chunks) can
+    /// be written out in as few syscalls as possible. Queued packets are sent by the next call to
+    /// [`flush`](Self::flush), [`write`](Self::write), or [`close`](Self::close), although they
+    /// may be written out earlier if the write buffer grows large.
+    pub async fn write_buffered<P>(&mut self, packet: P) -> anyhow::Result<()>
+    where
+        P: UnframedPacket,
+        P::Framed: FramedPacket<Direction = R::Outbound>,
+    {
+        let packet = packet.pre_frame()?;
+        let size = packet.body().len();
+
+        self.stream.feed(packet).await?;
+        self.metrics.packets_written += 1;
+        self.metrics.bytes_written += size as u64;
+
+        Ok(())
+    }
+
+    /// Sends every packet queued by [`write_buffered`](Self::write_buffered).
+    pub async fn flush(&mut self) -> anyhow::Result<()> {
+        // `Framed` is a sink for every packet type but flushing it is the same regardless.
+        SinkExt::<PreFramedPacket<R::Outbound>>::flush(&mut self.stream).await
+    }
+
+    /// Sends every queued packet and then closes the stream.
+    ///
+    /// Flushing is asynchronous so it can't happen on drop. Dropping the stream without closing or
+    /// flushing it discards any queued packets, which is logged as a warning.
+    pub async fn close(&mut self) -> anyhow::Result<()> {
+        SinkExt::<PreFramedPacket<R::Outbound>>::close(&mut self.stream).await
+    }
+
+    pub fn metrics(&self) -> &ConnectionMetrics {
+        &self.metrics
     }
 
     pub fn set_max_recv_len(&mut self, len: u32) {
         self.stream.codec_mut().max_recv_len = len.min(HARD_MAX_PACKET_LEN_INCL);
     }
+
+    /// Switches the stream to the compressed packet format, compressing every packet body at least
+    /// `threshold` bytes long. This should be called right after sending or receiving the login
+    /// `SetCompression` packet since every subsequent packet uses the new format.
+    pub fn set_compression_threshold(&mut self, threshold: Option<u32>) {
+        self.stream.codec_mut().compression_threshold = threshold;
+    }
+
+    /// Encrypts all subsequent traffic with AES-128-CFB8, using the shared secret as both the key
+    /// and the IV. This should be called right after the encryption response has been exchanged.
+    ///
+    /// Any bytes which were already buffered but not yet decoded arrived after the response and
+    /// were therefore encrypted by the peer, so they are decrypted in place.
+    pub fn enable_encryption(&mut self, shared_secret: [u8; 16]) {
+        let mut buffered = mem::take(self.stream.read_buffer_mut());
+        let stream = self.stream.get_mut();
+
+        stream.enable_encryption(shared_secret);
+        let (_, decryptor) = stream.ciphers.as_mut().unwrap();
+        decrypt_in_place(decryptor, &mut buffered);
+
+        *self.stream.read_buffer_mut() = buffered;
+    }
+}
+
+impl<R, T> RawPeerStream<R, T> {
+    /// The number of bytes which have been queued by [`write_buffered`](Self::write_buffered) but
+    /// not yet handed to the socket.
+    pub fn pending_write_len(&self) -> usize {
+        self.stream.write_buffer().len() + self.stream.get_ref().write_buf.len()
+    }
+}
+
+impl<R, T> Drop for RawPeerStream<R, T> {
+    fn drop(&mut self) {
+        let pending = self.pending_write_len();
+
+        if pending > 0 {
+            log::warn!(
+                "Dropped a peer stream with {pending} byte(s) of queued packets; they were never \
+                 sent. Call `flush` or `close` before dropping the stream to send them."
+            );
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> RawPeerStream<Server, T> {
+    /// Disconnects the peer, showing it `reason` if its current state has a disconnect packet, and
+    /// then closes the stream. Peers in the handshake or status states have no way of being told
+    /// why they were disconnected so their stream is simply closed.
+    pub async fn kick(&mut self, state: PeerState, reason: Chat) -> anyhow::Result<()> {
+        match state {
+            PeerState::Handshake | PeerState::Status => {}
+            PeerState::Login => self.write(cb_login::Disconnect { reason }).await?,
+            PeerState::Play => self.write(cb_play::Disconnect { reason }).await?,
+        }
+
+        self.close().await
+    }
+
+    /// Tells a peer in the login state to compress every packet body at least `threshold` bytes long
+    /// and switches the stream to the compressed packet format. The `SetCompression` packet itself
+    /// is sent before the switch so that it arrives in the format the peer is still expecting.
+    pub async fn enable_compression(&mut self, threshold: u32) -> anyhow::Result<()> {
+        let packet = cb_login::SetCompression {
+            threshold: VarInt(
+                i32::try_from(threshold).context("compression threshold is too large")?,
+            ),
+        };
+
+        self.write(packet).await?;
+
+        self.set_compression_threshold(Some(threshold));
+        Ok(())
+    }
+
+    /// Answers a legacy server list ping in the format its client expects and then closes the
+    /// stream, which is how legacy pings are always concluded.
+    pub async fn respond_to_legacy_ping(
+        &mut self,
+        ping: &LegacyPing,
+        response: &LegacyPingResponse<'_>,
+    ) -> anyhow::Result<()> {
+        let response = response.encode(ping);
+        let stream = self.stream.get_mut();
+
+        stream.write_all(&response).await?;
+        stream.shutdown().await?;
+
+        Ok(())
+    }
+}
+
+/// Sends the same packet to every stream, encoding it only once.
+///
+/// Only the packet body is shared between streams. The frame around it is still produced by each
+/// stream's codec so connections with different compression thresholds will each compress the body
+/// themselves. Broadcasting to streams with a uniform compression state is therefore cheapest.
+///
+/// The streams are written to concurrently so a slow peer does not hold up the others. A failure to
+/// write to one stream does not prevent the packet from being written to the rest. The first error
+/// encountered, if any, is returned once every stream has been attempted.
+pub async fn broadcast<R, T, P>(
+    packet: P,
+    streams: &mut [RawPeerStream<R, T>],
+) -> anyhow::Result<()>
+where
+    R: PeerRole,
+    T: AsyncRead + AsyncWrite + Unpin,
+    P: UnframedPacket,
+    P::Framed: FramedPacket<Direction = R::Outbound>,
+{
+    let packet = PreFramedPacket::new(packet)?;
+    let writes = streams
+        .iter_mut()
+        .map(|stream| stream.write(packet.clone()));
+
+    join_all(writes).await.into_iter().collect()
+}
+
+// === Metrics === //
+
+/// Per-connection traffic counters. Byte counts cover packet bodies (i.e. the packet id and its
+/// fields) but not the frame length prefix.
+#[derive(Debug, Copy, Clone)]
+pub struct ConnectionMetrics {
+    pub opened_at: Instant,
+    pub packets_read: u64,
+    pub bytes_read: u64,
+    pub packets_written: u64,
+    pub bytes_written: u64,
+}
+
+impl ConnectionMetrics {
+    pub fn new() -> Self {
+        Self {
+            opened_at: Instant::now(),
+            packets_read: 0,
+            bytes_read: 0,
+            packets_written: 0,
+            bytes_written: 0,
+        }
+    }
+
+    pub fn snapshot(&self) -> Self {
+        *self
+    }
+
+    pub fn age(&self) -> Duration {
+        self.opened_at.elapsed()
+    }
+}
+
+impl Default for ConnectionMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ConnectionMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "read {} packet(s) ({} byte(s)), wrote {} packet(s) ({} byte(s)) over {:?}",
+            self.packets_read,
+            self.bytes_read,
+            self.packets_written,
+            self.bytes_written,
+            self.age(),
+        )
+    }
+}
+
+// === Rate Limiting === //
+
+/// Limits on how much a peer may send within any one-second window. Byte counts cover packet
+/// bodies, as in [`ConnectionMetrics`].
+#[derive(Debug, Copy, Clone)]
+pub struct RateLimits {
+    pub max_packets_per_sec: u32,
+    pub max_bytes_per_sec: u64,
+}
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+struct RateLimiter {
+    limits: RateLimits,
+    /// The arrival time and size of every packet read within the last `RATE_LIMIT_WINDOW`. This
+    /// holds at most one more packet than the limit since the stream fails once it's exceeded.
+    recent: VecDeque<(Instant, u64)>,
+    recent_bytes: u64,
+}
+
+impl RateLimiter {
+    fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            recent: VecDeque::new(),
+            recent_bytes: 0,
+        }
+    }
+
+    fn record(&mut self, size: usize) -> anyhow::Result<()> {
+        let now = Instant::now();
+
+        while let Some(&(read_at, size)) = self.recent.front() {
+            if now.duration_since(read_at) < RATE_LIMIT_WINDOW {
+                break;
+            }
+
+            self.recent.pop_front();
+            self.recent_bytes -= size;
+        }
+
+        self.recent.push_back((now, size as u64));
+        self.recent_bytes += size as u64;
+
+        anyhow::ensure!(
+            self.recent.len() <= self.limits.max_packets_per_sec as usize,
+            "peer sent more than {} packet(s) within a second",
+            self.limits.max_packets_per_sec,
+        );
+
+        anyhow::ensure!(
+            self.recent_bytes <= self.limits.max_bytes_per_sec,
+            "peer sent more than {} byte(s) within a second",
+            self.limits.max_bytes_per_sec,
+        );
+
+        Ok(())
+    }
+}
+
+// === Roles === //
+
+pub trait PacketDirection {}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Clientbound;
+
+impl PacketDirection for Clientbound {}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Serverbound;
+
+impl PacketDirection for Serverbound {}
+
+/// The side of the connection a [`RawPeerStream`] is on, which determines which set of packets it
+/// reads and writes in each [`ProtocolState`]. A proxy would use a [`Server`] stream to talk to its
+/// clients and a [`Client`] stream to talk to the upstream server.
+pub trait PeerRole {
+    type Outbound: PacketDirection;
+    type Inbound<S: ProtocolState>: Codec<ProtocolVersion>;
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Server;
+
+impl PeerRole for Server {
+    type Outbound = Clientbound;
+    type Inbound<S: ProtocolState> = S::Serverbound;
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Client;
+
+impl PeerRole for Client {
+    type Outbound = Serverbound;
+    type Inbound<S: ProtocolState> = S::Clientbound;
 }
 
 // === Packet traits === //
 
-pub trait FramedPacket: SizedCodec<()> {}
+pub trait FramedPacket: SizedCodec<()> {
+    type Direction: PacketDirection;
+}
 
-pub trait UnframedPacket {
+pub trait UnframedPacket: Sized {
     type Framed: FramedPacket;
 
     fn frame(self) -> Self::Framed;
+
+    /// Validates and encodes the packet. This is how streams send packets since the encoded body
+    /// already knows its length whereas sizing some packets (e.g. those containing JSON) takes as
+    /// much work as encoding them.
+    fn pre_frame(
+        self,
+    ) -> anyhow::Result<PreFramedPacket<<Self::Framed as FramedPacket>::Direction>> {
+        PreFramedPacket::new(self)
+    }
+}
+
+/// A packet whose id and body have already been encoded. Cloning one only bumps a reference count
+/// so this is useful when the same packet is sent many times (e.g. when broadcasting entity
+/// movement to every nearby player) since it skips re-validating and re-encoding the packet on
+/// each send.
+#[derive_where(Debug, Clone)]
+pub struct PreFramedPacket<D> {
+    _direction: PhantomData<fn() -> D>,
+    body: Bytes,
+}
+
+impl<D: PacketDirection> PreFramedPacket<D> {
+    pub fn new<P>(packet: P) -> anyhow::Result<Self>
+    where
+        P: UnframedPacket,
+        P::Framed: FramedPacket<Direction = D>,
+    {
+        let packet = packet.frame();
+        packet.validate(())?;
+
+        let mut body = BytesMut::new();
+        packet.encode((), &mut body);
+
+        Ok(Self {
+            _direction: PhantomData,
+            body: body.freeze(),
+        })
+    }
+
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+}
+
+impl<D: PacketDirection> Codec<()> for PreFramedPacket<D> {
+    fn decode(_args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+        Ok(Self {
+            _direction: PhantomData,
+            body: Bytes::decode((), src, cursor)?,
+        })
+    }
+
+    fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+        self.body.encode((), cursor);
+    }
+}
+
+impl<D: PacketDirection> SizedCodec<()> for PreFramedPacket<D> {
+    fn size(&self, _args: ()) -> usize {
+        self.body.len()
+    }
+}
+
+impl<D: PacketDirection> FramedPacket for PreFramedPacket<D> {
+    type Direction = D;
+}
+
+impl<D: PacketDirection> UnframedPacket for PreFramedPacket<D> {
+    type Framed = Self;
+
+    fn frame(self) -> Self::Framed {
+        self
+    }
+
+    fn pre_frame(self) -> anyhow::Result<PreFramedPacket<D>> {
+        Ok(self)
+    }
+}
+
+// === Encryption === //
+
+type Aes128Cfb8Enc = cfb8::Encryptor<Aes128>;
+type Aes128Cfb8Dec = cfb8::Decryptor<Aes128>;
+
+/// A byte stream which transparently encrypts everything written to it and decrypts everything read
+/// from it once encryption has been enabled. CFB8 is a stream cipher mode so both ciphers carry
+/// their state from one read or write to the next.
+#[derive(Debug)]
+pub struct EncryptedStream<T = TcpStream> {
+    inner: T,
+    ciphers: Option<(Aes128Cfb8Enc, Aes128Cfb8Dec)>,
+
+    /// Bytes which have been encrypted but not yet accepted by `inner`. Once encrypted, bytes must
+    /// be written exactly once since the cipher state has already advanced past them.
+    write_buf: BytesMut,
+}
+
+impl<T> EncryptedStream<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            ciphers: None,
+            write_buf: BytesMut::new(),
+        }
+    }
+
+    pub fn enable_encryption(&mut self, shared_secret: [u8; 16]) {
+        let key = shared_secret.into();
+
+        self.ciphers = Some((
+            Aes128Cfb8Enc::new(&key, &key),
+            Aes128Cfb8Dec::new(&key, &key),
+        ));
+    }
+}
+
+impl<T: AsyncWrite + Unpin> EncryptedStream<T> {
+    fn poll_write_buf(&mut self, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.write_buf))?;
+
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+
+            self.write_buf.advance(written);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn encrypt_in_place(encryptor: &mut Aes128Cfb8Enc, data: &mut [u8]) {
+    // CFB8 has a block size of one byte.
+    for byte in data {
+        encryptor.encrypt_block_mut(slice::from_mut(byte).into());
+    }
+}
+
+fn decrypt_in_place(decryptor: &mut Aes128Cfb8Dec, data: &mut [u8]) {
+    for byte in data {
+        decryptor.decrypt_block_mut(slice::from_mut(byte).into());
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for EncryptedStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = self.get_mut();
+        let start = buf.filled().len();
+
+        ready!(Pin::new(&mut me.inner).poll_read(cx, buf))?;
+
+        if let Some((_, decryptor)) = &mut me.ciphers {
+            decrypt_in_place(decryptor, &mut buf.filled_mut()[start..]);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for EncryptedStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = self.get_mut();
+
+        if me.ciphers.is_none() {
+            return Pin::new(&mut me.inner).poll_write(cx, buf);
+        }
+
+        // Finish writing out previously encrypted bytes before accepting new ones so that the
+        // buffer stays bounded.
+        ready!(me.poll_write_buf(cx))?;
+
+        let (encryptor, _) = me.ciphers.as_mut().unwrap();
+        let start = me.write_buf.len();
+        me.write_buf.extend_from_slice(buf);
+        encrypt_in_place(encryptor, &mut me.write_buf[start..]);
+
+        // The bytes are now ours to write so we can report them as written even if `inner` is not
+        // ready for them yet. They'll be written out by the next write or flush.
+        if let Poll::Ready(Err(err)) = me.poll_write_buf(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        let me = self.get_mut();
+        ready!(me.poll_write_buf(cx))?;
+        Pin::new(&mut me.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        let me = self.get_mut();
+        ready!(me.poll_write_buf(cx))?;
+        Pin::new(&mut me.inner).poll_shutdown(cx)
+    }
+}
+
+// === Legacy Pings === //
+
+#[derive(Debug, Clone)]
+pub enum Frame {
+    Packet(Bytes),
+    LegacyPing(LegacyPing),
+}
+
+/// A server list ping sent by a pre-1.7 client. Each variant corresponds to a range of client
+/// versions which expect a different response.
+#[derive(Debug, Clone)]
+pub enum LegacyPing {
+    /// Sent by beta 1.8 through 1.3 clients, which expect a `§`-delimited response.
+    Beta,
+
+    /// Sent by 1.4 and 1.5 clients.
+    V1_4,
+
+    /// Sent by 1.6 clients, which also tell us which server they're trying to reach.
+    V1_6 {
+        protocol: u8,
+        host: String,
+        port: i32,
+    },
+}
+
+/// The prefix of the server list ping sent by pre-1.7 clients, which doesn't use modern framing.
+/// Clients older than 1.6 send only a subset of it.
+const LEGACY_PING_PREFIX: [u8; 3] = [0xFE, 0x01, 0xFA];
+
+/// The plugin channel through which 1.6 clients send their ping's details.
+const LEGACY_PING_CHANNEL: &str = "MC|PingHost";
+
+impl LegacyPing {
+    /// Decodes a legacy ping from a cursor starting at its leading `0xFE`. Like the vanilla server,
+    /// this distinguishes the older formats by how much was sent so far rather than by waiting for
+    /// more bytes, since those clients wait for a response after sending their partial prefix.
+    fn decode(cursor: &mut ByteCursor) -> anyhow::Result<Option<Self>> {
+        let prefix_len = cursor.len().min(LEGACY_PING_PREFIX.len());
+        cursor
+            .expect_bytes(&LEGACY_PING_PREFIX[..prefix_len])
+            .context("received a malformed legacy server list ping")?;
+
+        match prefix_len {
+            1 => return Ok(Some(Self::Beta)),
+            2 => return Ok(Some(Self::V1_4)),
+            _ => {}
+        }
+
+        // Read the plugin message containing the ping's details.
+        let Some(channel_len) = cursor.read_arr().map(u16::from_be_bytes) else {
+            return Ok(None);
+        };
+
+        let Some(channel) = cursor.read_slice(channel_len as usize * 2) else {
+            return Ok(None);
+        };
+
+        let Some(data_len) = cursor.read_arr().map(u16::from_be_bytes) else {
+            return Ok(None);
+        };
+
+        let Some(mut data) = cursor.sub_reader(data_len as usize) else {
+            return Ok(None);
+        };
+
+        let channel = decode_utf16_be(channel)?;
+        anyhow::ensure!(
+            channel == LEGACY_PING_CHANNEL,
+            "legacy server list ping was sent over unexpected channel {channel:?}",
+        );
+
+        // Parse the message's contents.
+        let truncated = || anyhow::anyhow!("legacy server list ping data was truncated");
+
+        let protocol = data.read().ok_or_else(truncated)?;
+        let host_len = data
+            .read_arr()
+            .map(u16::from_be_bytes)
+            .ok_or_else(truncated)?;
+        let host = data
+            .read_slice(host_len as usize * 2)
+            .ok_or_else(truncated)?;
+        let host = decode_utf16_be(host)?;
+        let port = data
+            .read_arr()
+            .map(i32::from_be_bytes)
+            .ok_or_else(truncated)?;
+
+        Ok(Some(Self::V1_6 {
+            protocol,
+            host,
+            port,
+        }))
+    }
+}
+
+/// The server information sent in response to a legacy server list ping.
+#[derive(Debug, Copy, Clone)]
+pub struct LegacyPingResponse<'a> {
+    pub protocol: i32,
+    pub version_name: &'a str,
+    pub motd: &'a str,
+    pub online: i32,
+    pub max: i32,
+}
+
+impl LegacyPingResponse<'_> {
+    /// Encodes the response as the kick packet that legacy clients expect: an `0xFF` packet id
+    /// followed by a length-prefixed UTF-16BE string.
+    pub fn encode(&self, ping: &LegacyPing) -> Bytes {
+        let Self {
+            protocol,
+            version_name,
+            motd,
+            online,
+            max,
+        } = self;
+
+        let text = match ping {
+            LegacyPing::Beta => format!("{motd}\u{a7}{online}\u{a7}{max}"),
+            LegacyPing::V1_4 | LegacyPing::V1_6 { .. } => {
+                format!("\u{a7}1\0{protocol}\0{version_name}\0{motd}\0{online}\0{max}")
+            }
+        };
+
+        let text = text
+            .encode_utf16()
+            .take(u16::MAX as usize)
+            .collect::<Vec<_>>();
+
+        let mut buf = BytesMut::with_capacity(3 + text.len() * 2);
+        buf.put_u8(0xFF);
+        buf.put_u16(text.len() as u16);
+
+        for unit in text {
+            buf.put_u16(unit);
+        }
+
+        buf.freeze()
+    }
+}
+
+fn decode_utf16_be(bytes: &[u8]) -> anyhow::Result<String> {
+    char::decode_utf16(
+        bytes
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]])),
+    )
+    .collect::<Result<String, _>>()
+    .context("legacy server list ping contained an invalid UTF-16 string")
 }
 
 // === Codecs === //
 
+/// Splits a raw capture of everything one side of a connection sent into its frames using the same
+/// codec as live connections. The capture must not be encrypted and must end before compression
+/// is enabled, which covers everything up to the end of an offline-mode login.
+pub fn split_capture(capture: &[u8]) -> anyhow::Result<Vec<Frame>> {
+    let mut codec = MinecraftCodec {
+        max_recv_len: HARD_MAX_PACKET_LEN_INCL,
+        compression_threshold: None,
+        is_first_frame: true,
+    };
+    let mut buf = BytesMut::from(capture);
+    let mut frames = Vec::new();
+
+    while let Some(frame) = codec.decode(&mut buf)? {
+        frames.push(frame);
+    }
+
+    anyhow::ensure!(
+        buf.is_empty(),
+        "Capture ends with an incomplete frame of {} byte(s).",
+        buf.len(),
+    );
+
+    Ok(frames)
+}
+
 #[derive(Debug, Copy, Clone, Default)]
 struct MinecraftCodec {
     pub max_recv_len: u32,
     pub compression_threshold: Option<u32>,
+    pub is_first_frame: bool,
 }
 
 impl Decoder for MinecraftCodec {
-    type Item = Bytes;
+    type Item = Frame;
     type Error = anyhow::Error;
 
-    // TODO: Handle legacy framing of packets.
     fn decode(&mut self, stream: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         log::trace!("MinecraftCodec is buffering {} byte(s).", stream.len());
 
         let stream = ByteMutReadSession::new(stream);
         let cursor = &mut stream.cursor();
 
-        if let Some(_compression_threshold) = self.compression_threshold {
-            todo!();
-        } else {
-            // Decode length, validate it, and ensure we have the capacity to hold it.
-            let Some(length) = VarUint::decode_streaming(cursor)? else { return Ok(None) };
+        // Legacy pings can only be the first thing sent by a client. Their first byte would
+        // otherwise be interpreted as the start of a huge frame length.
+        if self.is_first_frame {
+            match cursor.peek() {
+                Some(byte) if byte == LEGACY_PING_PREFIX[0] => {
+                    let Some(ping) = LegacyPing::decode(cursor)? else {
+                        return Ok(None);
+                    };
 
-            if length.0 > self.max_recv_len {
-                anyhow::bail!(
-					"received packet of {length:?} byte(s) while the codec was set to accept only {} byte(s)",
-					self.max_recv_len,
-				);
+                    stream.consume_cursor(cursor);
+                    self.is_first_frame = false;
+
+                    return Ok(Some(Frame::LegacyPing(ping)));
+                }
+                Some(_) => self.is_first_frame = false,
+                None => return Ok(None),
             }
+        }
+
+        // Decode length, validate it, and ensure we have the capacity to hold it. A length which
+        // was split across reads is incomplete rather than malformed so we wait for the rest of it.
+        let length = match <VarUint2 as DecodeSeqExt<MineCodec, ()>>::decode(cursor, ()) {
+            Ok(VarUint2(length)) => length,
+            Err(err) if ProtoError::is_incomplete(&err) => return Ok(None),
+            Err(err) => return Err(err),
+        };
 
-            stream.reserve(length.0 as usize);
+        // Every packet has at least an id so an empty frame means the stream is misframed.
+        if length == 0 {
+            anyhow::bail!("received empty packet frame");
+        }
+
+        if length > self.max_recv_len {
+            anyhow::bail!(
+				"received packet of {length} byte(s) while the codec was set to accept only {} byte(s)",
+				self.max_recv_len,
+			);
+        }
 
-            // Decode the body
-            let Some(body) = cursor.read_slice(length.0 as usize) else { return Ok(None) };
+        stream.reserve(length as usize);
 
-            // Construct a frame for it
-            let body = stream.freeze_range(body);
-            stream.consume_cursor(&cursor);
+        // Decode the body
+        let Some(body) = cursor.read_slice(length as usize) else { return Ok(None) };
 
-            Ok(Some(body))
+        // Construct a frame for it
+        let body = stream.freeze_range(body);
+        stream.consume_cursor(&cursor);
+
+        let body = match self.compression_threshold {
+            Some(threshold) => Self::decompress(threshold, body)?,
+            None => body,
+        };
+
+        Ok(Some(Frame::Packet(body)))
+    }
+}
+
+impl MinecraftCodec {
+    /// Decodes every complete frame in `buffer` at once, leaving a trailing partial frame buffered
+    /// for the next call.
+    pub fn decode_all(&mut self, buffer: &mut BytesMut) -> anyhow::Result<Vec<Frame>> {
+        let mut frames = Vec::new();
+        while let Some(frame) = Decoder::decode(self, buffer)? {
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    fn decompress(threshold: u32, frame: Bytes) -> anyhow::Result<Bytes> {
+        let cursor = &mut ByteCursor::new(&frame);
+        let data_len = VarUint::decode((), &frame, cursor)?.0;
+
+        // A data length of zero indicates that the body was too small to be compressed.
+        if data_len == 0 {
+            return Ok(frame.freeze_range(cursor.remaining()));
+        }
+
+        if data_len < threshold {
+            anyhow::bail!(
+                "received compressed packet of {data_len} byte(s), which is below the compression \
+                 threshold of {threshold} byte(s)",
+            );
+        }
+
+        if data_len > MAX_DECOMPRESSED_LEN_INCL {
+            anyhow::bail!(
+                "received compressed packet of {data_len} byte(s), which is larger than the \
+                 maximum of {MAX_DECOMPRESSED_LEN_INCL} byte(s)",
+            );
         }
+
+        // Deflate can't shrink its input by more than a factor of about 1032 so we don't trust a
+        // claimed length beyond that when preallocating. Otherwise, a tiny frame could have us
+        // reserve the maximum decompressed length.
+        let capacity = (data_len as usize).min(cursor.remaining().len().saturating_mul(1032));
+
+        // Read one more byte than expected so that we can detect over-long bodies without
+        // inflating all of them.
+        let mut body = Vec::with_capacity(capacity);
+        ZlibDecoder::new(cursor.remaining())
+            .take(u64::from(data_len) + 1)
+            .read_to_end(&mut body)?;
+
+        if body.len() != data_len as usize {
+            anyhow::bail!(
+                "compressed packet claimed to contain {data_len} byte(s) but inflated to {}{} \
+                 byte(s)",
+                if body.len() > data_len as usize { "more than " } else { "" },
+                body.len().min(data_len as usize),
+            );
+        }
+
+        Ok(Bytes::from(body))
     }
 }
 
+/// Encodes `packet` after its length prefix has been written for a body of `size` bytes. A
+/// [`SizedCodec`] which disagrees with its [`Codec`] would silently corrupt the rest of the stream
+/// so we check that the prediction held in debug builds.
+fn encode_predicted<B: FramedPacket>(packet: &B, size: usize, dst: &mut BytesMut) {
+    let start = dst.len();
+    packet.encode((), dst);
+
+    debug_assert_eq!(
+        dst.len() - start,
+        size,
+        "{} encoded to a different number of bytes than its `SizedCodec` predicted",
+        std::any::type_name::<B>(),
+    );
+}
+
 impl<B: FramedPacket> Encoder<B> for MinecraftCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, packet: B, dst: &mut bytes::BytesMut) -> Result<(), Self::Error> {
-        if let Some(_compression_threshold) = self.compression_threshold {
-            todo!();
-        } else {
-            let size = packet.size(());
+        // Ensure that the packet satisfies its length constraints before committing to it.
+        packet.validate(())?;
 
+        let size = packet.size(());
+
+        let Some(threshold) = self.compression_threshold else {
             // Validate packet size
             let Some(size) = size
                 .try_into()
                 .ok()
-                .filter(|&v| v < HARD_MAX_PACKET_LEN_INCL)
+                .filter(|&v| v <= HARD_MAX_PACKET_LEN_INCL)
             else {
                 anyhow::bail!("Attempted to send packet of size {size}, which is too big!");
             };
 
             // Write out packet
             VarUint(size).encode((), dst);
-            packet.encode((), dst);
+            encode_predicted(&packet, size as usize, dst);
+
+            return Ok(());
+        };
 
+        let Some(data_len) = size
+            .try_into()
+            .ok()
+            .filter(|&v| v <= MAX_DECOMPRESSED_LEN_INCL)
+        else {
+            anyhow::bail!("Attempted to send packet of size {size}, which is too big!");
+        };
+
+        if data_len < threshold {
+            // Small packets are sent uncompressed with a data length of zero.
+            let frame_len = VarUint(0).size(()) + size;
+            let Some(frame_len) = frame_len
+                .try_into()
+                .ok()
+                .filter(|&v| v <= HARD_MAX_PACKET_LEN_INCL)
+            else {
+                anyhow::bail!("Attempted to send packet of size {frame_len}, which is too big!");
+            };
+
+            VarUint(frame_len).encode((), dst);
+            VarUint(0).encode((), dst);
+            encode_predicted(&packet, size, dst);
+        } else {
+            let mut body = BytesMut::with_capacity(size);
+            encode_predicted(&packet, size, &mut body);
+
+            let mut compressor = ZlibEncoder::new(Vec::new(), Compression::default());
+            compressor.write_all(&body)?;
+            let compressed = compressor.finish()?;
+
+            let frame_len = VarUint(data_len).size(()) + compressed.len();
+            let Some(frame_len) = frame_len
+                .try_into()
+                .ok()
+                .filter(|&v| v <= HARD_MAX_PACKET_LEN_INCL)
+            else {
+                anyhow::bail!(
+                    "Attempted to send compressed packet of size {frame_len}, which is too big!"
+                );
+            };
+
+            VarUint(frame_len).encode((), dst);
+            VarUint(data_len).encode((), dst);
+            dst.extend_from_slice(&compressed);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use std::cell::Cell;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::net::{
+        primitives::{ChatComponent, JsonValue, SerializableJsonValue},
+        protocol::{sb_play, sb_status, state},
+    };
+
+    use super::*;
+
+    async fn loopback_sockets() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap());
+        let (client, accepted) = tokio::join!(client, listener.accept());
+
+        (accepted.unwrap().0, client.unwrap())
+    }
+
+    async fn loopback_pair() -> (RawPeerStream<Server>, RawPeerStream<Client>) {
+        let (server, client) = loopback_sockets().await;
+
+        (
+            RawPeerStream::new(server, HARD_MAX_PACKET_LEN_INCL),
+            RawPeerStream::new(client, HARD_MAX_PACKET_LEN_INCL),
+        )
+    }
+
+    fn reason() -> Chat {
+        JsonValue(ChatComponent::text("Kicked by an operator").into())
+    }
+
+    fn expected_json() -> serde_json::Value {
+        serde_json::json!({ "text": "Kicked by an operator" })
+    }
+
+    #[tokio::test]
+    async fn kick_sends_the_disconnect_for_the_peer_state() {
+        let (mut server, mut client) = loopback_pair().await;
+        server.kick(PeerState::Login, reason()).await.unwrap();
+
+        let packet = client.read().await.unwrap().unwrap();
+        assert_eq!(packet[0], 0);
+        let cb_login::Packet::Disconnect(disconnect) =
+            cb_login::Packet::decode_exact(ProtocolVersion::CURRENT, &packet).unwrap()
+        else {
+            panic!("expected a login disconnect");
+        };
+        assert_eq!(
+            serde_json::to_value(&disconnect.reason.0).unwrap(),
+            expected_json()
+        );
+        assert!(client.read().await.is_none());
+
+        let (mut server, mut client) = loopback_pair().await;
+        server.kick(PeerState::Play, reason()).await.unwrap();
+
+        let Some(Ok(cb_play::Packet::Disconnect(disconnect))) =
+            client.read_packet::<state::Play>().await
+        else {
+            panic!("expected a play disconnect");
+        };
+        assert_eq!(
+            serde_json::to_value(&disconnect.reason.0).unwrap(),
+            expected_json()
+        );
+        assert!(client.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn kick_closes_handshaking_peers_without_a_packet() {
+        let (mut server, mut client) = loopback_pair().await;
+        server.kick(PeerState::Handshake, reason()).await.unwrap();
+
+        assert!(client.read().await.is_none());
+    }
+
+    fn utf16_be(str: &str) -> Vec<u8> {
+        str.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    #[test]
+    fn decodes_canonical_legacy_pings() {
+        // The 1.6 ping, as sent to `localhost:25565`.
+        let host = utf16_be("localhost");
+        let mut data = vec![74];
+        data.extend_from_slice(&9u16.to_be_bytes());
+        data.extend_from_slice(&host);
+        data.extend_from_slice(&25565i32.to_be_bytes());
+
+        let mut ping = vec![0xFE, 0x01, 0xFA, 0x00, 0x0B];
+        ping.extend_from_slice(&utf16_be("MC|PingHost"));
+        ping.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        ping.extend_from_slice(&data);
+
+        let frames = split_capture(&ping).unwrap();
+        let [Frame::LegacyPing(LegacyPing::V1_6 {
+            protocol: 74,
+            host,
+            port: 25565,
+        })] = &frames[..]
+        else {
+            panic!("unexpected frames {frames:?}");
+        };
+        assert_eq!(host, "localhost");
+
+        // A truncated 1.6 ping waits for the rest rather than being treated as an older one.
+        assert!(split_capture(&ping[..ping.len() - 1]).is_err());
+
+        assert!(matches!(
+            &split_capture(&[0xFE, 0x01]).unwrap()[..],
+            [Frame::LegacyPing(LegacyPing::V1_4)],
+        ));
+        assert!(matches!(
+            &split_capture(&[0xFE]).unwrap()[..],
+            [Frame::LegacyPing(LegacyPing::Beta)],
+        ));
+    }
+
+    #[test]
+    fn encodes_legacy_ping_responses() {
+        let response = LegacyPingResponse {
+            protocol: 127,
+            version_name: "1.20.1",
+            motd: "A Raft server",
+            online: 1,
+            max: 20,
+        };
+
+        let text = "\u{a7}1\x00127\x001.20.1\x00A Raft server\x001\x0020";
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&(text.encode_utf16().count() as u16).to_be_bytes());
+        expected.extend_from_slice(&utf16_be(text));
+        assert_eq!(response.encode(&LegacyPing::V1_4), expected);
+
+        let text = "A Raft server\u{a7}1\u{a7}20";
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&(text.encode_utf16().count() as u16).to_be_bytes());
+        expected.extend_from_slice(&utf16_be(text));
+        assert_eq!(response.encode(&LegacyPing::Beta), expected);
+    }
+
+    #[test]
+    fn cfb8_ciphers_are_symmetric_and_stateful() {
+        let key = (*b"0123456789abcdef").into();
+        let plaintext = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+
+        // Encrypt in uneven chunks and decrypt in different ones to show that the cipher state
+        // carries over between calls.
+        let mut encryptor = Aes128Cfb8Enc::new(&key, &key);
+        let mut data = plaintext.clone();
+        for chunk in data.chunks_mut(7) {
+            encrypt_in_place(&mut encryptor, chunk);
+        }
+        assert_ne!(data, plaintext);
+
+        let mut decryptor = Aes128Cfb8Dec::new(&key, &key);
+        for chunk in data.chunks_mut(13) {
+            decrypt_in_place(&mut decryptor, chunk);
+        }
+        assert_eq!(data, plaintext);
+    }
+
+    #[tokio::test]
+    async fn encrypted_streams_exchange_packets() {
+        let (mut server, mut client) = loopback_pair().await;
+        let secret = *b"0123456789abcdef";
+        server.enable_encryption(secret);
+        client.enable_encryption(secret);
+
+        for id in 0..3 {
+            client.write(sb_play::KeepAlive { id }).await.unwrap();
+            let Some(Ok(sb_play::Packet::KeepAlive(packet))) =
+                server.read_packet::<state::Play>().await
+            else {
+                panic!("expected a keep-alive");
+            };
+            assert_eq!(packet.id, id);
+        }
+    }
+
+    async fn rate_limited_pair(
+        limits: RateLimits,
+    ) -> (RawPeerStream<Server>, RawPeerStream<Client>) {
+        let (server, client) = loopback_sockets().await;
+
+        (
+            RawPeerStream::with_limits(server, HARD_MAX_PACKET_LEN_INCL, limits),
+            RawPeerStream::new(client, HARD_MAX_PACKET_LEN_INCL),
+        )
+    }
+
+    #[tokio::test]
+    async fn rate_limits_fail_the_packet_past_the_limit() {
+        let (mut server, mut client) = rate_limited_pair(RateLimits {
+            max_packets_per_sec: 3,
+            max_bytes_per_sec: u64::MAX,
+        })
+        .await;
+
+        for id in 0..4 {
+            client
+                .write_buffered(sb_play::KeepAlive { id })
+                .await
+                .unwrap();
+        }
+        client.flush().await.unwrap();
+
+        for _ in 0..3 {
+            server.read().await.unwrap().unwrap();
+        }
+        let err = server.read().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("more than 3 packet(s)"), "{err:#}");
+
+        // Each keep-alive body is a one-byte id followed by an 8-byte payload.
+        let (mut server, mut client) = rate_limited_pair(RateLimits {
+            max_packets_per_sec: u32::MAX,
+            max_bytes_per_sec: 18,
+        })
+        .await;
+
+        for id in 0..3 {
+            client.write(sb_play::KeepAlive { id }).await.unwrap();
+        }
+
+        for _ in 0..2 {
+            server.read().await.unwrap().unwrap();
+        }
+        let err = server.read().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("more than 18 byte(s)"), "{err:#}");
+    }
+
+    #[test]
+    fn rejects_empty_frames() {
+        let err = split_capture(&[0x00]).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("received empty packet frame"),
+            "{err:#}"
+        );
+
+        // The frame is rejected even when the bytes following it would otherwise decode.
+        assert!(split_capture(&[0x00, 0x01, 0x00]).is_err());
+    }
+
+    #[test]
+    fn frames_fed_one_byte_at_a_time_are_buffered() {
+        // A body this long has a two-byte length prefix so the prefix is split between reads too.
+        let mut body = vec![0x00];
+        body.resize(300, 0xAB);
+        let mut capture = Vec::new();
+        VarUint(body.len() as u32).encode((), &mut capture);
+        capture.extend_from_slice(&body);
+
+        let mut codec = MinecraftCodec {
+            max_recv_len: HARD_MAX_PACKET_LEN_INCL,
+            compression_threshold: None,
+            is_first_frame: true,
+        };
+        let mut buf = BytesMut::new();
+        let (&last, rest) = capture.split_last().unwrap();
+
+        for &byte in rest {
+            buf.put_u8(byte);
+            assert!(codec.decode(&mut buf).unwrap().is_none());
+        }
+
+        buf.put_u8(last);
+        let Some(Frame::Packet(frame)) = codec.decode(&mut buf).unwrap() else {
+            panic!("expected the whole frame once its last byte arrived");
+        };
+        assert_eq!(frame, body);
+        assert!(buf.is_empty());
+
+        // A complete but malformed length prefix is still an error.
+        let mut negative = BytesMut::from(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F][..]);
+        assert!(codec.decode(&mut negative).is_err());
+    }
+
+    #[tokio::test]
+    async fn buffered_packets_are_sent_by_a_single_flush() {
+        let (mut server, mut client) = loopback_pair().await;
+
+        for id in 0..100 {
+            client
+                .write_buffered(sb_play::KeepAlive { id })
+                .await
+                .unwrap();
+        }
+        assert!(client.pending_write_len() > 0);
+
+        client.flush().await.unwrap();
+        assert_eq!(client.pending_write_len(), 0);
+
+        for id in 0..100 {
+            let Some(Ok(sb_play::Packet::KeepAlive(packet))) =
+                server.read_packet::<state::Play>().await
+            else {
+                panic!("expected keep-alive {id}");
+            };
+            assert_eq!(packet.id, id);
+        }
+
+        // Closing the stream sends whatever is still queued.
+        for id in 0..3 {
+            client
+                .write_buffered(sb_play::KeepAlive { id })
+                .await
+                .unwrap();
+        }
+        client.close().await.unwrap();
+
+        for _ in 0..3 {
+            server.read().await.unwrap().unwrap();
+        }
+        assert!(server.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn packets_after_set_compression_carry_a_data_length() {
+        let (server, mut raw) = tokio::io::duplex(4096);
+        let mut server = RawPeerStream::<Server, _>::new(server, HARD_MAX_PACKET_LEN_INCL);
+
+        server.enable_compression(256).await.unwrap();
+        server
+            .write(cb_login::Disconnect { reason: reason() })
+            .await
+            .unwrap();
+        let long_reason = JsonValue(ChatComponent::text("a".repeat(1000)).into());
+        server
+            .write(cb_login::Disconnect {
+                reason: long_reason,
+            })
+            .await
+            .unwrap();
+        server.close().await.unwrap();
+
+        let mut capture = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut raw, &mut capture)
+            .await
+            .unwrap();
+        let capture = Bytes::from(capture);
+        let mut cursor = ByteCursor::new(&capture);
+        fn var_uint(capture: &Bytes, cursor: &mut ByteCursor) -> u32 {
+            VarUint::decode((), capture, cursor).unwrap().0
+        }
+
+        fn next_frame<'a>(capture: &Bytes, cursor: &mut ByteCursor<'a>) -> ByteCursor<'a> {
+            let len = var_uint(capture, cursor);
+            cursor.sub_reader(len as usize).unwrap()
+        }
+
+        // `SetCompression` itself is still sent in the uncompressed format.
+        let frame = next_frame(&capture, &mut cursor);
+        assert_eq!(frame.remaining(), [0x03, 0x80, 0x02]);
+
+        // Packets below the threshold are prefixed with a data length of zero.
+        let mut frame = next_frame(&capture, &mut cursor);
+        assert_eq!(var_uint(&capture, &mut frame), 0);
+        assert_eq!(frame.remaining()[0], 0x00);
+
+        // Packets at or above it are prefixed with their uncompressed length and zlib compressed.
+        let mut frame = next_frame(&capture, &mut cursor);
+        let data_len = var_uint(&capture, &mut frame) as usize;
+        assert!(data_len >= 256);
+        assert!(frame.remaining().len() < data_len);
+
+        let mut body = Vec::new();
+        ZlibDecoder::new(frame.remaining())
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(body.len(), data_len);
+        assert_eq!(body[0], 0x00);
+
+        assert!(cursor.is_empty());
+    }
+
+    thread_local! {
+        static SERIALIZATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// JSON which counts how many times it has been serialized on the current thread.
+    #[derive(Serialize, Deserialize)]
+    struct CountedJson {
+        text: String,
+    }
+
+    impl SerializableJsonValue for CountedJson {
+        const MAX_STR_LEN: u32 = 32767;
+
+        fn write_json(&self, writer: &mut impl io::Write) -> io::Result<()> {
+            SERIALIZATIONS.with(|count| count.set(count.get() + 1));
+            serde_json::to_writer(writer, self).map_err(io::Error::from)
+        }
+    }
+
+    struct CountedPacket(JsonValue<CountedJson>);
+
+    impl Codec<()> for CountedPacket {
+        fn decode(args: (), src: &impl Snip, cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+            Ok(Self(JsonValue::decode(args, src, cursor)?))
+        }
+
+        fn encode(&self, args: (), cursor: &mut impl BufMut) {
+            self.0.encode(args, cursor);
+        }
+
+        fn validate(&self, args: ()) -> anyhow::Result<()> {
+            self.0.validate(args)
+        }
+    }
+
+    impl SizedCodec<()> for CountedPacket {
+        fn size(&self, args: ()) -> usize {
+            self.0.size(args)
+        }
+    }
+
+    impl FramedPacket for CountedPacket {
+        type Direction = Clientbound;
+    }
+
+    impl UnframedPacket for CountedPacket {
+        type Framed = Self;
+
+        fn frame(self) -> Self::Framed {
+            self
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn writes_serialize_json_without_sizing_it() {
+        let (server, mut raw) = tokio::io::duplex(4096);
+        let mut server = RawPeerStream::<Server, _>::new(server, HARD_MAX_PACKET_LEN_INCL);
+        let packet = || {
+            CountedPacket(JsonValue(CountedJson {
+                text: "a".repeat(500),
+            }))
+        };
+
+        // Once to check its length and once to encode it. Sizing it for the frame would be a third.
+        server.write(packet()).await.unwrap();
+        assert_eq!(SERIALIZATIONS.with(Cell::get), 2);
+
+        server.set_compression_threshold(Some(256));
+        server.write_buffered(packet()).await.unwrap();
+        server.close().await.unwrap();
+        assert_eq!(SERIALIZATIONS.with(Cell::get), 4);
+
+        let mut capture = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut raw, &mut capture)
+            .await
+            .unwrap();
+        let expected_len = packet().size(());
+        assert_eq!(server.metrics().bytes_written, 2 * expected_len as u64);
+        assert!(capture.len() > expected_len);
+    }
+
+    #[tokio::test]
+    async fn read_packet_rejects_trailing_bytes() {
+        let (server, mut client) = tokio::io::duplex(64);
+        let mut server = RawPeerStream::<Server, _>::new(server, HARD_MAX_PACKET_LEN_INCL);
+
+        // A status request (just its id) followed by a stray byte.
+        client.write_all(&[0x02, 0x00, 0x2A]).await.unwrap();
+
+        let err = server
+            .read_packet::<state::Status>()
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Found 1 trailing byte(s)"),
+            "{err:#}"
+        );
+    }
+
+    /// A packet whose `SizedCodec` undercounts its encoded body by a byte.
+    #[derive(Debug)]
+    struct MisSizedPacket;
+
+    impl Codec<()> for MisSizedPacket {
+        fn decode(_args: (), _src: &impl Snip, _cursor: &mut ByteCursor) -> anyhow::Result<Self> {
+            anyhow::bail!("MisSizedPacket is encode-only")
+        }
+
+        fn encode(&self, _args: (), cursor: &mut impl BufMut) {
+            cursor.put_slice(&[0x00, 0x01, 0x02]);
+        }
+
+        fn validate(&self, _args: ()) -> anyhow::Result<()> {
             Ok(())
         }
     }
+
+    impl SizedCodec<()> for MisSizedPacket {
+        fn size(&self, _args: ()) -> usize {
+            2
+        }
+    }
+
+    impl FramedPacket for MisSizedPacket {
+        type Direction = Clientbound;
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(
+        expected = "encoded to a different number of bytes than its `SizedCodec` predicted"
+    )]
+    fn mis_sized_packets_are_caught_when_encoding() {
+        let mut codec = MinecraftCodec {
+            max_recv_len: HARD_MAX_PACKET_LEN_INCL,
+            compression_threshold: None,
+            is_first_frame: false,
+        };
+
+        let _ = Encoder::encode(&mut codec, MisSizedPacket, &mut BytesMut::new());
+    }
+
+    #[test]
+    fn decode_all_leaves_partial_frames_buffered() {
+        let mut codec = MinecraftCodec {
+            max_recv_len: HARD_MAX_PACKET_LEN_INCL,
+            compression_threshold: None,
+            is_first_frame: true,
+        };
+
+        let mut buffer = BytesMut::new();
+        for payload in 1..=4 {
+            let packet = sb_status::Packet::from(sb_status::PingRequest { payload });
+            Encoder::encode(&mut codec, packet, &mut buffer).unwrap();
+        }
+
+        // Cut the fourth frame off halfway through its payload.
+        let mut rest = buffer.split_off(buffer.len() - 4);
+
+        let payloads = |frames: Vec<Frame>| {
+            frames
+                .into_iter()
+                .map(|frame| {
+                    let Frame::Packet(body) = frame else {
+                        panic!("expected a packet frame");
+                    };
+                    let sb_status::Packet::PingRequest(ping) =
+                        sb_status::Packet::decode_exact((), &body).unwrap()
+                    else {
+                        panic!("expected a ping request");
+                    };
+                    ping.payload
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(payloads(codec.decode_all(&mut buffer).unwrap()), [1, 2, 3]);
+        assert_eq!(buffer.len(), 6);
+
+        buffer.unsplit(rest.split());
+        assert_eq!(payloads(codec.decode_all(&mut buffer).unwrap()), [4]);
+        assert!(buffer.is_empty());
+        assert!(codec.decode_all(&mut buffer).unwrap().is_empty());
+    }
 }
@@ -13,3 +13,11 @@ pub fn i32_from_u32_2c(v: u32) -> i32 {
 pub fn i32_to_u32_2c(v: i32) -> u32 {
     u32::from_ne_bytes(v.to_ne_bytes())
 }
+
+pub fn i64_from_u64_2c(v: u64) -> i64 {
+    i64::from_ne_bytes(v.to_ne_bytes())
+}
+
+pub fn i64_to_u64_2c(v: i64) -> u64 {
+    u64::from_ne_bytes(v.to_ne_bytes())
+}
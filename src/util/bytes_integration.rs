@@ -80,6 +80,12 @@ impl<'a> ByteMutReadSession<'a> {
     pub fn consume_cursor(&self, cursor: &ByteCursor) {
         self.consume(cursor.pos());
     }
+
+    /// Discards any pending consumption or reservation so that dropping the session leaves the
+    /// buffer untouched, e.g. after a speculative decode fails partway.
+    pub fn rollback(&self) {
+        self.post_op.set(PostOp::Reserve(0));
+    }
 }
 
 impl Snip for ByteMutReadSession<'_> {
@@ -1,9 +1,11 @@
 use std::fmt;
 
+use anyhow::Context;
+
 use derive_where::derive_where;
 use hashbrown::HashMap;
 
-use super::{format::FmtIter, hash::HashBuilderExt};
+use super::{format::FmtIter, hash::HashBuilderExt, proto::byte_stream::ByteCursor};
 
 cfgenius::define! {
     has_debug_printing = false();
@@ -90,6 +92,28 @@ impl Interner {
         &self.buffer[offset..][..len]
     }
 
+    /// The number of distinct strings which have been interned.
+    pub fn len(&self) -> usize {
+        self.intern_entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intern_entries.is_empty()
+    }
+
+    /// Iterates over every interned string in the order in which they were first interned.
+    pub fn iter(&self) -> impl Iterator<Item = (Intern, &str)> + '_ {
+        self.intern_entries
+            .iter()
+            .enumerate()
+            .map(|(id, (offset, len))| {
+                (
+                    Intern::from_raw_id(id as u32),
+                    &self.buffer[*offset..][..*len],
+                )
+            })
+    }
+
     pub fn find_intern(&self, text: &str) -> Option<Intern> {
         let hash = self.intern_strings.hasher().hash_one(text);
 
@@ -100,6 +124,74 @@ impl Interner {
             })
             .map(|(entry, ())| entry.intern)
     }
+
+    /// Finds the first intern which matches `text` ignoring ASCII case. Unlike
+    /// [`find_intern`](Self::find_intern), this scans every intern.
+    pub fn find_intern_ascii_ci(&self, text: &str) -> Option<Intern> {
+        self.iter()
+            .find(|(_, candidate)| candidate.eq_ignore_ascii_case(text))
+            .map(|(intern, _)| intern)
+    }
+
+    /// Iterates over every intern starting with `prefix` in the order in which they were first
+    /// interned.
+    pub fn interns_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = Intern> + 'a {
+        self.iter()
+            .filter(move |(_, text)| text.starts_with(prefix))
+            .map(|(intern, _)| intern)
+    }
+
+    /// Serializes every interned string such that [`from_bytes`](Self::from_bytes) yields an
+    /// interner in which each [`Intern`] decodes to the same text.
+    ///
+    /// The format is a little-endian `u32` count followed by each string, in id order, as a
+    /// little-endian `u32` length and its UTF-8 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + self.len() * 4 + self.buffer.len());
+        out.extend_from_slice(&(self.len() as u32).to_le_bytes());
+
+        for (_, text) in self.iter() {
+            out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            out.extend_from_slice(text.as_bytes());
+        }
+
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut cursor = ByteCursor::new(bytes);
+        let read_u32 = |cursor: &mut ByteCursor| {
+            cursor
+                .read_arr()
+                .map(u32::from_le_bytes)
+                .context("unexpected end of interner data")
+        };
+
+        let count = read_u32(&mut cursor)?;
+        let mut interner = Self::new();
+
+        for id in 0..count {
+            let len = read_u32(&mut cursor)?;
+            let text = cursor
+                .read_slice(len as usize)
+                .context("unexpected end of interner data")?;
+            let text = std::str::from_utf8(text)
+                .with_context(|| format!("intern {id} is not valid UTF-8"))?;
+
+            anyhow::ensure!(
+                interner.intern(text).id() == id,
+                "intern {id} ({text:?}) is a duplicate of an earlier intern",
+            );
+        }
+
+        anyhow::ensure!(
+            cursor.is_empty(),
+            "found {} trailing byte(s) after interner data",
+            cursor.len(),
+        );
+
+        Ok(interner)
+    }
 }
 
 pub struct InternBuilder<'a> {
@@ -150,7 +242,7 @@ impl<'a> InternBuilder<'a> {
         &self.interner.buffer[self.start..]
     }
 
-    pub fn finish(self) -> Intern {
+    pub fn finish(mut self) -> Intern {
         let text = &self.interner.buffer[self.start..];
         let hash = self.interner.intern_strings.hasher().hash_one(text);
 
@@ -182,6 +274,9 @@ impl<'a> InternBuilder<'a> {
                 };
                 self.interner.intern_entries.push((offset, len));
 
+                // Keep the text around once we drop the builder.
+                self.start = self.interner.buffer.len();
+
                 entry.insert_with_hasher(
                     hash,
                     InternEntry {
@@ -211,3 +306,84 @@ impl Drop for InternBuilder<'_> {
         self.interner.buffer.truncate(self.start);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interners_round_trip_through_bytes() {
+        let mut interner = Interner::new();
+        let stone = interner.intern("minecraft:stone");
+        let empty = interner.intern("");
+        let dirt = interner.intern_iter("minecraft:dirt".chars());
+        assert_eq!(interner.intern("minecraft:stone"), stone);
+
+        let reloaded = Interner::from_bytes(&interner.to_bytes()).unwrap();
+        assert_eq!(reloaded.len(), 3);
+
+        // Ids are preserved so handles taken before saving still decode to the same text.
+        for (intern, text) in [
+            (stone, "minecraft:stone"),
+            (empty, ""),
+            (dirt, "minecraft:dirt"),
+        ] {
+            assert_eq!(reloaded.decode(intern), text);
+            assert_eq!(reloaded.find_intern(text), Some(intern));
+        }
+        assert_eq!(reloaded.find_intern("minecraft:grass"), None);
+
+        // New strings continue from the last id.
+        let mut reloaded = reloaded;
+        assert_eq!(reloaded.intern("minecraft:grass").id(), 3);
+    }
+
+    #[test]
+    fn malformed_interner_bytes_are_rejected() {
+        let mut interner = Interner::new();
+        interner.intern("a");
+        interner.intern("b");
+        let bytes = interner.to_bytes();
+
+        assert!(Interner::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Interner::from_bytes(&trailing).is_err());
+
+        // Two copies of the same string would have to share an id.
+        let mut duplicate = bytes;
+        *duplicate.last_mut().unwrap() = b'a';
+        let err = Interner::from_bytes(&duplicate).unwrap_err();
+        assert!(err.to_string().contains("duplicate"), "{err:#}");
+    }
+
+    #[test]
+    fn case_insensitive_lookup_ignores_ascii_case() {
+        let mut interner = Interner::new();
+        let stone = interner.intern("Stone");
+        interner.intern("dirt");
+
+        assert_eq!(interner.find_intern_ascii_ci("stone"), Some(stone));
+        assert_eq!(interner.find_intern_ascii_ci("STONE"), Some(stone));
+        assert_eq!(interner.find_intern("stone"), None);
+        assert_eq!(interner.find_intern_ascii_ci("ston"), None);
+    }
+
+    #[test]
+    fn prefix_lookup_yields_every_match_in_order() {
+        let mut interner = Interner::new();
+        let stone = interner.intern("minecraft:stone");
+        interner.intern("raft:stone");
+        let dirt = interner.intern("minecraft:dirt");
+
+        assert_eq!(
+            interner
+                .interns_with_prefix("minecraft:")
+                .collect::<Vec<_>>(),
+            [stone, dirt],
+        );
+        assert_eq!(interner.interns_with_prefix("").count(), 3);
+        assert_eq!(interner.interns_with_prefix("minecraft:grass").count(), 0);
+    }
+}
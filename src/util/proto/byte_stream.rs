@@ -4,7 +4,7 @@ use std::{
     str,
 };
 
-use crate::util::format::lazy_format;
+use crate::util::format::{format_closure, lazy_format};
 
 use super::{
     decode_seq::ReadCursor,
@@ -39,8 +39,19 @@ impl<'a> ByteCursor<'a> {
         self.original.len() - self.remaining.len()
     }
 
+    /// Moves the cursor to `pos`, returning `None` without moving it if `pos` is past the end of
+    /// the buffer.
+    pub fn try_set_pos(&mut self, pos: usize) -> Option<()> {
+        self.remaining = self.original.get(pos..)?;
+        Some(())
+    }
+
+    /// Moves the cursor to `pos`. Positions past the end of the buffer leave the cursor exhausted
+    /// so that a malformed summary causes later reads to fail rather than panicking here.
     pub fn set_pos(&mut self, pos: usize) {
-        self.remaining = &self.original[pos..];
+        if self.try_set_pos(pos).is_none() {
+            self.advance_remaining();
+        }
     }
 
     pub fn with_pos(self, pos: usize) -> Self {
@@ -57,8 +68,23 @@ impl<'a> ByteCursor<'a> {
         self.remaining.is_empty()
     }
 
+    /// The number of bytes left to read, spelled out for call sites where `len` would read as the
+    /// length of the whole buffer.
+    pub fn remaining_len(&self) -> usize {
+        self.remaining.len()
+    }
+
+    /// Skips `count` bytes, returning `None` without moving the cursor if fewer than that remain.
+    pub fn try_advance(&mut self, count: usize) -> Option<()> {
+        self.remaining = self.remaining.get(count..)?;
+        Some(())
+    }
+
+    /// Skips `count` bytes, saturating at the end of the buffer.
     pub fn advance(&mut self, count: usize) {
-        self.remaining = &self.remaining[count..];
+        if self.try_advance(count).is_none() {
+            self.advance_remaining();
+        }
     }
 
     pub fn advance_remaining(&mut self) {
@@ -70,20 +96,111 @@ impl<'a> ByteCursor<'a> {
     }
 
     pub fn read_slice(&mut self, count: usize) -> Option<&'a [u8]> {
-        let res = self.remaining.get(0..count)?;
+        let res = self.peek_slice(count)?;
         self.advance(count);
 
         Some(res)
     }
 
+    /// Reads the next `count` bytes as a cursor bounded to exactly that region. Positions reported
+    /// by the sub-reader remain relative to the start of this cursor's buffer.
+    pub fn sub_reader(&mut self, count: usize) -> Option<Self> {
+        let start = self.pos();
+        self.read_slice(count)?;
+
+        Some(Self {
+            original: &self.original[..start + count],
+            remaining: &self.original[start..start + count],
+        })
+    }
+
     pub fn read_arr<const N: usize>(&mut self) -> Option<[u8; N]> {
         self.read_slice(N).map(|slice| slice.try_into().unwrap())
     }
 
+    /// Like [`read_slice`](Self::read_slice) but reports the number of bytes which were available
+    /// if there weren't enough.
+    pub fn try_read_slice(&mut self, count: usize) -> Result<&'a [u8], usize> {
+        self.read_slice(count).ok_or(self.remaining.len())
+    }
+
+    /// Like [`read_arr`](Self::read_arr) but reports the number of bytes which were available if
+    /// there weren't enough.
+    pub fn try_read_arr<const N: usize>(&mut self) -> Result<[u8; N], usize> {
+        self.read_arr().ok_or(self.remaining.len())
+    }
+
+    /// Returns the next byte without advancing the cursor.
+    pub fn peek(&self) -> Option<u8> {
+        self.remaining.first().copied()
+    }
+
+    /// Returns the next `count` bytes without advancing the cursor.
+    pub fn peek_slice(&self, count: usize) -> Option<&'a [u8]> {
+        self.remaining.get(0..count)
+    }
+
+    /// Returns the next `N` bytes without advancing the cursor.
+    pub fn peek_arr<const N: usize>(&self) -> Option<[u8; N]> {
+        self.peek_slice(N).map(|slice| slice.try_into().unwrap())
+    }
+
+    /// Reads the next `expected.len()` bytes and ensures that they match `expected`, which is
+    /// useful for skipping fixed markers. The cursor is not advanced if they don't.
+    pub fn expect_bytes(&mut self, expected: &[u8]) -> anyhow::Result<()> {
+        let actual = self.remaining.get(..expected.len()).unwrap_or(self.remaining);
+
+        anyhow::ensure!(
+            actual == expected,
+            "Expected the bytes {expected:02X?} but found {actual:02X?} (location: {}).",
+            self.format_location(),
+        );
+
+        self.advance(expected.len());
+        Ok(())
+    }
+
     pub fn format_location(&self) -> impl fmt::Display {
         let read_count = self.pos();
         lazy_format!("{read_count} byte(s) from the packet frame start")
     }
+
+    /// Renders a hex dump of up to `radius` bytes on either side of the cursor with a caret under
+    /// the byte it is pointing at. The dump starts with the offset of its first byte and a cursor
+    /// at the end of the buffer points at a `..` placeholder.
+    ///
+    /// ```text
+    /// 0x0004: 0a 0b 0c 0d 0e
+    ///               ^^
+    /// ```
+    pub fn format_context(&self, radius: usize) -> impl fmt::Display + 'a {
+        let pos = self.pos();
+        let start = pos.saturating_sub(radius);
+        let end = pos
+            .saturating_add(radius.saturating_add(1))
+            .min(self.original.len());
+        let window = &self.original[start..end];
+        let at_end = pos == self.original.len();
+
+        format_closure(move |f| {
+            let prefix = format!("{start:#06x}: ");
+            f.write_str(&prefix)?;
+
+            for (i, byte) in window.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(" ")?;
+                }
+                write!(f, "{byte:02x}")?;
+            }
+
+            if at_end {
+                f.write_str(if window.is_empty() { ".." } else { " .." })?;
+            }
+
+            let indent = prefix.len() + (pos - start) * 3;
+            write!(f, "\n{:indent$}^^", "")
+        })
+    }
 }
 
 impl ReadCursor for ByteCursor<'_> {
@@ -96,6 +213,10 @@ impl ReadCursor for ByteCursor<'_> {
     fn set_pos(&mut self, pos: Self::Pos) {
         self.set_pos(pos);
     }
+
+    fn try_set_pos(&mut self, pos: Self::Pos) -> Option<()> {
+        self.try_set_pos(pos)
+    }
 }
 
 // === Write as Stream === //
@@ -122,7 +243,10 @@ pub struct AdaptWriteStream<'a, S: ?Sized>(&'a mut S);
 
 impl<S: ByteWriteStream> io::Write for AdaptWriteStream<'_, S> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.push(buf)?;
+        // N.B. `self.push` would resolve to our own `WriteStream` impl (since we implement
+        // `io::Write`) and recurse forever.
+        self.0.push(buf).map_err(io::Error::other)?;
+
         Ok(buf.len())
     }
 
@@ -153,6 +277,7 @@ pub struct WriteCodepointCounter {
     buffer: [u8; 4],
     offset: u8,
     codepoints: usize,
+    utf16_len: usize,
     bytes: usize,
 }
 
@@ -167,13 +292,17 @@ impl io::Write for WriteCodepointCounter {
         for &byte in buf {
             self.buffer[self.offset as usize] = byte;
 
-            if str::from_utf8(&self.buffer[0..self.offset as usize]).is_ok() {
-                self.offset = 0;
-                self.codepoints += 1;
-            } else {
-                self.offset += 1;
-
-                if self.offset >= 4 {
+            match str::from_utf8(&self.buffer[..=self.offset as usize]) {
+                Ok(char) => {
+                    self.offset = 0;
+                    self.codepoints += 1;
+                    self.utf16_len += char.encode_utf16().count();
+                }
+                // The sequence is incomplete but could still become valid.
+                Err(err) if err.error_len().is_none() => {
+                    self.offset += 1;
+                }
+                Err(_) => {
                     self.codepoints = usize::MAX;
                     return Ok(buf.len());
                 }
@@ -189,8 +318,18 @@ impl io::Write for WriteCodepointCounter {
 }
 
 impl WriteCodepointCounter {
+    fn is_valid(&self) -> bool {
+        self.codepoints != usize::MAX && self.offset == 0
+    }
+
     pub fn codepoints(&self) -> Option<usize> {
-        (self.codepoints != usize::MAX).then_some(self.codepoints)
+        self.is_valid().then_some(self.codepoints)
+    }
+
+    /// The length of the written string in UTF-16 code units, which is how the vanilla client
+    /// measures string length limits.
+    pub fn utf16_len(&self) -> Option<usize> {
+        self.is_valid().then_some(self.utf16_len)
     }
 
     pub fn bytes(&self) -> usize {
@@ -224,3 +363,88 @@ impl SizeMetricForElement<[u8]> for ByteSize {
         Self(elem.len())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expect_bytes_skips_matching_markers() {
+        let mut cursor = ByteCursor::new(&[0xFE, 0x01, 0xFA, 0x00]);
+
+        cursor.expect_bytes(&[0xFE, 0x01, 0xFA]).unwrap();
+        assert_eq!(cursor.pos(), 3);
+
+        let err = cursor.expect_bytes(&[0x01]).unwrap_err();
+        assert!(err.to_string().contains("(location: 3 byte(s)"), "{err:#}");
+        assert_eq!(cursor.pos(), 3);
+
+        // A marker running past the end of the buffer never matches.
+        assert!(cursor.expect_bytes(&[0x00, 0x00]).is_err());
+        assert_eq!(cursor.pos(), 3);
+    }
+
+    #[test]
+    fn peeks_match_the_following_read_without_advancing() {
+        let mut cursor = ByteCursor::new(&[0xFE, 0x01, 0xFA, 0x00]);
+        cursor.advance(1);
+
+        assert_eq!(cursor.peek(), Some(0x01));
+        assert_eq!(cursor.peek_arr::<2>(), Some([0x01, 0xFA]));
+        assert_eq!(cursor.peek_slice(3), Some(&[0x01, 0xFA, 0x00][..]));
+        assert_eq!(cursor.pos(), 1);
+        assert_eq!(cursor.remaining_len(), 3);
+
+        assert_eq!(cursor.read_arr::<2>(), Some([0x01, 0xFA]));
+        assert_eq!(cursor.remaining_len(), 1);
+
+        // Peeking past the end fails without moving the cursor.
+        assert_eq!(cursor.peek_arr::<2>(), None);
+        assert_eq!(cursor.peek(), cursor.clone().read());
+        assert_eq!(cursor.pos(), 3);
+
+        cursor.advance_remaining();
+        assert_eq!(cursor.peek(), None);
+        assert_eq!(cursor.remaining_len(), 0);
+    }
+
+    #[test]
+    fn failed_try_reads_report_the_bytes_available() {
+        let mut cursor = ByteCursor::new(&[0x00, 0x01, 0x02, 0x03, 0x04]);
+
+        assert_eq!(cursor.try_read_arr::<2>(), Ok([0x00, 0x01]));
+        assert_eq!(cursor.try_read_arr::<4>(), Err(3));
+        assert_eq!(cursor.try_read_slice(8), Err(3));
+        assert_eq!(cursor.pos(), 2);
+
+        assert_eq!(cursor.try_read_slice(3), Ok(&[0x02, 0x03, 0x04][..]));
+        assert_eq!(cursor.try_read_arr::<1>(), Err(0));
+        assert_eq!(cursor.try_read_arr::<0>(), Ok([]));
+    }
+
+    #[test]
+    fn format_context_points_at_the_current_byte() {
+        let bytes = [0x00, 0x01, 0x02, 0x03, 0x04, 0xAB, 0x06, 0x07, 0x08, 0x09];
+        let mut cursor = ByteCursor::new(&bytes);
+        cursor.advance(5);
+
+        let context = cursor.format_context(2).to_string();
+        let (hex, caret) = context.split_once('\n').unwrap();
+        assert_eq!(hex, "0x0003: 03 04 ab 06 07");
+        assert_eq!(caret.find('^'), hex.find("ab"));
+        assert_eq!(caret.trim_start(), "^^");
+
+        // The window is clipped to the buffer, even for radii which would overflow.
+        assert_eq!(
+            cursor.format_context(usize::MAX).to_string().lines().next(),
+            Some("0x0000: 00 01 02 03 04 ab 06 07 08 09"),
+        );
+
+        // Past the last byte, the caret points just beyond it.
+        cursor.advance_remaining();
+        let context = cursor.format_context(1).to_string();
+        let (hex, caret) = context.split_once('\n').unwrap();
+        assert_eq!(hex, "0x0009: 09 ..");
+        assert_eq!(caret.find('^'), hex.find(".."));
+    }
+}
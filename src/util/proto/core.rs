@@ -7,7 +7,9 @@ pub trait Codec: Sized + 'static {}
 #[doc(hidden)]
 pub mod codec_struct_internals {
     pub(crate) use super::super::{
-        decode_schema::derive_schema_decode_macro::derive_schema_decode,
+        decode_schema::derive_schema_decode_macro::{
+            derive_schema_decode, derive_schema_enum_decode,
+        },
         decode_seq::derive_seq_decode_macro::derive_seq_decode,
         encode::derive_encode_macro::derive_encode,
     };
@@ -54,6 +56,7 @@ macro_rules! seq_codec_struct {
 			}
 		}
 
+		#[allow(unused_imports)]
 		$struct_vis use $mod_name::$struct_name;
 	)*};
 }
@@ -63,7 +66,7 @@ pub(crate) use seq_codec_struct;
 macro_rules! schema_codec_struct {
     ($(
 		$(#[$attr:meta])*
-        $struct_vis:vis struct $mod_name:ident::$struct_name:ident($codec:ty) {
+        $struct_vis:vis struct $mod_name:ident::$struct_name:ident($codec:ty $(, validate = $validator:path)?) {
             $(
 				$(#[$field_attr:meta])*
 				$field_name:ident: $field_ty:ty $(=> $config_ty:ty : $config:expr)?
@@ -93,7 +96,7 @@ macro_rules! schema_codec_struct {
 
 			$crate::util::proto::core::codec_struct_internals::derive_schema_decode! {
 				$(#[$attr])*
-				$struct_vis struct $struct_name($codec) {
+				$struct_vis struct $struct_name($codec $(, validate = $validator)?) {
 					$(
 						$(#[$field_attr])*
 						$field_name: $field_ty $(=> $config_ty : $config)?
@@ -107,3 +110,46 @@ macro_rules! schema_codec_struct {
 }
 
 pub(crate) use schema_codec_struct;
+
+/// Like [`schema_codec_struct!`] but for an object whose shape depends on the value of a string
+/// field. The object is decoded as the variant whose tag matches that field, with the variant's
+/// type seeing the entire object (including the tag itself).
+///
+/// Both macros accept a `validate = path` after the codec naming a function which takes the raw
+/// view and checks constraints spanning several fields. It runs at the end of `validate_deep`.
+macro_rules! schema_codec_enum {
+    ($(
+		$(#[$attr:meta])*
+        $enum_vis:vis enum $mod_name:ident::$enum_name:ident($codec:ty $(, validate = $validator:path)?) by $tag_field:literal {
+            $(
+				$(#[$variant_attr:meta])*
+				$variant_name:ident($variant_ty:ty) = $tag:literal
+			),*
+            $(,)?
+        }
+	)*) => {$(
+		$enum_vis mod $mod_name {
+			#[allow(unused_imports)]
+			use super::*;
+
+			$(#[$attr])*
+			pub enum $enum_name {
+				$($(#[$variant_attr])* $variant_name($variant_ty),)*
+			}
+
+			$crate::util::proto::core::codec_struct_internals::derive_schema_enum_decode! {
+				$(#[$attr])*
+				$enum_vis enum $enum_name($codec $(, validate = $validator)?) by $tag_field {
+					$(
+						$(#[$variant_attr])*
+						$variant_name($variant_ty) = $tag
+					),*
+				}
+			}
+		}
+
+		$enum_vis use $mod_name::$enum_name;
+	)*};
+}
+
+pub(crate) use schema_codec_enum;
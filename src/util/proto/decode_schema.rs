@@ -23,6 +23,16 @@ pub trait SchemaDocument: Sized + 'static {
 
 // === Deserialize Traits === //
 
+/// The maximum nesting depth [`SchemaView::validate_deep`] will tolerate before bailing. This
+/// prevents adversarially nested documents from overflowing the stack during validation.
+pub const DEFAULT_MAX_VALIDATION_DEPTH: u32 = 64;
+
+pub fn descend_validation_depth(remaining_depth: u32) -> anyhow::Result<u32> {
+    remaining_depth.checked_sub(1).ok_or_else(|| {
+        anyhow::anyhow!("Exceeded the maximum nesting depth while validating the document.")
+    })
+}
+
 pub trait DeserializeSchema<C: SchemaDecodeCodec, A>: Sized + 'static {
     type Shortcut: 'static + fmt::Debug + Clone;
     type View<'a>: SchemaView<
@@ -75,7 +85,16 @@ pub trait SchemaView<C: SchemaDecodeCodec, A>: fmt::Debug + Clone {
 
     fn assume_valid(self) -> Self::Validated;
 
-    fn validate_deep(&self) -> anyhow::Result<()>;
+    /// Validates the entire object tree, bailing if it is nested more than
+    /// [`DEFAULT_MAX_VALIDATION_DEPTH`] levels deep.
+    fn validate_deep(&self) -> anyhow::Result<()> {
+        self.validate_deep_bounded(DEFAULT_MAX_VALIDATION_DEPTH)
+    }
+
+    /// Validates the entire object tree, bailing if it is nested more than `remaining_depth`
+    /// levels deep. Containers should decrement the depth using [`descend_validation_depth`]
+    /// before validating their children.
+    fn validate_deep_bounded(&self, remaining_depth: u32) -> anyhow::Result<()>;
 
     fn as_shortcut(&self) -> Self::Shortcut;
 
@@ -106,17 +125,18 @@ pub trait ValidatedSchemaView<C: SchemaDecodeCodec, A>: fmt::Debug + Clone {
 pub mod derive_schema_decode_internals {
     pub use {
         super::{
-            DeserializeSchema, SchemaDecodeCodec, SchemaDocument, SchemaView, ValidatedSchemaView,
+            descend_validation_depth, DeserializeSchema, SchemaDecodeCodec, SchemaDocument,
+            SchemaView, ValidatedSchemaView,
         },
         anyhow,
-        std::{concat, fmt, option::Option, stringify},
+        std::{clone::Clone, concat, fmt, option::Option, string::String, stringify},
     };
 }
 
 macro_rules! derive_schema_decode {
     (
         $(#[$attr:meta])*
-        $struct_vis:vis struct $struct_name:ident($codec:ty) {
+        $struct_vis:vis struct $struct_name:ident($codec:ty $(, validate = $validator:path)?) {
             $(
 				$(#[$field_attr:meta])*
 				$field_name:ident: $field_ty:ty $(=> $config_ty:ty : $config:expr)?
@@ -171,10 +191,15 @@ macro_rules! derive_schema_decode {
 				ValidatedView(self)
 			}
 
-			fn validate_deep(&self) -> $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result<()> {
-				$($crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaView::<$codec, ($($config_ty)?)>::validate_deep(
+			fn validate_deep_bounded(&self, remaining_depth: u32) -> $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result<()> {
+				let remaining_depth = $crate::util::proto::decode_schema::derive_schema_decode_internals::descend_validation_depth(remaining_depth)?;
+				let _ = remaining_depth;
+
+				$($crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaView::<$codec, ($($config_ty)?)>::validate_deep_bounded(
 					&self.$field_name()?,
+					remaining_depth,
 				)?;)*
+				$($validator(self)?;)?
 				$crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result::Ok(())
 			}
 
@@ -223,6 +248,35 @@ macro_rules! derive_schema_decode {
 					res
 				}
 			)*
+
+			/// Fetches the raw entry for a field by its runtime name. If the name corresponds to
+			/// a field declared by the schema, that field's view is constructed to ensure that it
+			/// matches the expected type. Other schema fields are left untouched.
+			pub fn get_dynamic(&self, name: &str) -> $crate::util::proto::decode_schema::derive_schema_decode_internals::Option<
+				$crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result<
+					<$codec as $crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaDecodeCodec>::AnyRef
+				>
+			> {
+				match name {
+					$(
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::stringify!($field_name) => {
+							if let $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result::Err(err) = self.$field_name() {
+								return $crate::util::proto::decode_schema::derive_schema_decode_internals::Option::Some(
+									$crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result::Err(err),
+								);
+							}
+						}
+					)*
+					_ => {}
+				}
+
+				$crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaDocument::object_entry(
+					self.document,
+					&self.shortcut,
+					name,
+				)
+				.map($crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result::Ok)
+			}
 		}
 
 		impl $crate::util::proto::decode_schema::derive_schema_decode_internals::fmt::Debug for View<'_> {
@@ -267,6 +321,172 @@ macro_rules! derive_schema_decode {
     };
 }
 
+macro_rules! derive_schema_enum_decode {
+    (
+        $(#[$attr:meta])*
+        $enum_vis:vis enum $enum_name:ident($codec:ty $(, validate = $validator:path)?) by $tag_field:literal {
+            $(
+				$(#[$variant_attr:meta])*
+				$variant_name:ident($variant_ty:ty) = $tag:literal
+			),*
+            $(,)?
+        }
+    ) => {
+		#[derive(Debug, Clone)]
+		pub enum Shortcut {
+			$($variant_name(<$variant_ty as $crate::util::proto::decode_schema::derive_schema_decode_internals::DeserializeSchema<$codec, ()>>::Shortcut),)*
+		}
+
+		#[derive(Debug, Clone)]
+		pub enum View<'a> {
+			$($variant_name(<$variant_ty as $crate::util::proto::decode_schema::derive_schema_decode_internals::DeserializeSchema<$codec, ()>>::View<'a>),)*
+		}
+
+		#[derive(Debug, Clone)]
+		pub enum ValidatedView<'a> {
+			$($variant_name(<$variant_ty as $crate::util::proto::decode_schema::derive_schema_decode_internals::DeserializeSchema<$codec, ()>>::ValidatedView<'a>),)*
+		}
+
+		impl $crate::util::proto::decode_schema::derive_schema_decode_internals::DeserializeSchema<$codec, ()> for $enum_name {
+			type Shortcut = Shortcut;
+			type View<'a> = View<'a>;
+			type ValidatedView<'a> = ValidatedView<'a>;
+
+			fn make_shortcut(
+				document: &<$codec as $crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaDecodeCodec>::Document,
+				object: $crate::util::proto::decode_schema::derive_schema_decode_internals::Option<<$codec as $crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaDecodeCodec>::AnyRef>,
+			) -> $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result<Self::Shortcut> {
+				let $crate::util::proto::decode_schema::derive_schema_decode_internals::Option::Some(any_ref) = $crate::util::proto::decode_schema::derive_schema_decode_internals::Clone::clone(&object) else {
+					$crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::bail!("Expected an object, got an unassigned key.");
+				};
+
+				let tag_object = $crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaDocument::any_ref_as_object(document, any_ref)
+					.map_err(|value| $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::anyhow!("Expected an object, got {value:?}"))?;
+
+				let tag = <$crate::util::proto::decode_schema::derive_schema_decode_internals::String as $crate::util::proto::decode_schema::derive_schema_decode_internals::DeserializeSchema<$codec, ()>>::view_object(
+					document,
+					$crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaDocument::object_entry(
+						document,
+						&tag_object,
+						$tag_field,
+					),
+					(),
+				);
+
+				let tag = $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Context::context(
+					tag.and_then(|tag| $crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaView::<$codec, ()>::try_reify(&tag)),
+					$crate::util::proto::decode_schema::derive_schema_decode_internals::concat!(
+						"Failed to access the `",
+						$tag_field,
+						"` discriminator of ",
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::stringify!($enum_name),
+						".",
+					),
+				)?;
+
+				match tag.as_str() {
+					$(
+						$tag => $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result::Ok(Shortcut::$variant_name(
+							<$variant_ty as $crate::util::proto::decode_schema::derive_schema_decode_internals::DeserializeSchema<$codec, ()>>::make_shortcut(document, object)?,
+						)),
+					)*
+					_ => $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::bail!(
+						"Unknown {} `{}` {tag:?}.",
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::stringify!($enum_name),
+						$tag_field,
+					),
+				}
+			}
+
+			fn view_shortcut<'a>(
+				document: &'a <$codec as $crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaDecodeCodec>::Document,
+				shortcut: Self::Shortcut,
+				_args: (),
+			) -> Self::View<'a> {
+				match shortcut {
+					$(
+						Shortcut::$variant_name(shortcut) => View::$variant_name(
+							<$variant_ty as $crate::util::proto::decode_schema::derive_schema_decode_internals::DeserializeSchema<$codec, ()>>::view_shortcut(document, shortcut, ()),
+						),
+					)*
+				}
+			}
+		}
+
+		impl<'a> $crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaView<$codec, ()> for View<'a> {
+			type Reified = $enum_name;
+			type Shortcut = Shortcut;
+			type Validated = ValidatedView<'a>;
+
+			fn assume_valid(self) -> Self::Validated {
+				match self {
+					$(Self::$variant_name(view) => ValidatedView::$variant_name(
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaView::<$codec, ()>::assume_valid(view),
+					),)*
+				}
+			}
+
+			fn validate_deep_bounded(&self, remaining_depth: u32) -> $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result<()> {
+				// The enum shares its object with the variant so it doesn't count as a level of
+				// nesting of its own.
+				match self {
+					$(Self::$variant_name(view) => {
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaView::<$codec, ()>::validate_deep_bounded(view, remaining_depth)?
+					})*
+				}
+				$($validator(self)?;)?
+				$crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result::Ok(())
+			}
+
+			fn as_shortcut(&self) -> Self::Shortcut {
+				match self {
+					$(Self::$variant_name(view) => Shortcut::$variant_name(
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaView::<$codec, ()>::as_shortcut(view),
+					),)*
+				}
+			}
+
+			fn try_reify(&self) -> $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result<Self::Reified> {
+				match self {
+					$(Self::$variant_name(view) => $crate::util::proto::decode_schema::derive_schema_decode_internals::anyhow::Result::Ok($enum_name::$variant_name(
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::SchemaView::<$codec, ()>::try_reify(view)?,
+					)),)*
+				}
+			}
+		}
+
+		impl<'a> $crate::util::proto::decode_schema::derive_schema_decode_internals::ValidatedSchemaView<$codec, ()> for ValidatedView<'a> {
+			type Reified = $enum_name;
+			type Shortcut = Shortcut;
+			type RawView = View<'a>;
+
+			fn unwrap_validation(self) -> Self::RawView {
+				match self {
+					$(Self::$variant_name(view) => View::$variant_name(
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::ValidatedSchemaView::<$codec, ()>::unwrap_validation(view),
+					),)*
+				}
+			}
+
+			fn as_shortcut_validated(&self) -> Self::Shortcut {
+				match self {
+					$(Self::$variant_name(view) => Shortcut::$variant_name(
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::ValidatedSchemaView::<$codec, ()>::as_shortcut_validated(view),
+					),)*
+				}
+			}
+
+			fn reify(&self) -> Self::Reified {
+				match self {
+					$(Self::$variant_name(view) => $enum_name::$variant_name(
+						$crate::util::proto::decode_schema::derive_schema_decode_internals::ValidatedSchemaView::<$codec, ()>::reify(view),
+					),)*
+				}
+			}
+		}
+    };
+}
+
 pub(super) mod derive_schema_decode_macro {
-    pub(crate) use derive_schema_decode;
+    pub(crate) use {derive_schema_decode, derive_schema_enum_decode};
 }
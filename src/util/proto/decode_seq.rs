@@ -1,5 +1,7 @@
 use std::{fmt, marker::PhantomData};
 
+use anyhow::Context;
+
 use super::core::Codec;
 
 // === Codec === //
@@ -17,6 +19,18 @@ pub trait ReadCursor: Sized + Clone {
     fn pos(&self) -> Self::Pos;
 
     fn set_pos(&mut self, pos: Self::Pos);
+
+    /// Moves the cursor to `pos`, returning `None` without moving it if `pos` is out of bounds.
+    fn try_set_pos(&mut self, pos: Self::Pos) -> Option<()>;
+
+    /// Moves the cursor to a position recorded in a summary. Summaries are normally produced from
+    /// the same buffer but `skip` accepts any summary so this reports positions which don't fit
+    /// rather than trusting them.
+    fn set_summary_pos(&mut self, pos: Self::Pos) -> anyhow::Result<()> {
+        self.try_set_pos(pos).with_context(|| {
+            format!("Summary position {pos:?} is past the end of the buffer being skipped.")
+        })
+    }
 }
 
 pub trait ReadPos: Sized + 'static + fmt::Debug + Copy + Eq {}
@@ -130,10 +144,10 @@ pub trait DecodeSeqExt<C: SeqDecodeCodec, A>: DecodeSeq<C, A> {
     /// of the method call.
     fn skip(
         summary: &Self::Summary,
-        skip_to_start: impl Fn(&mut C::Reader<'_>),
+        skip_to_start: impl Fn(&mut C::Reader<'_>) -> anyhow::Result<()>,
         cursor: &mut C::Reader<'_>,
         args: &mut A,
-    );
+    ) -> anyhow::Result<()>;
 
     /// Returns a summary bound to the target cursor.
     fn summarize_bound<'s>(
@@ -143,6 +157,15 @@ pub trait DecodeSeqExt<C: SeqDecodeCodec, A>: DecodeSeq<C, A> {
 
     /// Decodes the reified version of this value in a single step.
     fn decode<'a>(cursor: &'a mut C::Reader<'_>, args: A) -> anyhow::Result<Self>;
+
+    /// Decodes the reified version of this value in a single step, consuming the cursor instead of
+    /// forking it.
+    ///
+    /// [`decode`](DecodeSeqExt::decode) forks the cursor before summarizing so that it can view the
+    /// value from its start while leaving the caller's cursor at its end. This method instead
+    /// rewinds the cursor it summarized with and hands it to the view, which saves the fork at the
+    /// cost of the caller having to resume reading from the returned end position.
+    fn decode_in_place(cursor: C::Reader<'_>, args: A) -> anyhow::Result<(Self, C::ReaderPos)>;
 }
 
 pub trait SeqDecoderFull<T: 'static, C: SeqDecodeCodec, A>: Sized {
@@ -162,10 +185,10 @@ pub trait SeqDecoderFull<T: 'static, C: SeqDecodeCodec, A>: Sized {
 
     fn skip(
         summary: &Self::Summary,
-        skip_to_start: impl Fn(&mut C::Reader<'_>),
+        skip_to_start: impl Fn(&mut C::Reader<'_>) -> anyhow::Result<()>,
         cursor: &mut C::Reader<'_>,
         args: &mut A,
-    );
+    ) -> anyhow::Result<()>;
 }
 
 impl<T, C, A, D> DecodeSeqExt<C, A> for T
@@ -198,10 +221,10 @@ where
 
     fn skip(
         summary: &Self::Summary,
-        skip_to_start: impl Fn(&mut <C as SeqDecodeCodec>::Reader<'_>),
+        skip_to_start: impl Fn(&mut <C as SeqDecodeCodec>::Reader<'_>) -> anyhow::Result<()>,
         cursor: &mut <C as SeqDecodeCodec>::Reader<'_>,
         args: &mut A,
-    ) {
+    ) -> anyhow::Result<()> {
         D::skip(summary, skip_to_start, cursor, args)
     }
 
@@ -221,6 +244,21 @@ where
         Self::summarize_bound(cursor, &mut args)
             .map(|summary| Self::reify_view(&summary.view(args)))
     }
+
+    fn decode_in_place(
+        mut cursor: C::Reader<'_>,
+        mut args: A,
+    ) -> anyhow::Result<(Self, C::ReaderPos)> {
+        let start = cursor.pos();
+        let summary = Self::summarize(&mut cursor, &mut args)?;
+        let end = cursor.pos();
+        cursor.set_pos(start);
+
+        // Safety: we just generated this summary with this very cursor.
+        let view = unsafe { Self::view(&summary, C::covariant_cast(cursor), args) };
+
+        Ok((Self::reify_view(&view), end))
+    }
 }
 
 // === SeqDecoderSimple === //
@@ -319,10 +357,11 @@ pub trait SimpleSummary<C: SeqDecodeCodec>: ReadPos {
 
     fn skip_to_end<W, T: 'static, A>(
         self,
-        skip_to_start: impl Fn(&mut C::Reader<'_>),
+        skip_to_start: impl Fn(&mut C::Reader<'_>) -> anyhow::Result<()>,
         cursor: &mut C::Reader<'_>,
         args: &mut A,
-    ) where
+    ) -> anyhow::Result<()>
+    where
         W: SeqDecoderSimple<T, C, A>;
 }
 
@@ -333,14 +372,16 @@ impl<C: SeqDecodeCodec> SimpleSummary<C> for () {
 
     fn skip_to_end<W, T: 'static, A>(
         self,
-        skip_to_start: impl Fn(&mut C::Reader<'_>),
+        skip_to_start: impl Fn(&mut C::Reader<'_>) -> anyhow::Result<()>,
         cursor: &mut C::Reader<'_>,
         args: &mut A,
-    ) where
+    ) -> anyhow::Result<()>
+    where
         W: SeqDecoderSimple<T, C, A>,
     {
-        skip_to_start(cursor);
-        let _ = W::decode([], cursor, args);
+        skip_to_start(cursor)?;
+        W::decode([], cursor, args)?;
+        Ok(())
     }
 }
 
@@ -354,13 +395,14 @@ impl<C: SeqDecodeCodec> SimpleSummary<C> for EndPosSummary<C::ReaderPos> {
 
     fn skip_to_end<W, T: 'static, A>(
         self,
-        _skip_to_start: impl Fn(&mut C::Reader<'_>),
+        _skip_to_start: impl Fn(&mut C::Reader<'_>) -> anyhow::Result<()>,
         cursor: &mut C::Reader<'_>,
         _args: &mut A,
-    ) where
+    ) -> anyhow::Result<()>
+    where
         W: SeqDecoderSimple<T, C, A>,
     {
-        cursor.set_pos(self.0);
+        cursor.set_summary_pos(self.0)
     }
 }
 
@@ -399,10 +441,10 @@ where
 
     fn skip(
         summary: &Self::Summary,
-        skip_to_start: impl Fn(&mut <C as SeqDecodeCodec>::Reader<'_>),
+        skip_to_start: impl Fn(&mut <C as SeqDecodeCodec>::Reader<'_>) -> anyhow::Result<()>,
         cursor: &mut <C as SeqDecodeCodec>::Reader<'_>,
         args: &mut A,
-    ) {
+    ) -> anyhow::Result<()> {
         summary.skip_to_end::<W, T, A>(skip_to_start, cursor, args)
     }
 }
@@ -451,10 +493,10 @@ where
 
     fn skip(
         summary: &Self::Summary,
-        skip_to_start: impl Fn(&mut <C as SeqDecodeCodec>::Reader<'_>),
+        skip_to_start: impl Fn(&mut <C as SeqDecodeCodec>::Reader<'_>) -> anyhow::Result<()>,
         cursor: &mut <C as SeqDecodeCodec>::Reader<'_>,
         args: &mut AIn,
-    ) {
+    ) -> anyhow::Result<()> {
         W::map_borrowed_args(args, |args| T::skip(summary, skip_to_start, cursor, args))
     }
 }
@@ -464,10 +506,13 @@ where
 #[doc(hidden)]
 pub mod derive_seq_decode_internals {
     pub use {
-        super::{DecodeSeq, DecodeSeqExt, ReadCursor, SeqDecodeCodec, SeqDecoderFull},
+        super::{DecodeSeq, DecodeSeqExt, SeqDecodeCodec, SeqDecoderFull},
         anyhow,
-        std::{clone::Clone, convert::identity, fmt, ops::Fn, result::Result::Ok, stringify},
+        std::{clone::Clone, fmt, ops::Fn, result::Result::Ok, stringify},
     };
+
+    #[cfg(feature = "trace-decode")]
+    pub use {super::ReadCursor, log::trace};
 }
 
 macro_rules! derive_seq_decode {
@@ -483,6 +528,7 @@ macro_rules! derive_seq_decode {
     ) => {
 		// Structure definitions
 		#[derive(Debug, Clone)]
+		#[allow(unused_parens)]
 		pub struct Summary {
 			$($field_name: <$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt<$codec, ($($config_ty)?)>>::Summary,)*
 		}
@@ -497,11 +543,12 @@ macro_rules! derive_seq_decode {
 		pub struct Decoder { _never: () }
 
 		// Deserialization
-		impl $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeq<$codec, ()> for Decoder {
+		impl $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeq<$codec, ()> for $struct_name {
 			type Decoder = Decoder;
 		}
 
-		impl $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecoderFull<$codec, ()> for Decoder {
+		#[allow(unused_parens)]
+		impl $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecoderFull<$struct_name, $codec, ()> for Decoder {
 			type Summary = Summary;
 			type View<'a> = View<'a>;
 
@@ -509,7 +556,7 @@ macro_rules! derive_seq_decode {
 				let _ = view;
 
 				$struct_name {
-					$($field_name: $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt::<$codec, ($($config_ty)?)>::reify_view(&view.$field_name()),)*
+					$($field_name: <$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt<$codec, ($($config_ty)?)>>::reify_view(&view.$field_name()),)*
 				}
 			}
 
@@ -519,19 +566,34 @@ macro_rules! derive_seq_decode {
 			) -> $crate::util::proto::decode_seq::derive_seq_decode_internals::anyhow::Result<Self::Summary> {
 				let _ = &cursor;
 
-				$crate::util::proto::decode_seq::derive_seq_decode_internals::Ok(Summary {$(
+				$(
+					#[cfg(feature = "trace-decode")]
+					let start_pos = $crate::util::proto::decode_seq::derive_seq_decode_internals::ReadCursor::pos(cursor);
+
 					#[allow(unused_parens)]
-					$field_name: <$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt::<$codec, ($($config_ty)?)>>::summarize(
+					let $field_name = <$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt::<$codec, ($($config_ty)?)>>::summarize(
 						cursor,
 						&mut {$($config)?},
-					)?,
-				)*})
+					)?;
+
+					#[cfg(feature = "trace-decode")]
+					$crate::util::proto::decode_seq::derive_seq_decode_internals::trace!(
+						"\tSummarized {}::{}: {:?} (range: {:?}..{:?})",
+						$crate::util::proto::decode_seq::derive_seq_decode_internals::stringify!($struct_name),
+						$crate::util::proto::decode_seq::derive_seq_decode_internals::stringify!($field_name),
+						$field_name,
+						start_pos,
+						$crate::util::proto::decode_seq::derive_seq_decode_internals::ReadCursor::pos(cursor),
+					);
+				)*
+
+				$crate::util::proto::decode_seq::derive_seq_decode_internals::Ok(Summary {$($field_name,)*})
 			}
 
 			unsafe fn view<'a>(
 				summary: &'a Self::Summary,
 				cursor: <$codec as $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecodeCodec>::Reader<'a>,
-				_args: &mut (),
+				_args: (),
 			) -> Self::View<'a> {
 				// Safety: the caller guarantees that the summary was generated using this cursor's
 				// backing buffer. Because every sub-summary created by `summarize` was also
@@ -544,25 +606,25 @@ macro_rules! derive_seq_decode {
 				summary: &Self::Summary,
 				skip_to_start: impl $crate::util::proto::decode_seq::derive_seq_decode_internals::Fn(
 					&mut <$codec as $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecodeCodec>::Reader<'_>,
-				),
+				) -> $crate::util::proto::decode_seq::derive_seq_decode_internals::anyhow::Result<()>,
 				cursor: &mut <$codec as $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecodeCodec>::Reader<'_>,
 				_args: &mut (),
-			) {
+			) -> $crate::util::proto::decode_seq::derive_seq_decode_internals::anyhow::Result<()> {
 				let _ = (summary, &cursor);
 
 				$(
 					#[allow(unused_parens)]
 					let skip_to_start = |cursor: &mut <$codec as $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecodeCodec>::Reader<'_>| {
-						<$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DeserializeSeq<$codec, ($($config_ty)?)>>::skip(
+						<$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt<$codec, ($($config_ty)?)>>::skip(
 							&summary.$field_name,
 							&skip_to_start,
 							cursor,
 							&mut {$($config)?},
-						);
+						)
 					};
 				)*
 
-				skip_to_start(cursor);
+				skip_to_start(cursor)
 			}
 		}
 
@@ -573,30 +635,32 @@ macro_rules! derive_seq_decode {
 
 			#[allow(unused_macros)]
 			macro_rules! prev_func_call {
-				($cursor:expr, $summary:expr) => {
+				($cursor:expr, $summary:expr) => {{
 					let _ = $cursor;
-				};
+					$crate::util::proto::decode_seq::derive_seq_decode_internals::Ok(())
+				}};
 			}
 
 			$(
+				#[allow(unused_parens)]
 				pub fn $field_name(
 					cursor: &mut <$codec as $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecodeCodec>::Reader<'_>,
 					summary: &Summary,
-				) {
+				) -> $crate::util::proto::decode_seq::derive_seq_decode_internals::anyhow::Result<()> {
 					<$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt<$codec, ($($config_ty)?)>>::skip(
 						&summary.$field_name,
 						|cursor: &mut <$codec as $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecodeCodec>::Reader<'_>| {
-							prev_func_call!(cursor, summary);
+							prev_func_call!(cursor, summary)
 						},
 						cursor,
 						&mut {$($config)?},
-					);
+					)
 				}
 
 				#[allow(unused_macros)]
 				macro_rules! prev_func_call {
 					($cursor:expr, $summary:expr) => {
-						$field_name($cursor, $summary);
+						$field_name($cursor, $summary)
 					};
 				}
 			)*
@@ -612,8 +676,9 @@ macro_rules! derive_seq_decode {
 					pub fn $export_name(
 						_cursor: &mut <$codec as $crate::util::proto::decode_seq::derive_seq_decode_internals::SeqDecodeCodec>::Reader<'_>,
 						_summary: &Summary,
-					) {
+					) -> $crate::util::proto::decode_seq::derive_seq_decode_internals::anyhow::Result<()> {
 						// (nothing to do for the first field)
+						$crate::util::proto::decode_seq::derive_seq_decode_internals::Ok(())
 					}
 				};
 			}
@@ -630,15 +695,19 @@ macro_rules! derive_seq_decode {
 			)*
 		}
 
+		#[allow(unused_parens)]
 		impl<'a> View<'a> {
 			$(
 				pub fn $field_name(&self) -> <$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt<$codec, ($($config_ty)?)>>::View<'a> {
 					// Align the cursor to the appropriate location.
+					// By invariant, the summary was generated from this cursor's buffer so skipping the
+					// fields before this one cannot fail.
 					let mut cursor = $crate::util::proto::decode_seq::derive_seq_decode_internals::Clone::clone(&self.cursor);
-					__skip_to::$field_name(&mut cursor, &self.summary);
+					__skip_to::$field_name(&mut cursor, &self.summary)
+						.expect("a view's own summary should always be skippable");
 
 					// Compute the config outside of the `unsafe` block.
-					let mut config = {$($config)?};
+					let config = {$($config)?};
 
 					unsafe {
 						// Safety: by invariant, we know the summary, its sub-element summaries, and
@@ -649,7 +718,7 @@ macro_rules! derive_seq_decode {
 						<$field_ty as $crate::util::proto::decode_seq::derive_seq_decode_internals::DecodeSeqExt<$codec, ($($config_ty)?)>>::view(
 							&self.summary.$field_name,
 							cursor,
-							&mut config,
+							config,
 						)
 					}
 				}
@@ -1,4 +1,4 @@
-use std::{error::Error, ops::AddAssign};
+use std::{error::Error, hash::Hasher, ops::AddAssign};
 
 use crate::util::error::NeverError;
 
@@ -59,6 +59,29 @@ where
     }
 }
 
+// HashingWriteStream
+/// A byte stream which feeds everything written to it into a [`Hasher`], allowing the digest of an
+/// encoded value to be computed in a single pass without buffering its encoding. Writes reach the
+/// hasher in the same chunks as they were pushed so the hasher must be insensitive to how its input
+/// is split for the digest to match that of the concatenated bytes.
+#[derive(Debug, Clone, Default)]
+pub struct HashingWriteStream<H>(pub H);
+
+impl<H: Hasher> HashingWriteStream<H> {
+    pub fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+}
+
+impl<H: Hasher> WriteStream<[u8]> for HashingWriteStream<H> {
+    type PushError = NeverError;
+
+    fn push(&mut self, elem: &[u8]) -> Result<(), Self::PushError> {
+        self.0.write(elem);
+        Ok(())
+    }
+}
+
 // === Serialization traits === //
 
 pub trait SerializeInto<C: EncodeCodec, T, A>: Sized {
@@ -109,7 +132,6 @@ where
 pub mod derive_encode_internals {
     pub use {
         super::{EncodeCodec, SerializeInto, WriteStream},
-        crate::util::proto::core::Codec,
         anyhow,
         std::{default::Default, result::Result::Ok},
     };
@@ -174,7 +196,92 @@ macro_rules! derive_encode {
 			}
 		}
 
-		// TODO: Ensure that reified form can also serialize.
+		// Allow the reified form to be serialized directly so that decoded values can be modified
+		// and re-encoded without going through a `Builder`. This requires every field's owned type
+		// to be serializable as itself.
+		#[allow(unused_parens)]
+		impl $crate::util::proto::encode::derive_encode_internals::SerializeInto<$codec, $struct_name, ()> for $struct_name {
+			fn serialize(
+				&mut self,
+				stream: &mut impl for<'a>
+					$crate::util::proto::encode::derive_encode_internals::WriteStream<
+						<$codec as $crate::util::proto::encode::derive_encode_internals::EncodeCodec>::WriteElement<'a>>,
+				_args: &mut (),
+			) -> $crate::util::proto::encode::derive_encode_internals::anyhow::Result<()> {
+				let _ = &stream;
+
+				$(
+					$crate::util::proto::encode::derive_encode_internals::SerializeInto::<$codec, $field_ty, ($($config_ty)?)>::serialize(
+						&mut self.$field_name,
+						stream,
+						&mut {$($config)?},
+					)?;
+				)*
+
+				$crate::util::proto::encode::derive_encode_internals::Ok(())
+			}
+
+			fn size(&mut self, _args: &mut ()) -> $crate::util::proto::encode::derive_encode_internals::anyhow::Result<
+				<$codec as $crate::util::proto::encode::derive_encode_internals::EncodeCodec>::SizeMetric,
+			> {
+				let counter: <$codec as $crate::util::proto::encode::derive_encode_internals::EncodeCodec>::SizeMetric =
+					$crate::util::proto::encode::derive_encode_internals::Default::default();
+
+				$(
+					let counter = counter + $crate::util::proto::encode::derive_encode_internals::SerializeInto::<$codec, $field_ty, ($($config_ty)?)>::size(
+						&mut self.$field_name,
+						&mut {$($config)?},
+					)?;
+				)*
+
+				$crate::util::proto::encode::derive_encode_internals::Ok(counter)
+			}
+		}
+
+		// Allow a borrowed value to be serialized so that callers holding a fully-owned packet can
+		// send it without moving it or reconstructing a `Builder`. This requires every field to be
+		// serializable through a shared reference.
+		#[allow(unused_parens)]
+		impl $crate::util::proto::encode::derive_encode_internals::SerializeInto<$codec, $struct_name, ()> for &'_ $struct_name
+		where
+			$(for<'r> &'r $field_ty: $crate::util::proto::encode::derive_encode_internals::SerializeInto<$codec, $field_ty, ($($config_ty)?)>,)*
+		{
+			fn serialize(
+				&mut self,
+				stream: &mut impl for<'a>
+					$crate::util::proto::encode::derive_encode_internals::WriteStream<
+						<$codec as $crate::util::proto::encode::derive_encode_internals::EncodeCodec>::WriteElement<'a>>,
+				_args: &mut (),
+			) -> $crate::util::proto::encode::derive_encode_internals::anyhow::Result<()> {
+				let _ = &stream;
+
+				$(
+					$crate::util::proto::encode::derive_encode_internals::SerializeInto::<$codec, $field_ty, ($($config_ty)?)>::serialize(
+						&mut &self.$field_name,
+						stream,
+						&mut {$($config)?},
+					)?;
+				)*
+
+				$crate::util::proto::encode::derive_encode_internals::Ok(())
+			}
+
+			fn size(&mut self, _args: &mut ()) -> $crate::util::proto::encode::derive_encode_internals::anyhow::Result<
+				<$codec as $crate::util::proto::encode::derive_encode_internals::EncodeCodec>::SizeMetric,
+			> {
+				let counter: <$codec as $crate::util::proto::encode::derive_encode_internals::EncodeCodec>::SizeMetric =
+					$crate::util::proto::encode::derive_encode_internals::Default::default();
+
+				$(
+					let counter = counter + $crate::util::proto::encode::derive_encode_internals::SerializeInto::<$codec, $field_ty, ($($config_ty)?)>::size(
+						&mut &self.$field_name,
+						&mut {$($config)?},
+					)?;
+				)*
+
+				$crate::util::proto::encode::derive_encode_internals::Ok(counter)
+			}
+		}
 	};
 }
 
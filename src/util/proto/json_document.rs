@@ -1,4 +1,8 @@
-use std::{fmt, marker::PhantomData, ops::Deref};
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, Range},
+};
 
 use derive_where::derive_where;
 use either::Either;
@@ -10,7 +14,8 @@ use crate::util::interner::{Intern, Interner};
 use super::{
     core::Codec,
     decode_schema::{
-        DeserializeSchema, SchemaDecodeCodec, SchemaDocument, SchemaView, ValidatedSchemaView,
+        descend_validation_depth, DeserializeSchema, SchemaDecodeCodec, SchemaDocument,
+        SchemaView, ValidatedSchemaView,
     },
 };
 
@@ -21,6 +26,7 @@ use super::{
 pub struct JsonDocument {
     interner: Interner,
     map: HashMap<JsonKey, JsonValue>,
+    object_keys: HashMap<u32, Box<[Intern]>>,
     root: JsonValue,
 }
 
@@ -30,15 +36,28 @@ struct JsonKey {
     key: u32,
 }
 
+/// The maximum number of nested objects and arrays [`JsonDocument::parse`] will tolerate before
+/// bailing. This keeps adversarially nested documents from exhausting the stack.
+pub const DEFAULT_MAX_DEPTH: u32 = 128;
+
 impl JsonDocument {
     pub fn parse(text: &str) -> anyhow::Result<Self> {
+        Self::parse_with_max_depth(text, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Parses a document, bailing if objects and arrays are nested more than `max_depth` levels
+    /// deep.
+    pub fn parse_with_max_depth(text: &str, max_depth: u32) -> anyhow::Result<Self> {
         // N.B. this check is necessary to allow us to use u32s everywhere.
         assert!(text.len() <= u32::MAX as usize);
 
         let mut delegate = JsonDocumentParser {
             interner: Interner::default(),
             map: HashMap::default(),
+            object_keys: HashMap::default(),
             gen: 0,
+            depth: 0,
+            max_depth,
         };
 
         let root = Parser::parse_json(text, &mut delegate)?;
@@ -46,6 +65,7 @@ impl JsonDocument {
         Ok(Self {
             interner: delegate.interner,
             map: delegate.map,
+            object_keys: delegate.object_keys,
             root,
         })
     }
@@ -68,6 +88,23 @@ impl JsonDocument {
             .copied()
     }
 
+    /// Iterates over the fields of an object in the order in which they first appeared in the
+    /// source text. If a key was repeated, its last value is yielded in place of its first.
+    pub fn object_entries(&self, obj: JsonObject) -> impl Iterator<Item = (&str, JsonValue)> + '_ {
+        self.object_keys
+            .get(&obj.0)
+            .map_or(&[][..], |keys| &keys[..])
+            .iter()
+            .map(move |&key| {
+                let value = self.map[&JsonKey {
+                    parent: obj.0,
+                    key: key.id(),
+                }];
+
+                (self.interner.decode(key), value)
+            })
+    }
+
     pub fn array_element(&self, obj: JsonArray, index: u32) -> Option<JsonValue> {
         self.map
             .get(&JsonKey {
@@ -124,8 +161,11 @@ pub enum JsonNumber {
 impl JsonNumber {
     pub fn as_uint(self) -> anyhow::Result<u64> {
         match self {
-            // TODO: Check this logic
-            JsonNumber::F64(v) => Ok(v as u64),
+            // 2^64 is exactly representable as a float whereas `u64::MAX` is not.
+            JsonNumber::F64(v) => {
+                Self::ensure_integral(v, 0.0..18446744073709551616.0)?;
+                Ok(v as u64)
+            }
             JsonNumber::U64(v) => Ok(v),
             JsonNumber::I64(v) => Ok(u64::try_from(v)?),
         }
@@ -133,13 +173,25 @@ impl JsonNumber {
 
     pub fn as_int(self) -> anyhow::Result<i64> {
         match self {
-            // TODO: Check this logic
-            JsonNumber::F64(v) => Ok(v as i64),
+            // -2^63 and 2^63 are exactly representable as floats whereas `i64::MAX` is not.
+            JsonNumber::F64(v) => {
+                Self::ensure_integral(v, -9223372036854775808.0..9223372036854775808.0)?;
+                Ok(v as i64)
+            }
             JsonNumber::U64(v) => Ok(i64::try_from(v)?),
             JsonNumber::I64(v) => Ok(v),
         }
     }
 
+    fn ensure_integral(v: f64, range: Range<f64>) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            v.fract() == 0.0 && range.contains(&v),
+            "Expected an integer in the range {range:?}, got {v}"
+        );
+
+        Ok(())
+    }
+
     pub fn as_float(self) -> anyhow::Result<f64> {
         match self {
             JsonNumber::F64(v) => Ok(v),
@@ -201,6 +253,12 @@ impl<'a> JsonObjectView<'a> {
             .object_field(self.handle, key)
             .map(|handle| JsonValueView::wrap(self.document, handle))
     }
+
+    pub fn iter(self) -> impl Iterator<Item = (&'a str, JsonValueView<'a>)> + 'a {
+        self.document
+            .object_entries(self.handle)
+            .map(move |(key, value)| (key, JsonValueView::wrap(self.document, value)))
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -216,12 +274,35 @@ impl<'a> JsonArrayView<'a> {
             .map(|handle| JsonValueView::wrap(self.document, handle))
     }
 
+    /// Like [`get`](Self::get) but distinguishes an index beyond the array's length, which yields
+    /// `Ok(None)`, from an index within it whose element is missing, which yields an error. The
+    /// parser never produces arrays with gaps so the latter indicates a corrupted document.
+    pub fn get_checked(self, idx: u32) -> anyhow::Result<Option<JsonValueView<'a>>> {
+        if idx >= self.len() {
+            return Ok(None);
+        }
+
+        match self.get(idx) {
+            Some(value) => Ok(Some(value)),
+            None => anyhow::bail!(
+                "JSON array of length {} is missing its element at index {idx}.",
+                self.len(),
+            ),
+        }
+    }
+
     pub fn len(self) -> u32 {
         self.handle.len()
     }
 
-    pub fn iter(self) -> impl Iterator<Item = JsonValueView<'a>> + 'a {
-        (0..self.len()).map_while(move |i| self.get(i))
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over exactly [`len`](Self::len) elements, yielding errors for missing ones rather
+    /// than stopping early. Elements can only be missing if the document is corrupted.
+    pub fn iter(self) -> impl Iterator<Item = anyhow::Result<JsonValueView<'a>>> + 'a {
+        (0..self.len()).map(move |i| self.get_checked(i).map(Option::unwrap))
     }
 }
 
@@ -240,19 +321,124 @@ impl Deref for JsonStringView<'_> {
     }
 }
 
+// Formatting
+/// Formats the value as compact JSON. Object fields are written in the order they appeared in the
+/// source text.
+impl fmt::Display for JsonValueView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValueView::Object(obj) => obj.fmt(f),
+            JsonValueView::Array(arr) => arr.fmt(f),
+            JsonValueView::String(str) => str.fmt(f),
+            JsonValueView::Number(JsonNumber::F64(v)) => write!(f, "{v:?}"),
+            JsonValueView::Number(JsonNumber::U64(v)) => write!(f, "{v}"),
+            JsonValueView::Number(JsonNumber::I64(v)) => write!(f, "{v}"),
+            JsonValueView::Boolean(v) => write!(f, "{v}"),
+            JsonValueView::Null => f.write_str("null"),
+        }
+    }
+}
+
+impl fmt::Display for JsonObjectView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+
+            write_json_string(f, key)?;
+            write!(f, ":{value}")?;
+        }
+
+        f.write_str("}")
+    }
+}
+
+impl fmt::Display for JsonArrayView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+
+        for (i, value) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+
+            // Formatting errors can't carry a message and make `to_string` panic so elements
+            // missing from a corrupted document are rendered as `null` instead.
+            match value {
+                Ok(value) => write!(f, "{value}")?,
+                Err(_) => f.write_str("null")?,
+            }
+        }
+
+        f.write_str("]")
+    }
+}
+
+impl fmt::Display for JsonStringView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_json_string(f, self.text)
+    }
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+
+    for char in text.chars() {
+        match char {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\u{8}' => f.write_str("\\b")?,
+            '\u{c}' => f.write_str("\\f")?,
+            char if (char as u32) < 0x20 => write!(f, "\\u{:04x}", char as u32)?,
+            char => write!(f, "{char}")?,
+        }
+    }
+
+    f.write_str("\"")
+}
+
 // === JsonDocumentParser === //
 
 #[derive(Debug)]
 struct JsonDocumentParser {
     interner: Interner,
     map: HashMap<JsonKey, JsonValue>,
+    object_keys: HashMap<u32, Box<[Intern]>>,
     gen: u32,
+    depth: u32,
+    max_depth: u32,
+}
+
+impl JsonDocumentParser {
+    fn begin_container(&mut self) -> anyhow::Result<ObjectOrArrayBuilder> {
+        anyhow::ensure!(
+            self.depth < self.max_depth,
+            "JSON document is nested more than {} level(s) deep.",
+            self.max_depth,
+        );
+
+        self.depth += 1;
+        self.gen += 1;
+
+        Ok(ObjectOrArrayBuilder {
+            id: self.gen,
+            len: 0,
+            keys: Vec::new(),
+        })
+    }
 }
 
 #[derive(Debug)]
 struct ObjectOrArrayBuilder {
     id: u32,
     len: u32,
+    keys: Vec<Intern>,
 }
 
 impl ParseDelegate<'_> for &'_ mut JsonDocumentParser {
@@ -293,12 +479,7 @@ impl ParseDelegate<'_> for &'_ mut JsonDocumentParser {
     }
 
     fn begin_object(&mut self) -> Result<Self::Object, Self::Error> {
-        self.gen += 1;
-
-        Ok(ObjectOrArrayBuilder {
-            id: self.gen,
-            len: 0,
-        })
+        self.begin_container()
     }
 
     fn object_key(
@@ -315,13 +496,18 @@ impl ParseDelegate<'_> for &'_ mut JsonDocumentParser {
         key: Self::Key,
         value: Self::Value,
     ) -> Result<(), Self::Error> {
-        self.map.insert(
+        let prev = self.map.insert(
             JsonKey {
                 parent: object.id,
                 key: key.id(),
             },
             value,
         );
+
+        if prev.is_none() {
+            object.keys.push(key);
+        }
+
         object.len += 1;
 
         Ok(())
@@ -332,16 +518,18 @@ impl ParseDelegate<'_> for &'_ mut JsonDocumentParser {
     }
 
     fn end_object(&mut self, object: Self::Object) -> Result<Self::Value, Self::Error> {
+        self.depth -= 1;
+
+        if !object.keys.is_empty() {
+            self.object_keys
+                .insert(object.id, object.keys.into_boxed_slice());
+        }
+
         Ok(JsonValue::Object(JsonObject(object.id)))
     }
 
     fn begin_array(&mut self) -> Result<Self::Array, Self::Error> {
-        self.gen += 1;
-
-        Ok(ObjectOrArrayBuilder {
-            id: self.gen,
-            len: 0,
-        })
+        self.begin_container()
     }
 
     fn array_value(
@@ -366,6 +554,8 @@ impl ParseDelegate<'_> for &'_ mut JsonDocumentParser {
     }
 
     fn end_array(&mut self, array: Self::Array) -> Result<Self::Value, Self::Error> {
+        self.depth -= 1;
+
         Ok(JsonValue::Array(JsonArray {
             id: array.id,
             len: array.len,
@@ -418,9 +608,9 @@ impl SchemaDocument for JsonDocument {
 }
 
 // Option
-impl<T> DeserializeSchema<JsonSchema, ()> for Option<T>
+impl<A, T> DeserializeSchema<JsonSchema, A> for Option<T>
 where
-    T: DeserializeSchema<JsonSchema, ()>,
+    T: DeserializeSchema<JsonSchema, A>,
 {
     type Shortcut = Option<T::Shortcut>;
     type View<'a> = Option<T::View<'a>>;
@@ -439,13 +629,13 @@ where
     fn view_shortcut<'a>(
         document: &'a <JsonSchema as SchemaDecodeCodec>::Document,
         shortcut: Self::Shortcut,
-        args: (),
+        args: A,
     ) -> Self::View<'a> {
         shortcut.map(|shortcut| T::view_shortcut(document, shortcut, args))
     }
 }
 
-impl<V: SchemaView<JsonSchema, ()>> SchemaView<JsonSchema, ()> for Option<V> {
+impl<A, V: SchemaView<JsonSchema, A>> SchemaView<JsonSchema, A> for Option<V> {
     type Reified = Option<V::Reified>;
     type Shortcut = Option<V::Shortcut>;
     type Validated = Option<V::Validated>;
@@ -454,9 +644,9 @@ impl<V: SchemaView<JsonSchema, ()>> SchemaView<JsonSchema, ()> for Option<V> {
         self.map(|v| v.assume_valid())
     }
 
-    fn validate_deep(&self) -> anyhow::Result<()> {
+    fn validate_deep_bounded(&self, remaining_depth: u32) -> anyhow::Result<()> {
         if let Some(inner) = self {
-            inner.validate_deep()?;
+            inner.validate_deep_bounded(remaining_depth)?;
         }
         Ok(())
     }
@@ -474,7 +664,10 @@ impl<V: SchemaView<JsonSchema, ()>> SchemaView<JsonSchema, ()> for Option<V> {
     }
 }
 
-impl<V: ValidatedSchemaView<JsonSchema, ()>> ValidatedSchemaView<JsonSchema, ()> for Option<V> {
+impl<A, V> ValidatedSchemaView<JsonSchema, A> for Option<V>
+where
+    V: ValidatedSchemaView<JsonSchema, A>,
+{
     type Reified = Option<V::Reified>;
     type Shortcut = Option<V::Shortcut>;
     type RawView = Option<V::RawView>;
@@ -545,18 +738,32 @@ impl<'a, T> ArrayView<'a, T>
 where
     T: DeserializeSchema<JsonSchema, ()>,
 {
+    /// Fetches the element at index `i`, yielding `None` if the index is beyond the array's length
+    /// and an error if the element is missing or has the wrong type.
     pub fn get(self, i: u32) -> Option<anyhow::Result<T::View<'a>>> {
-        self.view
-            .get(i)
-            .map(|object| T::view_object(self.view.document, Some(object.unwrap()), ()))
+        match self.view.get_checked(i) {
+            Ok(Some(object)) => Some(T::view_object(
+                self.view.document,
+                Some(object.unwrap()),
+                (),
+            )),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
     }
 
     pub fn len(self) -> u32 {
         self.view.len()
     }
 
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over exactly [`len`](Self::len) elements, yielding errors for missing ones rather
+    /// than stopping early.
     pub fn iter(self) -> impl Iterator<Item = anyhow::Result<T::View<'a>>> {
-        (0..self.len()).map_while(move |i| self.get(i))
+        (0..self.len()).map(move |i| self.get(i).unwrap())
     }
 }
 
@@ -572,9 +779,11 @@ where
         ValidatedArrayView(self)
     }
 
-    fn validate_deep(&self) -> anyhow::Result<()> {
+    fn validate_deep_bounded(&self, remaining_depth: u32) -> anyhow::Result<()> {
+        let remaining_depth = descend_validation_depth(remaining_depth)?;
+
         for elem in self.iter() {
-            elem?.validate_deep()?;
+            elem?.validate_deep_bounded(remaining_depth)?;
         }
         Ok(())
     }
@@ -608,6 +817,10 @@ where
         self.0.len()
     }
 
+    pub fn is_empty(self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn iter(self) -> impl Iterator<Item = T::View<'a>> {
         self.0.iter().map(|v| v.unwrap())
     }
@@ -670,7 +883,7 @@ macro_rules! impl_numerics {
                 self
             }
 
-            fn validate_deep(&self) -> anyhow::Result<()> {
+            fn validate_deep_bounded(&self, _remaining_depth: u32) -> anyhow::Result<()> {
                 Ok(())
             }
 
@@ -741,7 +954,7 @@ impl SchemaView<JsonSchema, ()> for bool {
         self
     }
 
-    fn validate_deep(&self) -> anyhow::Result<()> {
+    fn validate_deep_bounded(&self, _remaining_depth: u32) -> anyhow::Result<()> {
         Ok(())
     }
 
@@ -773,10 +986,46 @@ impl ValidatedSchemaView<JsonSchema, ()> for bool {
 }
 
 // String
+/// A view of a JSON string. Strings viewed with a `u32` argument are limited to that many UTF-16
+/// code units, which is checked during validation.
 #[derive(Debug, Copy, Clone)]
 pub struct StringView<'a> {
     intern: Intern,
     text: &'a str,
+    max_len: Option<u32>,
+}
+
+impl StringView<'_> {
+    fn validate_len(&self) -> anyhow::Result<()> {
+        let Some(max_len) = self.max_len else {
+            return Ok(());
+        };
+
+        let len = self.text.encode_utf16().count();
+        anyhow::ensure!(
+            len <= max_len as usize,
+            "String {:?} has a max length of {max_len} UTF-16 code unit(s) but was {len} code \
+             unit(s) long.",
+            self.text,
+        );
+
+        Ok(())
+    }
+}
+
+fn make_string_shortcut(object: Option<JsonValue>) -> anyhow::Result<Intern> {
+    match object {
+        Some(JsonValue::String(intern)) => Ok(intern),
+        value @ _ => anyhow::bail!("Expected string, got {value:?}."),
+    }
+}
+
+fn view_string(document: &JsonDocument, shortcut: Intern, max_len: Option<u32>) -> StringView<'_> {
+    StringView {
+        intern: shortcut,
+        text: document.string_value(shortcut),
+        max_len,
+    }
 }
 
 impl DeserializeSchema<JsonSchema, ()> for String {
@@ -788,10 +1037,7 @@ impl DeserializeSchema<JsonSchema, ()> for String {
         _document: &JsonDocument,
         object: Option<JsonValue>,
     ) -> anyhow::Result<Self::Shortcut> {
-        match object {
-            Some(JsonValue::String(intern)) => Ok(intern),
-            value @ _ => anyhow::bail!("Expected string, got {value:?}."),
-        }
+        make_string_shortcut(object)
     }
 
     fn view_shortcut<'a>(
@@ -799,53 +1045,78 @@ impl DeserializeSchema<JsonSchema, ()> for String {
         shortcut: Intern,
         _args: (),
     ) -> Self::View<'a> {
-        StringView {
-            intern: shortcut,
-            text: document.string_value(shortcut),
-        }
+        view_string(document, shortcut, None)
     }
 }
 
-impl SchemaView<JsonSchema, ()> for StringView<'_> {
-    type Reified = String;
+impl DeserializeSchema<JsonSchema, u32> for String {
     type Shortcut = Intern;
-    type Validated = Self;
+    type View<'a> = StringView<'a>;
+    type ValidatedView<'a> = StringView<'a>;
 
-    fn assume_valid(self) -> Self::Validated {
-        self
+    fn make_shortcut(
+        _document: &JsonDocument,
+        object: Option<JsonValue>,
+    ) -> anyhow::Result<Self::Shortcut> {
+        make_string_shortcut(object)
     }
 
-    fn validate_deep(&self) -> anyhow::Result<()> {
-        Ok(())
+    fn view_shortcut<'a>(
+        document: &'a JsonDocument,
+        shortcut: Intern,
+        max_len: u32,
+    ) -> Self::View<'a> {
+        view_string(document, shortcut, Some(max_len))
     }
+}
 
-    fn as_shortcut(&self) -> Self::Shortcut {
-        self.intern
-    }
+macro_rules! impl_string_view {
+    ($($args:ty),*) => {$(
+        impl SchemaView<JsonSchema, $args> for StringView<'_> {
+            type Reified = String;
+            type Shortcut = Intern;
+            type Validated = Self;
 
-    fn try_reify(&self) -> anyhow::Result<Self::Reified> {
-        Ok(self.text.to_string())
-    }
-}
+            fn assume_valid(self) -> Self::Validated {
+                self
+            }
 
-impl ValidatedSchemaView<JsonSchema, ()> for StringView<'_> {
-    type Reified = String;
-    type Shortcut = Intern;
-    type RawView = Self;
+            fn validate_deep_bounded(&self, _remaining_depth: u32) -> anyhow::Result<()> {
+                self.validate_len()
+            }
 
-    fn unwrap_validation(self) -> Self::RawView {
-        self
-    }
+            fn as_shortcut(&self) -> Self::Shortcut {
+                self.intern
+            }
 
-    fn as_shortcut_validated(&self) -> Self::Shortcut {
-        self.intern
-    }
+            fn try_reify(&self) -> anyhow::Result<Self::Reified> {
+                self.validate_len()?;
+                Ok(self.text.to_string())
+            }
+        }
 
-    fn reify(&self) -> Self::Reified {
-        self.text.to_string()
-    }
+        impl ValidatedSchemaView<JsonSchema, $args> for StringView<'_> {
+            type Reified = String;
+            type Shortcut = Intern;
+            type RawView = Self;
+
+            fn unwrap_validation(self) -> Self::RawView {
+                self
+            }
+
+            fn as_shortcut_validated(&self) -> Self::Shortcut {
+                self.intern
+            }
+
+            fn reify(&self) -> Self::Reified {
+                self.text.to_string()
+            }
+        }
+    )*};
 }
 
+impl_string_view!((), u32);
+
 impl Deref for StringView<'_> {
     type Target = str;
 
@@ -907,9 +1178,11 @@ where
         self.map_either(SchemaView::assume_valid, SchemaView::assume_valid)
     }
 
-    fn validate_deep(&self) -> anyhow::Result<()> {
-        self.as_ref()
-            .either(SchemaView::validate_deep, SchemaView::validate_deep)
+    fn validate_deep_bounded(&self, remaining_depth: u32) -> anyhow::Result<()> {
+        self.as_ref().either(
+            |left| left.validate_deep_bounded(remaining_depth),
+            |right| right.validate_deep_bounded(remaining_depth),
+        )
     }
 
     fn as_shortcut(&self) -> Self::Shortcut {
@@ -953,3 +1226,117 @@ where
             .map_either(ValidatedSchemaView::reify, ValidatedSchemaView::reify)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_conversions_reject_non_integers_and_out_of_range_values() {
+        for v in [
+            1.5,
+            -1.0,
+            f64::NAN,
+            f64::INFINITY,
+            1e30,
+            18446744073709551616.0,
+        ] {
+            assert!(JsonNumber::F64(v).as_uint().is_err(), "{v} as uint");
+        }
+
+        for v in [
+            1.5,
+            f64::NAN,
+            f64::NEG_INFINITY,
+            1e30,
+            -1e30,
+            9223372036854775808.0,
+        ] {
+            assert!(JsonNumber::F64(v).as_int().is_err(), "{v} as int");
+        }
+
+        assert_eq!(JsonNumber::F64(3.0).as_uint().unwrap(), 3);
+        assert_eq!(JsonNumber::F64(-0.0).as_uint().unwrap(), 0);
+        assert_eq!(JsonNumber::F64(-3.0).as_int().unwrap(), -3);
+        assert_eq!(
+            JsonNumber::F64(-9223372036854775808.0).as_int().unwrap(),
+            i64::MIN
+        );
+    }
+
+    #[test]
+    fn integer_conversions_are_checked() {
+        assert_eq!(JsonNumber::U64(u64::MAX).as_uint().unwrap(), u64::MAX);
+        assert!(JsonNumber::U64(u64::MAX).as_int().is_err());
+        assert!(JsonNumber::I64(-1).as_uint().is_err());
+        assert_eq!(JsonNumber::I64(i64::MIN).as_int().unwrap(), i64::MIN);
+        assert_eq!(JsonNumber::I64(5).as_uint().unwrap(), 5);
+    }
+
+    #[test]
+    fn display_round_trips_through_the_parser() {
+        for text in [
+            r#"{"x":[true,null,"a\"b"]}"#,
+            r#"{"a":{"b":[]},"c":{},"d":"\\\n\u0001"}"#,
+            r#"[1,-2,2.5,"é"]"#,
+        ] {
+            let document = JsonDocument::parse(text).unwrap();
+            let view = JsonValueView::wrap(&document, document.root());
+            assert_eq!(view.to_string(), text);
+        }
+    }
+
+    fn nested_arrays(depth: usize) -> String {
+        format!("{}{}", "[".repeat(depth), "]".repeat(depth))
+    }
+
+    #[test]
+    fn parse_rejects_documents_nested_past_the_max_depth() {
+        JsonDocument::parse(&nested_arrays(100)).unwrap();
+        JsonDocument::parse(&nested_arrays(DEFAULT_MAX_DEPTH as usize)).unwrap();
+
+        let err = JsonDocument::parse(&nested_arrays(200)).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("more than 128 level(s)"),
+            "{err:#}"
+        );
+
+        // Objects count towards the same limit as arrays.
+        let mixed = format!("{}1{}", r#"[{"a":"#.repeat(3), "}]".repeat(3));
+        JsonDocument::parse_with_max_depth(&mixed, 6).unwrap();
+        assert!(JsonDocument::parse_with_max_depth(&mixed, 5).is_err());
+    }
+
+    #[test]
+    fn arrays_with_gaps_yield_errors_instead_of_truncating() {
+        let mut document = JsonDocument::parse("[1,[2],3]").unwrap();
+        let JsonValue::Array(array) = document.root() else {
+            panic!("expected an array");
+        };
+
+        // The parser never leaves gaps so we have to punch one out ourselves.
+        document.map.remove(&JsonKey {
+            parent: array.id,
+            key: 1,
+        });
+
+        let view = JsonArrayView {
+            document: &document,
+            handle: array,
+        };
+
+        assert!(view.get_checked(0).unwrap().is_some());
+        let err = view.get_checked(1).unwrap_err();
+        assert!(
+            err.to_string().contains("missing its element at index 1"),
+            "{err:#}"
+        );
+        assert!(view.get_checked(3).unwrap().is_none());
+
+        let elems = view.iter().collect::<Vec<_>>();
+        assert_eq!(elems.len(), 3);
+        assert!(elems[0].is_ok() && elems[1].is_err() && elems[2].is_ok());
+
+        assert_eq!(view.to_string(), "[1,null,3]");
+    }
+}
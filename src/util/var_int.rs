@@ -1,7 +1,7 @@
 use std::io;
 
 use super::{
-    bits::{i32_from_u32_2c, i32_to_u32_2c, StaticBitSet},
+    bits::{i32_from_u32_2c, i32_to_u32_2c, i64_from_u64_2c, i64_to_u64_2c, StaticBitSet},
     proto::byte_stream::ByteCursor,
 };
 
@@ -48,3 +48,47 @@ pub fn encode_var_u32(stream: &mut impl io::Write, value: i32) -> io::Result<()>
 
     Ok(())
 }
+
+pub fn decode_var_i64_streaming(cursor: &mut ByteCursor) -> anyhow::Result<Option<i64>> {
+    let mut accum = 0u64;
+    let mut shift = 0;
+
+    loop {
+        let Some(byte) = cursor.read() else { return Ok(None) };
+        accum |= ((byte & !u8::MSB) as u64) << shift;
+
+        if byte & u8::MSB == 0 {
+            break;
+        }
+
+        shift += 7;
+
+        if shift >= 64 {
+            anyhow::bail!(
+                "VarLong is too long to fit an i64 (location: {}).",
+                cursor.format_location(),
+            );
+        }
+    }
+
+    let accum = i64_from_u64_2c(accum);
+    Ok(Some(accum))
+}
+
+pub fn encode_var_u64(stream: &mut impl io::Write, value: i64) -> io::Result<()> {
+    let mut accum = i64_to_u64_2c(value);
+
+    loop {
+        let byte = accum & !u8::MSB as u64;
+        accum >>= 7;
+
+        if accum > 0 {
+            stream.write_all(&[byte as u8 | u8::MSB])?;
+        } else {
+            stream.write_all(&[byte as u8])?;
+            break;
+        }
+    }
+
+    Ok(())
+}